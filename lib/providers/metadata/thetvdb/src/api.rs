@@ -0,0 +1,87 @@
+use resources::RemoteId;
+
+pub const THETVDB_BASE_URL: &'static str = "https://api4.thetvdb.com/v4";
+
+/// The artwork types TheTVDB uses for the images that are of interest.
+pub const ARTWORK_SERIES_POSTER: u64 = 2;
+pub const ARTWORK_SERIES_BACKGROUND: u64 = 3;
+pub const ARTWORK_SEASON_POSTER: u64 = 7;
+
+/// The remote id type of an IMDb id.
+pub const REMOTE_ID_IMDB: u64 = 2;
+
+/// Every response wraps its data along with links to other pages, if the
+/// data is paginated.
+#[derive(Debug, Deserialize)]
+pub struct Response<T> {
+    pub status: String,
+    pub data: T,
+    pub links: Option<Links>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Links {
+    pub prev: Option<String>,
+    #[serde(rename = "self")]
+    pub _self: Option<String>,
+    pub next: Option<String>,
+    pub total_items: Option<u64>,
+    pub page_size: Option<u64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LoginRequest<'a> {
+    pub apikey: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pin: Option<&'a str>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LoginData {
+    pub token: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SearchResult {
+    pub tvdb_id: String,
+    pub name: String,
+    #[serde(rename = "type")]
+    pub _type: Option<String>,
+    pub slug: Option<String>,
+    pub overview: Option<String>,
+    pub image_url: Option<String>,
+    pub first_air_time: Option<String>,
+    pub year: Option<String>,
+    pub network: Option<String>,
+    /// Such as "Continuing" or "Ended".
+    pub status: Option<String>,
+    #[serde(default)]
+    pub genres: Vec<String>,
+    #[serde(default)]
+    pub remote_ids: Vec<RemoteId>,
+}
+
+/// A match of a search by an id in another database.
+#[derive(Debug, Deserialize)]
+pub struct RemoteIdResult {
+    pub series: Option<::resources::Series>,
+}
+
+/// The way episodes are split into seasons. Almost every show only has
+/// `Official` seasons, but some also have DVD or absolute orderings.
+#[derive(Clone, Copy, Debug)]
+pub enum SeasonType {
+    Official,
+    DVD,
+    Absolute,
+}
+
+impl SeasonType {
+    pub fn as_path_segment(&self) -> &'static str {
+        match *self {
+            SeasonType::Official => "official",
+            SeasonType::DVD => "dvd",
+            SeasonType::Absolute => "absolute",
+        }
+    }
+}
@@ -0,0 +1,242 @@
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use api::*;
+use error::Error;
+use resources::*;
+
+use futures::future::{self, Loop};
+use futures::{Future, Stream};
+use hyper::body::Body;
+use hyper::client::HttpConnector;
+use hyper::{self, Request};
+#[cfg(feature = "tls")]
+use hyper_tls::HttpsConnector;
+use serde::de::DeserializeOwned;
+use serde_json;
+use url::form_urlencoded::Serializer;
+
+#[cfg(feature = "tls")]
+pub type HTTPClient = hyper::Client<HttpsConnector<HttpConnector>, Body>;
+
+#[cfg(not(feature = "tls"))]
+pub type HTTPClient = hyper::Client<HttpConnector, Body>;
+
+pub type QueryFuture<R> = Box<Future<Item = R, Error = Error> + Send>;
+
+/// Tokens are valid for a month. They are replaced a little before that so
+/// that a request never races against the expiry.
+const TOKEN_LIFETIME_SECS: u64 = 25 * 24 * 60 * 60;
+
+struct Token {
+    value: String,
+    obtained: Instant,
+}
+
+#[derive(Clone)]
+pub struct Client {
+    http_client: HTTPClient,
+    base_url: String,
+    apikey: String,
+    pin: Option<String>,
+    token: Arc<Mutex<Option<Token>>>,
+}
+
+impl Client {
+    #[cfg(not(feature = "tls"))]
+    pub fn new(apikey: &str, pin: Option<&str>) -> Client {
+        Client::with_http_client(hyper::Client::new(), apikey, pin)
+    }
+
+    #[cfg(feature = "tls")]
+    pub fn new(apikey: &str, pin: Option<&str>) -> Result<Client, Error> {
+        let connector = HttpsConnector::new(4)?;
+        let http_client = hyper::Client::builder().build(connector);
+        Ok(Client::with_http_client(http_client, apikey, pin))
+    }
+
+    fn with_http_client(http_client: HTTPClient, apikey: &str, pin: Option<&str>) -> Client {
+        Client {
+            http_client,
+            base_url: THETVDB_BASE_URL.to_string(),
+            apikey: apikey.to_string(),
+            pin: pin.map(|pin| pin.to_string()),
+            token: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    /// Sends requests to another server, such as a local mock of the API.
+    pub fn with_base_url(mut self, base_url: &str) -> Client {
+        self.base_url = base_url.trim_right_matches('/').to_string();
+        self
+    }
+
+    /// Exchanges the API key for a bearer token, which is used by every
+    /// following request.
+    pub fn login(&self) -> Result<QueryFuture<String>, Error> {
+        let body = serde_json::to_string(&LoginRequest {
+            apikey: &self.apikey,
+            pin: self.pin.as_ref().map(|pin| pin.as_str()),
+        })?;
+        let req = Request::post([&self.base_url, "/login"].join(""))
+            .header("Content-Type", "application/json")
+            .body(Body::from(body))?;
+        let token = self.token.clone();
+
+        Ok(Box::new(self.send::<Response<LoginData>>(req).then(
+            move |res| match res {
+                Ok(res) => {
+                    *token.lock().unwrap() = Some(Token {
+                        value: res.data.token.clone(),
+                        obtained: Instant::now(),
+                    });
+                    Ok(res.data.token)
+                }
+                Err(Error::Unauthorized) => Err(Error::LoginFailed),
+                Err(e) => Err(e),
+            },
+        )))
+    }
+
+    pub fn search_series(&self, query: &str) -> Result<QueryFuture<Vec<SearchResult>>, Error> {
+        let params = vec![("query", query), ("type", "series")];
+        Ok(Box::new(self.get("/search", params)?.map(|res| res.data)))
+    }
+
+    /// Finds series by their id in another database, such as an IMDb id.
+    pub fn search_remote_id(&self, id: &str) -> Result<QueryFuture<Vec<RemoteIdResult>>, Error> {
+        let path = &["/search/remoteid/", id].join("");
+        Ok(Box::new(self.get(path, vec![])?.map(|res| res.data)))
+    }
+
+    pub fn series_extended(&self, id: &str) -> Result<QueryFuture<SeriesExtended>, Error> {
+        let path = &["/series/", id, "/extended"].join("");
+        Ok(Box::new(self.get(path, vec![])?.map(|res| res.data)))
+    }
+
+    pub fn season_extended(&self, id: &str) -> Result<QueryFuture<SeasonExtended>, Error> {
+        let path = &["/seasons/", id, "/extended"].join("");
+        Ok(Box::new(self.get(path, vec![])?.map(|res| res.data)))
+    }
+
+    /// Loads all of a series' episodes in `season_type` order, following the
+    /// pagination until the last page.
+    pub fn series_episodes(
+        &self,
+        id: &str,
+        season_type: SeasonType,
+    ) -> Result<QueryFuture<Vec<Episode>>, Error> {
+        let path = ["/series/", id, "/episodes/", season_type.as_path_segment()].join("");
+        let client = self.clone();
+
+        Ok(Box::new(future::loop_fn(
+            (0, Vec::new()),
+            move |(page, mut episodes): (u64, Vec<Episode>)| {
+                let page_param = page.to_string();
+                future::result(client.get::<SeriesEpisodes>(&path, vec![("page", &page_param)]))
+                    .flatten()
+                    .map(move |res| {
+                        episodes.extend(res.data.episodes);
+                        match res.links.and_then(|links| links.next) {
+                            Some(_) => Loop::Continue((page + 1, episodes)),
+                            None => Loop::Break(episodes),
+                        }
+                    })
+            },
+        )))
+    }
+
+    /// Sends an authorized GET request. A rejected token is replaced by
+    /// logging in again once, since tokens can be revoked before they expire.
+    fn get<R>(
+        &self,
+        path: &str,
+        query_params: Vec<(&str, &str)>,
+    ) -> Result<QueryFuture<Response<R>>, Error>
+    where
+        R: DeserializeOwned + Send + 'static,
+    {
+        let url = create_url(&self.base_url, path, query_params);
+        let token: QueryFuture<String> = match self.current_token() {
+            Some(token) => Box::new(future::ok(token)),
+            None => self.login()?,
+        };
+        let client = self.clone();
+
+        Ok(Box::new(token.and_then(move |token| {
+            let retry_client = client.clone();
+            let retry_url = url.clone();
+
+            future::result(create_request(&url, &token))
+                .and_then(move |req| client.send(req))
+                .or_else(move |e| -> QueryFuture<Response<R>> {
+                    match e {
+                        Error::Unauthorized => Box::new(
+                            future::result(retry_client.login())
+                                .flatten()
+                                .and_then(move |token| create_request(&retry_url, &token))
+                                .and_then(move |req| retry_client.send(req)),
+                        ),
+                        e => Box::new(future::err(e)),
+                    }
+                })
+        })))
+    }
+
+    fn current_token(&self) -> Option<String> {
+        let lifetime = Duration::from_secs(TOKEN_LIFETIME_SECS);
+
+        match *self.token.lock().unwrap() {
+            Some(ref token) if token.obtained.elapsed() < lifetime => Some(token.value.clone()),
+            _ => None,
+        }
+    }
+
+    fn send<R>(&self, req: Request<Body>) -> QueryFuture<R>
+    where
+        R: DeserializeOwned + Send + 'static,
+    {
+        Box::new(
+            self.http_client
+                .request(req)
+                .map_err(Error::from)
+                .and_then(|res| {
+                    let status = res.status();
+                    res.into_body()
+                        .concat2()
+                        .map_err(Error::from)
+                        .and_then(move |body| {
+                            if status.is_success() {
+                                return serde_json::from_slice(&body).map_err(Error::from);
+                            }
+
+                            Err(match status.as_u16() {
+                                401 => Error::Unauthorized,
+                                404 => Error::NotFound,
+                                status => Error::UnexpectedResponse(status),
+                            })
+                        })
+                }),
+        )
+    }
+}
+
+fn create_url(base_url: &str, path: &str, query_params: Vec<(&str, &str)>) -> String {
+    if query_params.is_empty() {
+        return [base_url, path].join("");
+    }
+
+    let mut serializer = Serializer::new(String::new());
+    for (key, val) in query_params {
+        serializer.append_pair(key, val);
+    }
+
+    [base_url, path, "?", &serializer.finish()].join("")
+}
+
+fn create_request(url: &str, token: &str) -> Result<Request<Body>, Error> {
+    Request::get(url)
+        .header("Authorization", ["Bearer ", token].join(""))
+        .body(Body::empty())
+        .map_err(Error::from)
+}
@@ -0,0 +1,45 @@
+use http;
+use hyper;
+#[cfg(feature = "tls")]
+use hyper_tls;
+use serde_json;
+
+#[derive(Debug)]
+pub enum Error {
+    DeserializationError(serde_json::Error),
+    HTTPError(http::Error),
+    HTTPClientError(hyper::Error),
+    /// The API key or PIN was rejected when logging in.
+    LoginFailed,
+    NotFound,
+    #[cfg(feature = "tls")]
+    TLSError(hyper_tls::Error),
+    /// The token was rejected even after logging in again.
+    Unauthorized,
+    UnexpectedResponse(u16),
+}
+
+impl From<http::Error> for Error {
+    fn from(error: http::Error) -> Self {
+        Error::HTTPError(error)
+    }
+}
+
+impl From<hyper::Error> for Error {
+    fn from(error: hyper::Error) -> Self {
+        Error::HTTPClientError(error)
+    }
+}
+
+#[cfg(feature = "tls")]
+impl From<hyper_tls::Error> for Error {
+    fn from(error: hyper_tls::Error) -> Self {
+        Error::TLSError(error)
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(error: serde_json::Error) -> Self {
+        Error::DeserializationError(error)
+    }
+}
@@ -0,0 +1,18 @@
+//! A client for version 4 of TheTVDB's API.
+
+extern crate futures;
+extern crate http;
+extern crate hyper;
+#[cfg(feature = "tls")]
+extern crate hyper_tls;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+extern crate serde_json;
+extern crate tokio;
+extern crate url;
+
+pub mod api;
+pub mod client;
+pub mod error;
+pub mod resources;
@@ -0,0 +1,20 @@
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Episode {
+    pub id: u64,
+    pub series_id: u64,
+    pub name: Option<String>,
+    pub aired: Option<String>,
+    pub runtime: Option<u64>,
+    pub overview: Option<String>,
+    pub image: Option<String>,
+    pub number: u64,
+    pub season_number: u64,
+    pub absolute_number: Option<u64>,
+}
+
+/// A page of a series' episodes.
+#[derive(Debug, Deserialize)]
+pub struct SeriesEpisodes {
+    pub episodes: Vec<Episode>,
+}
@@ -0,0 +1,35 @@
+#[derive(Debug, Deserialize)]
+pub struct Genre {
+    pub id: u64,
+    pub name: String,
+    pub slug: Option<String>,
+}
+
+/// Another name of a series, such as its title in another language.
+#[derive(Debug, Deserialize)]
+pub struct Alias {
+    pub language: String,
+    pub name: String,
+}
+
+/// An id of a series in another database, such as IMDb.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RemoteId {
+    pub id: String,
+    #[serde(rename = "type")]
+    pub _type: u64,
+    pub source_name: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Artwork {
+    pub id: u64,
+    pub image: String,
+    pub thumbnail: Option<String>,
+    pub language: Option<String>,
+    #[serde(rename = "type")]
+    pub _type: u64,
+    pub score: Option<f64>,
+}
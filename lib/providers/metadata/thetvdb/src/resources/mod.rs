@@ -0,0 +1,9 @@
+pub use self::episode::*;
+pub use self::general::*;
+pub use self::season::*;
+pub use self::series::*;
+
+pub mod episode;
+pub mod general;
+pub mod season;
+pub mod series;
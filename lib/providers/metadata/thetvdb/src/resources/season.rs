@@ -0,0 +1,39 @@
+use resources::episode::Episode;
+use resources::general::*;
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Season {
+    pub id: u64,
+    pub series_id: u64,
+    #[serde(rename = "type")]
+    pub _type: SeasonTypeInfo,
+    pub number: u64,
+    pub name: Option<String>,
+    pub image: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SeasonExtended {
+    pub id: u64,
+    pub series_id: u64,
+    #[serde(rename = "type")]
+    pub _type: SeasonTypeInfo,
+    pub number: u64,
+    pub name: Option<String>,
+    pub image: Option<String>,
+    #[serde(default)]
+    pub episodes: Vec<Episode>,
+    #[serde(default)]
+    pub artwork: Vec<Artwork>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SeasonTypeInfo {
+    pub id: u64,
+    pub name: String,
+    /// `official`, `dvd`, `absolute` or `alternate`.
+    #[serde(rename = "type")]
+    pub _type: String,
+}
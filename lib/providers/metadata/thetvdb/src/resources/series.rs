@@ -0,0 +1,38 @@
+use resources::general::*;
+use resources::season::Season;
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Series {
+    pub id: u64,
+    pub name: String,
+    pub slug: String,
+    pub image: Option<String>,
+    pub first_aired: Option<String>,
+    pub overview: Option<String>,
+    pub average_runtime: Option<u64>,
+    pub year: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SeriesExtended {
+    pub id: u64,
+    pub name: String,
+    pub slug: String,
+    pub image: Option<String>,
+    pub first_aired: Option<String>,
+    pub overview: Option<String>,
+    pub average_runtime: Option<u64>,
+    pub year: Option<String>,
+    #[serde(default)]
+    pub aliases: Vec<Alias>,
+    #[serde(default)]
+    pub genres: Vec<Genre>,
+    #[serde(default)]
+    pub remote_ids: Vec<RemoteId>,
+    #[serde(default)]
+    pub seasons: Vec<Season>,
+    #[serde(default)]
+    pub artworks: Vec<Artwork>,
+}
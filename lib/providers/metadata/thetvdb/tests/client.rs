@@ -0,0 +1,170 @@
+//! Runs the client against a local stub of TheTVDB's API that serves canned
+//! responses from `fixtures/`.
+
+extern crate hyper;
+extern crate stub_server;
+extern crate thetvdb;
+extern crate tokio;
+
+use std::sync::{Arc, Mutex};
+
+use thetvdb::api::SeasonType;
+use thetvdb::client::Client;
+use thetvdb::error::Error;
+
+use hyper::{Body, Request, Response, StatusCode};
+use stub_server::{json, Stub};
+use tokio::runtime::Runtime;
+
+const API_KEY: &'static str = "test-key";
+
+#[derive(Default)]
+struct Logins {
+    logins: usize,
+    /// The only token that is accepted. Cleared to make the stub reject
+    /// tokens it handed out before.
+    valid_token: Option<String>,
+}
+
+fn fixture(name: &str) -> &'static str {
+    match name {
+        "login" => include_str!("fixtures/login.json"),
+        "search" => include_str!("fixtures/search.json"),
+        "series_extended" => include_str!("fixtures/series_extended.json"),
+        "season_extended" => include_str!("fixtures/season_extended.json"),
+        "series_episodes_0" => include_str!("fixtures/series_episodes_0.json"),
+        "series_episodes_1" => include_str!("fixtures/series_episodes_1.json"),
+        _ => panic!("No fixture named {}", name),
+    }
+}
+
+fn respond(stub: &mut Logins, req: &Request<Body>, body: &[u8]) -> Response<Body> {
+    let path = req.uri().path();
+    let query = req.uri().query().unwrap_or("");
+
+    if path == "/v4/login" {
+        let body = String::from_utf8_lossy(body);
+        if !body.contains(&format!("\"apikey\":\"{}\"", API_KEY)) {
+            return status(StatusCode::UNAUTHORIZED);
+        }
+
+        stub.logins += 1;
+        let token = format!("token-{}", stub.logins);
+        stub.valid_token = Some(token.clone());
+        return json(fixture("login").replace("{token}", &token));
+    }
+
+    let expected = stub.valid_token.as_ref().map(|t| format!("Bearer {}", t));
+    let authorization = req.headers()
+        .get("Authorization")
+        .and_then(|h| h.to_str().ok());
+    if expected.is_none() || authorization != expected.as_ref().map(|e| e.as_str()) {
+        return status(StatusCode::UNAUTHORIZED);
+    }
+
+    let name = match (path, query) {
+        ("/v4/search", "query=game+of+thrones&type=series") => "search",
+        ("/v4/series/121361/extended", _) => "series_extended",
+        ("/v4/seasons/364731/extended", _) => "season_extended",
+        ("/v4/series/121361/episodes/official", "page=0") => "series_episodes_0",
+        ("/v4/series/121361/episodes/official", "page=1") => "series_episodes_1",
+        _ => return status(StatusCode::NOT_FOUND),
+    };
+    json(fixture(name))
+}
+
+fn status(status: StatusCode) -> Response<Body> {
+    stub_server::status(status, "{\"status\":\"failure\",\"data\":null}")
+}
+
+/// Starts the stub on a free port and returns its base URL.
+fn start_stub(runtime: &mut Runtime) -> (String, Arc<Mutex<Logins>>) {
+    let (url, stub) = Stub::new(Logins::default()).start(runtime, respond);
+    (format!("{}/v4", url), stub)
+}
+
+fn client(url: &str) -> Client {
+    Client::new(API_KEY, None).with_base_url(url)
+}
+
+#[test]
+fn searches_and_loads_series() {
+    let mut runtime = Runtime::new().unwrap();
+    let (url, _) = start_stub(&mut runtime);
+    let client = client(&url);
+
+    let results = runtime
+        .block_on(client.search_series("game of thrones").unwrap())
+        .unwrap();
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].tvdb_id, "121361");
+    assert_eq!(results[0].remote_ids[0].id, "tt0944947");
+    assert_eq!(results[0].network, Some("HBO".to_string()));
+    assert_eq!(results[0].status, Some("Ended".to_string()));
+
+    let series = runtime
+        .block_on(client.series_extended("121361").unwrap())
+        .unwrap();
+    assert_eq!(series.name, "Game of Thrones");
+    assert_eq!(series.aliases[1].name, "Juego de tronos");
+    assert_eq!(series.average_runtime, Some(58));
+    assert_eq!(series.seasons.len(), 2);
+    assert_eq!(series.seasons[0]._type._type, "official");
+    assert_eq!(series.artworks[0]._type, 3);
+
+    let season = runtime
+        .block_on(client.season_extended("364731").unwrap())
+        .unwrap();
+    assert_eq!(season.number, 1);
+    assert_eq!(season.episodes[0].name, Some("Winter Is Coming".to_string()));
+}
+
+#[test]
+fn follows_episode_pagination() {
+    let mut runtime = Runtime::new().unwrap();
+    let (url, _) = start_stub(&mut runtime);
+
+    let episodes = runtime
+        .block_on(client(&url).series_episodes("121361", SeasonType::Official).unwrap())
+        .unwrap();
+    let numbers: Vec<_> = episodes.iter().map(|e| (e.season_number, e.number)).collect();
+    assert_eq!(numbers, vec![(1, 1), (1, 2)]);
+}
+
+#[test]
+fn logs_in_once_and_again_when_the_token_is_rejected() {
+    let mut runtime = Runtime::new().unwrap();
+    let (url, stub) = start_stub(&mut runtime);
+    let client = client(&url);
+
+    runtime
+        .block_on(client.series_extended("121361").unwrap())
+        .unwrap();
+    runtime
+        .block_on(client.series_extended("121361").unwrap())
+        .unwrap();
+    assert_eq!(stub.lock().unwrap().logins, 1);
+
+    stub.lock().unwrap().valid_token = None;
+    runtime
+        .block_on(client.series_extended("121361").unwrap())
+        .unwrap();
+    assert_eq!(stub.lock().unwrap().logins, 2);
+}
+
+#[test]
+fn reports_failed_logins_and_missing_series() {
+    let mut runtime = Runtime::new().unwrap();
+    let (url, _) = start_stub(&mut runtime);
+
+    let bad_key = Client::new("wrong-key", None).with_base_url(&url);
+    match runtime.block_on(bad_key.series_extended("121361").unwrap()) {
+        Err(Error::LoginFailed) => {}
+        other => panic!("Expected a failed login, got {:?}", other),
+    }
+
+    match runtime.block_on(client(&url).series_extended("1").unwrap()) {
+        Err(Error::NotFound) => {}
+        other => panic!("Expected a missing series, got {:?}", other),
+    }
+}
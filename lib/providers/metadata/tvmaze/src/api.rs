@@ -24,6 +24,24 @@ pub enum LinkEmbed<'a> {
     Multiple(Vec<&'a str>),
 }
 
+/// How far back `Client::show_updates` goes.
+#[derive(Clone, Copy, Debug)]
+pub enum UpdatePeriod {
+    Day,
+    Week,
+    Month,
+}
+
+impl UpdatePeriod {
+    pub fn as_query_param(&self) -> (&str, &str) {
+        match self {
+            UpdatePeriod::Day => ("since", "day"),
+            UpdatePeriod::Week => ("since", "week"),
+            UpdatePeriod::Month => ("since", "month"),
+        }
+    }
+}
+
 #[derive(Debug)]
 pub enum ShowLookup<'a> {
     IMDB(&'a str),
@@ -0,0 +1,368 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde_json;
+
+/// Stores the bodies of successful responses by their URL. Caches are
+/// best-effort: a response that can't be stored or read again is simply
+/// fetched from the API.
+pub trait Cache: Send + Sync {
+    fn get(&self, url: &str) -> Option<CachedResponse>;
+    fn put(&self, url: &str, response: CachedResponse);
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct CachedResponse {
+    pub body: String,
+    /// Validators that are sent back to check whether a stale response is
+    /// still current.
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+    /// Unix timestamp after which the response has to be revalidated.
+    pub fresh_until: u64,
+}
+
+impl CachedResponse {
+    pub fn is_fresh(&self) -> bool {
+        unix_now() < self.fresh_until
+    }
+
+    /// Whether a stale response can be revalidated instead of fetched again.
+    pub fn has_validators(&self) -> bool {
+        self.etag.is_some() || self.last_modified.is_some()
+    }
+}
+
+/// How long responses stay fresh. Endpoints with a TTL ignore the max-age
+/// sent by TVmaze, the others follow it and are revalidated on every request
+/// without one.
+#[derive(Clone, Debug)]
+pub struct CachePolicy {
+    /// Shows whose status is "Ended" hardly ever change.
+    pub ended_show_ttl: Option<Duration>,
+    pub show_ttl: Option<Duration>,
+    pub schedule_ttl: Option<Duration>,
+    pub updates_ttl: Option<Duration>,
+}
+
+impl Default for CachePolicy {
+    fn default() -> CachePolicy {
+        CachePolicy {
+            ended_show_ttl: Some(Duration::from_secs(7 * 24 * 60 * 60)),
+            show_ttl: None,
+            schedule_ttl: Some(Duration::from_secs(10 * 60)),
+            updates_ttl: Some(Duration::from_secs(10 * 60)),
+        }
+    }
+}
+
+/// The kinds of endpoints that have TTLs of their own.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Endpoint {
+    Show,
+    Schedule,
+    Updates,
+    Other,
+}
+
+impl CachePolicy {
+    /// The TTL for a response from `endpoint`, if it has one.
+    pub fn ttl(&self, endpoint: Endpoint, body: &str) -> Option<Duration> {
+        match endpoint {
+            Endpoint::Show if is_ended_show(body) => self.ended_show_ttl,
+            Endpoint::Show => self.show_ttl,
+            Endpoint::Schedule => self.schedule_ttl,
+            Endpoint::Updates => self.updates_ttl,
+            Endpoint::Other => None,
+        }
+    }
+}
+
+fn is_ended_show(body: &str) -> bool {
+    #[derive(Deserialize)]
+    struct Status {
+        status: Option<String>,
+    }
+
+    serde_json::from_str::<Status>(body)
+        .ok()
+        .and_then(|show| show.status)
+        .map_or(false, |status| status == "Ended")
+}
+
+/// The latest update of each show that the updates endpoint reported. Cached
+/// shows that are older are stale, whatever their TTL.
+#[derive(Debug, Default)]
+pub struct ShowUpdates {
+    latest: Mutex<HashMap<u64, u64>>,
+}
+
+impl ShowUpdates {
+    pub fn record(&self, updates: &HashMap<u64, u64>) {
+        let mut latest = self.latest.lock().unwrap();
+        for (&id, &updated) in updates {
+            let known = latest.entry(id).or_insert(updated);
+            *known = (*known).max(updated);
+        }
+    }
+
+    /// Whether `body`, a show, is older than its last reported update.
+    pub fn outdates(&self, body: &str) -> bool {
+        #[derive(Deserialize)]
+        struct Version {
+            id: u64,
+            updated: u64,
+        }
+
+        let show = match serde_json::from_str::<Version>(body) {
+            Ok(show) => show,
+            Err(_) => return false,
+        };
+        self.latest
+            .lock()
+            .unwrap()
+            .get(&show.id)
+            .map_or(false, |&latest| latest > show.updated)
+    }
+}
+
+/// How often the cache answered requests. Responses that were revalidated
+/// with a 304 count as hits as well.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct CacheStats {
+    pub hits: usize,
+    pub misses: usize,
+    pub revalidations: usize,
+}
+
+#[derive(Debug, Default)]
+pub struct CacheCounters {
+    hits: AtomicUsize,
+    misses: AtomicUsize,
+    revalidations: AtomicUsize,
+}
+
+impl CacheCounters {
+    pub fn hit(&self) {
+        self.hits.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn miss(&self) {
+        self.misses.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn revalidated(&self) {
+        self.revalidations.fetch_add(1, Ordering::Relaxed);
+        self.hit();
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            revalidations: self.revalidations.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Keeps up to `capacity` responses in memory, dropping the least recently
+/// used one when it is full.
+pub struct MemoryCache {
+    lru: Mutex<Lru>,
+}
+
+struct Lru {
+    capacity: usize,
+    /// Responses with the tick they were last used at.
+    entries: HashMap<String, (CachedResponse, u64)>,
+    /// URLs by the tick they were last used at, oldest first.
+    used: BTreeMap<u64, String>,
+    tick: u64,
+}
+
+impl MemoryCache {
+    pub fn new(capacity: usize) -> MemoryCache {
+        MemoryCache {
+            lru: Mutex::new(Lru {
+                capacity: capacity.max(1),
+                entries: HashMap::new(),
+                used: BTreeMap::new(),
+                tick: 0,
+            }),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.lru.lock().unwrap().entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl Lru {
+    fn touch(&mut self, url: &str) -> u64 {
+        self.tick += 1;
+        if let Some(&mut (_, ref mut used)) = self.entries.get_mut(url) {
+            let last_used = *used;
+            self.used.remove(&last_used);
+            *used = self.tick;
+        }
+        self.used.insert(self.tick, url.to_string());
+        self.tick
+    }
+}
+
+impl Cache for MemoryCache {
+    fn get(&self, url: &str) -> Option<CachedResponse> {
+        let mut lru = self.lru.lock().unwrap();
+        if !lru.entries.contains_key(url) {
+            return None;
+        }
+        lru.touch(url);
+        lru.entries.get(url).map(|&(ref response, _)| response.clone())
+    }
+
+    fn put(&self, url: &str, response: CachedResponse) {
+        let mut lru = self.lru.lock().unwrap();
+        let tick = lru.touch(url);
+        lru.entries.insert(url.to_string(), (response, tick));
+
+        while lru.entries.len() > lru.capacity {
+            let oldest = match lru.used.keys().next() {
+                Some(&oldest) => oldest,
+                None => break,
+            };
+            if let Some(url) = lru.used.remove(&oldest) {
+                lru.entries.remove(&url);
+            }
+        }
+    }
+}
+
+/// Keeps responses as files in a directory, so they outlive the process.
+/// Files are named after a hash of their URL.
+pub struct DiskCache {
+    dir: PathBuf,
+}
+
+#[derive(Deserialize, Serialize)]
+struct DiskEntry {
+    /// Tells responses apart whose URLs have the same hash.
+    url: String,
+    response: CachedResponse,
+}
+
+impl DiskCache {
+    /// Creates `dir` if it doesn't exist yet.
+    pub fn new<P: Into<PathBuf>>(dir: P) -> io::Result<DiskCache> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)?;
+        Ok(DiskCache { dir })
+    }
+
+    fn path(&self, url: &str) -> PathBuf {
+        self.dir.join(format!("{:016x}.json", fnv1a(url.as_bytes())))
+    }
+}
+
+impl Cache for DiskCache {
+    fn get(&self, url: &str) -> Option<CachedResponse> {
+        let file = fs::File::open(self.path(url)).ok()?;
+        serde_json::from_reader::<_, DiskEntry>(file)
+            .ok()
+            .filter(|entry| entry.url == url)
+            .map(|entry| entry.response)
+    }
+
+    fn put(&self, url: &str, response: CachedResponse) {
+        let entry = DiskEntry {
+            url: url.to_string(),
+            response,
+        };
+        let path = self.path(url);
+        // Written next to the entry first, so readers never see half a file.
+        let partial = path.with_extension("partial");
+        let written = serde_json::to_vec(&entry)
+            .map_err(io::Error::from)
+            .and_then(|json| fs::write(&partial, json))
+            .and_then(|_| fs::rename(&partial, &path));
+        if written.is_err() {
+            let _ = fs::remove_file(&partial);
+        }
+    }
+}
+
+/// A hash that stays the same across Rust versions, unlike `DefaultHasher`,
+/// so that the files of a disk cache can be found again after an upgrade.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+pub fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    fn response(body: &str) -> CachedResponse {
+        CachedResponse {
+            body: body.to_string(),
+            etag: Some("\"1\"".to_string()),
+            last_modified: None,
+            fresh_until: unix_now() + 60,
+        }
+    }
+
+    #[test]
+    fn evicts_the_least_recently_used_response() {
+        let cache = MemoryCache::new(2);
+        cache.put("a", response("a"));
+        cache.put("b", response("b"));
+        assert!(cache.get("a").is_some());
+
+        cache.put("c", response("c"));
+        assert_eq!(cache.len(), 2);
+        assert!(cache.get("b").is_none());
+        assert_eq!(cache.get("a"), Some(response("a")));
+        assert_eq!(cache.get("c"), Some(response("c")));
+    }
+
+    #[test]
+    fn keeps_responses_on_disk() {
+        let dir = env::temp_dir().join(format!("tvmaze-cache-{}", unix_now()));
+        let cache = DiskCache::new(&dir).unwrap();
+        cache.put("http://api.tvmaze.com/shows/1", response("{}"));
+
+        let cache = DiskCache::new(&dir).unwrap();
+        assert_eq!(cache.get("http://api.tvmaze.com/shows/1"), Some(response("{}")));
+        assert_eq!(cache.get("http://api.tvmaze.com/shows/2"), None);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn keeps_ended_shows_longer() {
+        let policy = CachePolicy::default();
+        let ended = r#"{"id":1,"status":"Ended"}"#;
+        let running = r#"{"id":2,"status":"Running"}"#;
+
+        assert_eq!(policy.ttl(Endpoint::Show, ended), policy.ended_show_ttl);
+        assert_eq!(policy.ttl(Endpoint::Show, running), None);
+        assert_eq!(policy.ttl(Endpoint::Other, ended), None);
+        assert_eq!(policy.ttl(Endpoint::Schedule, "[]"), policy.schedule_ttl);
+    }
+}
@@ -1,143 +1,286 @@
-use std::io::Cursor;
-use std::marker::PhantomData;
+use std::cmp;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::str;
+use std::sync::Arc;
+use std::time::Duration;
 
 use api::*;
+use cache::{
+    unix_now, Cache, CacheCounters, CachePolicy, CacheStats, CachedResponse, Endpoint, ShowUpdates,
+};
 use error::Error;
+use rate_limit::{backoff, RateLimiter};
 use resources::*;
+use timer::Timer;
 
-use futures::{Async, Future, Poll, Stream};
+use proxy::ProxyConnector;
+use replay::{Recorder, Replayer};
+
+use futures::future::{self, Loop};
+use futures::{stream, Future, Stream};
+use http::header::{
+    HeaderMap, HeaderValue, CACHE_CONTROL, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED,
+    USER_AGENT,
+};
 #[cfg(feature = "tls")]
 use http::uri::{Parts, Scheme};
 use hyper::body::Body;
+use hyper::client::connect::Connect;
 use hyper::client::HttpConnector;
 #[cfg(feature = "tls")]
 use hyper::Uri;
-use hyper::{self, Request, Response};
+use hyper::{self, Request, Response, StatusCode};
 #[cfg(feature = "tls")]
-use hyper_tls::{self, HttpsConnector};
+use hyper_tls::HttpsConnector;
 use serde::de::DeserializeOwned;
 use serde_json;
 use url::form_urlencoded::Serializer;
 
-#[cfg(feature = "tls")]
-pub type HTTPClient = hyper::Client<HttpsConnector<HttpConnector>, Body>;
+pub type QueryFuture<R> = Box<Future<Item = R, Error = Error> + Send>;
 
-#[cfg(not(feature = "tls"))]
-pub type HTTPClient = hyper::Client<HttpConnector, Body>;
+pub type QueryStream<R> = Box<Stream<Item = R, Error = Error> + Send>;
 
-pub enum QueryState {
-    AwaitingRedirect {
-        http_client: HTTPClient,
-        inner: Box<Future<Item = Response<Body>, Error = hyper::Error>>,
-    },
-    AwaitingData(Box<Future<Item = Vec<u8>, Error = Error>>),
+/// Sends the client's requests. Implemented by hyper's client, which the
+/// builder sets up, and by the `Recorder` and `Replayer` of fixtures.
+pub trait Transport: Send + Sync {
+    fn send(&self, req: Request<Body>) -> QueryFuture<Response<Body>>;
 }
 
-pub struct QueryFuture<R> {
-    state: QueryState,
-    _marker: PhantomData<R>,
+impl<C> Transport for hyper::Client<C, Body>
+where
+    C: Connect + 'static,
+    C::Transport: 'static,
+    C::Future: 'static,
+{
+    fn send(&self, req: Request<Body>) -> QueryFuture<Response<Body>> {
+        Box::new(self.request(req).map_err(Error::from))
+    }
 }
 
-unsafe impl<R: DeserializeOwned> Send for QueryFuture<R> {}
-
-impl<R: DeserializeOwned> Future for QueryFuture<R> {
-    type Item = R;
-    type Error = Error;
-
-    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
-        loop {
-            let state = match self.state {
-                QueryState::AwaitingRedirect {
-                    ref http_client,
-                    ref mut inner,
-                } => {
-                    let res = try_ready!(inner.poll());
-                    let url = match res.status().as_u16() {
-                        301 | 302 => res
-                            .headers()
-                            .get("Location")
-                            .map(|loc| loc.to_str().map_err(Error::from))
-                            .ok_or(Error::MissingLocationHeader),
-                        404 => {
-                            return Err(Error::ShowNotFound);
-                        }
-                        _ => {
-                            return Err(Error::UnexpectedResponse);
-                        }
-                    };
-
-                    match url {
-                        Ok(Ok(url)) => {
-                            #[cfg(feature = "tls")]
-                            let url = url_to_https(url)?;
-                            let req = Request::get(url).body(Body::empty())?;
-                            let inner = http_client
-                                .request(req)
-                                .and_then(|res| res.into_body().concat2())
-                                .map(|chunk| chunk.to_vec())
-                                .map_err(Error::from);
-
-                            QueryState::AwaitingData(Box::new(inner))
-                        }
-                        Ok(Err(e)) | Err(e) => {
-                            return Err(e);
-                        }
-                    }
-                }
+/// How often a request that was answered with a 429 or 5xx is sent again.
+const MAX_RETRIES: u32 = 4;
 
-                QueryState::AwaitingData(ref mut fut) => {
-                    let bytes = try_ready!(fut.poll());
-                    let cursor = Cursor::new(bytes);
-                    let deserialized: R = serde_json::from_reader(cursor)?;
-                    return Ok(Async::Ready(deserialized));
-                }
-            };
+/// The wait before the first retry, which doubles with every following one.
+const BASE_BACKOFF_MS: u64 = 500;
 
-            self.state = state;
-        }
-    }
-}
+/// Threads that resolve host names for the default connectors.
+const DNS_THREADS: usize = 4;
+
+/// How many idle connections are kept open to the API.
+const DEFAULT_POOL_SIZE: usize = 4;
+
+const DEFAULT_USER_AGENT: &'static str =
+    concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"));
 
 #[derive(Clone)]
 pub struct Client {
-    http_client: HTTPClient,
+    transport: Arc<Transport>,
+    base_url: String,
+    user_agent: HeaderValue,
+    timeout: Option<Duration>,
+    limiter: RateLimiter,
+    timer: Timer,
+    base_backoff: Duration,
+    cache: Option<Arc<Cache>>,
+    cache_policy: CachePolicy,
+    cache_counters: Arc<CacheCounters>,
+    show_updates: Arc<ShowUpdates>,
+}
+
+/// Configures a `Client`. Everything that isn't set falls back to what TVmaze
+/// expects: its public API, 20 requests every 10 seconds, no proxy, no
+/// timeout and no cache.
+pub struct ClientBuilder {
+    base_url: String,
+    user_agent: String,
+    timeout: Option<Duration>,
+    proxy: Option<String>,
+    pool_size: usize,
+    limiter: RateLimiter,
+    base_backoff: Duration,
+    cache: Option<Arc<Cache>>,
+    cache_policy: CachePolicy,
+    fixtures: Option<Fixtures>,
+}
+
+/// Where requests and their responses are saved to or served from.
+enum Fixtures {
+    Record(PathBuf),
+    Replay(PathBuf),
 }
 
 impl Client {
-    #[cfg(not(feature = "tls"))]
-    pub fn new() -> Client {
-        Client {
-            http_client: hyper::Client::new(),
-        }
+    /// A client for TVmaze's public API with the default settings.
+    pub fn new() -> Result<Client, Error> {
+        ClientBuilder::new().build()
     }
 
-    #[cfg(feature = "tls")]
-    pub fn new() -> Result<Client, hyper_tls::Error> {
-        let mut connector = HttpsConnector::new(4)?;
-        connector.force_https(true);
+    pub fn builder() -> ClientBuilder {
+        ClientBuilder::new()
+    }
 
-        Ok(Client {
-            http_client: hyper::Client::builder().build(connector),
-        })
+    /// How often the cache answered requests since the client was built.
+    /// Always empty without a cache.
+    pub fn cache_stats(&self) -> CacheStats {
+        self.cache_counters.stats()
     }
 
-    fn create_future<R: DeserializeOwned>(&self, req: Request<Body>) -> QueryFuture<R> {
-        let inner = self
-            .http_client
-            .request(req)
-            .and_then(|res| res.into_body().concat2())
-            .map(|chunk| chunk.to_vec())
-            .map_err(Error::from);
+    /// Sends a GET request once the rate limit allows it. Requests that are
+    /// answered with a 429 or 5xx are retried with exponential backoff, or
+    /// after the time the server asks for if that is longer. The last
+    /// response is returned whatever its status. With a `cached` response,
+    /// the request is conditional and may be answered with a 304.
+    fn send(
+        &self,
+        url: String,
+        cached: Option<&CachedResponse>,
+    ) -> QueryFuture<Response<Body>> {
+        let client = self.clone();
+        let mut validators = vec![];
+        if let Some(cached) = cached {
+            if let Some(ref etag) = cached.etag {
+                validators.push((IF_NONE_MATCH, etag.clone()));
+            }
+            if let Some(ref last_modified) = cached.last_modified {
+                validators.push((IF_MODIFIED_SINCE, last_modified.clone()));
+            }
+        }
+
+        Box::new(future::loop_fn(0, move |attempt| {
+            let transport = client.transport.clone();
+            let timeout = client.timeout;
+            let timer = client.timer.clone();
+            let retry_timer = client.timer.clone();
+            let base_backoff = client.base_backoff;
+            let mut req = Request::get(url.as_str());
+            req.header(USER_AGENT, client.user_agent.clone());
+            for &(ref name, ref value) in &validators {
+                req.header(name.clone(), value.as_str());
+            }
+            let req = req.body(Body::empty());
+
+            client
+                .limiter
+                .acquire(&client.timer)
+                .then(move |_| req.map_err(Error::from))
+                .and_then(move |req| with_timeout(transport.send(req), timeout, &timer))
+                .and_then(move |res| -> QueryFuture<Loop<Response<Body>, u32>> {
+                    let status = res.status();
+                    let retry = status.as_u16() == 429 || status.is_server_error();
+                    if retry && attempt < MAX_RETRIES {
+                        let wait = backoff(base_backoff, attempt);
+                        let wait = retry_after(&res).map_or(wait, |after| wait.max(after));
+                        let wait = retry_timer.delay(wait);
+                        Box::new(wait.then(move |_| Ok(Loop::Continue(attempt + 1))))
+                    } else {
+                        Box::new(future::ok(Loop::Break(res)))
+                    }
+                })
+        }))
+    }
 
-        QueryFuture {
-            state: QueryState::AwaitingData(Box::new(inner)),
-            _marker: PhantomData,
+    /// Sends a GET request and deserializes the response, or fails with the
+    /// error for its status. Fresh responses are taken from the cache, and
+    /// stale ones are revalidated if TVmaze sent an ETag or Last-Modified
+    /// header with them. Shows that the updates endpoint reported a newer
+    /// update of are stale even within their TTL.
+    fn get<R>(&self, url: String, endpoint: Endpoint) -> QueryFuture<R>
+    where
+        R: DeserializeOwned + Send + 'static,
+    {
+        let cached = self.cache.as_ref().and_then(|cache| cache.get(&url));
+        if let Some(ref cached) = cached {
+            let outdated = endpoint == Endpoint::Show && self.show_updates.outdates(&cached.body);
+            if cached.is_fresh() && !outdated {
+                self.cache_counters.hit();
+                return Box::new(future::result(
+                    serde_json::from_str(&cached.body).map_err(Error::from),
+                ));
+            }
         }
+        let cached = cached.filter(|cached| cached.has_validators());
+        let client = self.clone();
+
+        Box::new(self.send(url.clone(), cached.as_ref()).and_then(
+            move |res| -> QueryFuture<R> {
+                if let (StatusCode::NOT_MODIFIED, Some(cached)) = (res.status(), cached) {
+                    client.cache_counters.revalidated();
+                    let parsed = serde_json::from_str(&cached.body).map_err(Error::from);
+                    let headers = res.headers();
+                    client.cache_response(&url, endpoint, headers, &cached.body, Some(&cached));
+                    return Box::new(future::result(parsed));
+                }
+                if !res.status().is_success() {
+                    return read_error(res);
+                }
+
+                if client.cache.is_some() {
+                    client.cache_counters.miss();
+                }
+                let headers = res.headers().clone();
+                Box::new(
+                    res.into_body()
+                        .concat2()
+                        .map_err(Error::from)
+                        .and_then(move |body| {
+                            if let Ok(text) = str::from_utf8(&body) {
+                                client.cache_response(&url, endpoint, &headers, text, None);
+                            }
+                            serde_json::from_slice(&body).map_err(Error::from)
+                        }),
+                )
+            },
+        ))
+    }
+
+    /// Stores a response's body for as long as both the cache policy and its
+    /// `Cache-Control` header allow, unless the header forbids storing it.
+    /// Validators that a 304 didn't repeat are kept from the `revalidated`
+    /// response.
+    fn cache_response(
+        &self,
+        url: &str,
+        endpoint: Endpoint,
+        headers: &HeaderMap,
+        body: &str,
+        revalidated: Option<&CachedResponse>,
+    ) {
+        let cache = match self.cache {
+            Some(ref cache) => cache,
+            None => return,
+        };
+        let max_age = match cache_control(headers) {
+            CacheControl::NoStore => return,
+            CacheControl::MaxAge(max_age) => Some(max_age),
+            CacheControl::Unspecified => None,
+        };
+        let ttl = match (self.cache_policy.ttl(endpoint, body), max_age) {
+            (Some(ttl), Some(max_age)) => cmp::min(ttl, max_age),
+            (ttl, max_age) => ttl.or(max_age).unwrap_or_else(|| Duration::from_secs(0)),
+        };
+        let header = |name| {
+            headers
+                .get(name)
+                .and_then(|value: &HeaderValue| value.to_str().ok())
+                .map(|value| value.to_string())
+        };
+
+        cache.put(
+            url,
+            CachedResponse {
+                body: body.to_string(),
+                etag: header(ETAG).or_else(|| revalidated.and_then(|r| r.etag.clone())),
+                last_modified: header(LAST_MODIFIED)
+                    .or_else(|| revalidated.and_then(|r| r.last_modified.clone())),
+                fresh_until: unix_now() + ttl.as_secs(),
+            },
+        );
     }
 
     pub fn search_shows(&self, query: &str) -> Result<QueryFuture<Vec<ShowSearchResult>>, Error> {
-        let req = create_request("/search/shows", Some(vec![("q", query)]), None)?;
-        Ok(self.create_future(req))
+        let url = create_url(&self.base_url, "/search/shows", Some(vec![("q", query)]), None);
+        Ok(self.get(url, Endpoint::Other))
     }
 
     pub fn search_show(
@@ -145,30 +288,50 @@ impl Client {
         query: &str,
         embed: Option<LinkEmbed>,
     ) -> Result<QueryFuture<TVShow>, Error> {
-        let req = create_request("/singlesearch/shows", Some(vec![("q", query)]), embed)?;
-        Ok(self.create_future(req))
+        let url = create_url(&self.base_url, "/singlesearch/shows", Some(vec![("q", query)]), embed);
+        Ok(self.get(url, Endpoint::Show))
     }
 
     pub fn lookup_show(&self, show: ShowLookup) -> Result<QueryFuture<Option<TVShow>>, Error> {
-        // This request should return either a redirect or a 404, so the future
-        // resolves to a URL, the content of the location header.
-        let initial_lookup =
-            create_request("/lookup/shows", Some(vec![show.as_query_param()]), None)?;
-        let inner = Box::new(self.http_client.request(initial_lookup));
-        let http_client = self.http_client.clone();
-
-        Ok(QueryFuture {
-            state: QueryState::AwaitingRedirect { http_client, inner },
-            _marker: PhantomData,
-        })
+        // This request should be answered with either a redirect to the show
+        // or a 404, so the show is loaded from the location header.
+        let params = Some(vec![show.as_query_param()]);
+        let url = create_url(&self.base_url, "/lookup/shows", params, None);
+        let client = self.clone();
+
+        Ok(Box::new(self.send(url, None).and_then(
+            move |res| -> QueryFuture<Option<TVShow>> {
+                let location = match res.status().as_u16() {
+                    301 | 302 => res
+                        .headers()
+                        .get("Location")
+                        .ok_or(Error::MissingLocationHeader)
+                        .and_then(|loc| loc.to_str().map_err(Error::from))
+                        .map(|loc| loc.to_string()),
+                    404 => Err(Error::ShowNotFound),
+                    _ => return read_error(res),
+                };
+                #[cfg(feature = "tls")]
+                let location = if client.base_url.starts_with("https:") {
+                    location.and_then(|url| url_to_https(&url))
+                } else {
+                    location
+                };
+
+                match location {
+                    Ok(url) => client.get(url, Endpoint::Show),
+                    Err(e) => Box::new(future::err(e)),
+                }
+            },
+        )))
     }
 
     pub fn search_people(
         &self,
         query: &str,
     ) -> Result<QueryFuture<Vec<PeopleSearchResult>>, Error> {
-        let req = create_request("/search/people", Some(vec![("q", query)]), None)?;
-        Ok(self.create_future(req))
+        let url = create_url(&self.base_url, "/search/people", Some(vec![("q", query)]), None);
+        Ok(self.get(url, Endpoint::Other))
     }
 
     pub fn today_schedule(
@@ -188,24 +351,29 @@ impl Client {
             Some(params)
         };
 
-        let req = create_request("/schedule", params, None)?;
-        Ok(self.create_future(req))
+        let url = create_url(&self.base_url, "/schedule", params, None);
+        Ok(self.get(url, Endpoint::Schedule))
     }
 
     pub fn full_schedule(&self) -> Result<QueryFuture<Vec<Episode>>, Error> {
-        let req = create_request("/schedule/full", None, None)?;
-        Ok(self.create_future(req))
+        let url = create_url(&self.base_url, "/schedule/full", None, None);
+        Ok(self.get(url, Endpoint::Schedule))
     }
 
-    pub fn show_main_info(&self, id: &str) -> Result<QueryFuture<TVShow>, Error> {
-        let req = create_request(&["/shows/", id].join(""), None, None)?;
-        Ok(self.create_future(req))
+    pub fn show_main_info(
+        &self,
+        id: &str,
+        embed: Option<LinkEmbed>,
+    ) -> Result<QueryFuture<TVShow>, Error> {
+        let url = create_url(&self.base_url, &["/shows/", id].join(""), None, embed);
+        Ok(self.get(url, Endpoint::Show))
     }
 
     pub fn show_episode_list(
         &self,
         id: &str,
         specials: bool,
+        embed: Option<LinkEmbed>,
     ) -> Result<QueryFuture<Vec<Episode>>, Error> {
         let path = &["/shows/", id, "/episodes"].join("");
         // I'm not sure how specials actually work. I've tried looking at the JSON
@@ -216,8 +384,8 @@ impl Client {
         } else {
             None
         };
-        let req = create_request(path, params, None)?;
-        Ok(self.create_future(req))
+        let url = create_url(&self.base_url, path, params, embed);
+        Ok(self.get(url, Endpoint::Other))
     }
 
     pub fn show_episode_by_number(
@@ -233,8 +401,8 @@ impl Client {
             ("season", season.as_str()),
             ("number", episode.as_str()),
         ]);
-        let req = create_request(path, params, None)?;
-        Ok(self.create_future(req))
+        let url = create_url(&self.base_url, path, params, None);
+        Ok(self.get(url, Endpoint::Other))
     }
 
     pub fn show_episodes_by_date(
@@ -243,58 +411,367 @@ impl Client {
         date: &str,
     ) -> Result<QueryFuture<Vec<Episode>>, Error> {
         let path = &["/shows/", id, "/episodesbydate"].join("");
-        let req = create_request(path, Some(vec![("date", date)]), None)?;
-        Ok(self.create_future(req))
+        let url = create_url(&self.base_url, path, Some(vec![("date", date)]), None);
+        Ok(self.get(url, Endpoint::Other))
     }
 
     pub fn show_seasons(&self, id: &str) -> Result<QueryFuture<Vec<TVShowSeason>>, Error> {
         let path = &["/shows/", id, "/seasons"].join("");
-        let req = create_request(path, None, None)?;
-        Ok(self.create_future(req))
+        let url = create_url(&self.base_url, path, None, None);
+        Ok(self.get(url, Endpoint::Other))
     }
 
     pub fn season_episodes(&self, season_id: &str) -> Result<QueryFuture<Vec<Episode>>, Error> {
         let path = &["/seasons/", season_id, "/episodes"].join("");
-        let req = create_request(path, None, None)?;
-        Ok(self.create_future(req))
+        let url = create_url(&self.base_url, path, None, None);
+        Ok(self.get(url, Endpoint::Other))
     }
 
     pub fn show_cast(&self, id: &str) -> Result<QueryFuture<Vec<CastPerson>>, Error> {
         let path = &["/shows/", id, "/cast"].join("");
-        let req = create_request(path, None, None)?;
-        Ok(self.create_future(req))
+        let url = create_url(&self.base_url, path, None, None);
+        Ok(self.get(url, Endpoint::Other))
     }
 
     pub fn show_crew(&self, id: &str) -> Result<QueryFuture<Vec<CrewPerson>>, Error> {
         let path = &["/shows/", id, "/crew"].join("");
-        let req = create_request(path, None, None)?;
-        Ok(self.create_future(req))
+        let url = create_url(&self.base_url, path, None, None);
+        Ok(self.get(url, Endpoint::Other))
     }
 
     pub fn show_akas(&self, id: &str) -> Result<QueryFuture<Vec<AKA>>, Error> {
         let path = &["/shows/", id, "/akas"].join("");
-        let req = create_request(path, None, None)?;
-        Ok(self.create_future(req))
+        let url = create_url(&self.base_url, path, None, None);
+        Ok(self.get(url, Endpoint::Other))
+    }
+
+    pub fn show_images(&self, id: &str) -> Result<QueryFuture<Vec<ShowImage>>, Error> {
+        let path = &["/shows/", id, "/images"].join("");
+        let url = create_url(&self.base_url, path, None, None);
+        Ok(self.get(url, Endpoint::Other))
     }
 
     pub fn show_index(&self, page: Option<u64>) -> Result<QueryFuture<Vec<TVShow>>, Error> {
-        let page = page.unwrap_or(1).to_string();
-        let req = create_request("/shows", Some(vec![("page", &page)]), None)?;
-        Ok(self.create_future(req))
+        Ok(self.show_index_page(page.unwrap_or(1)))
+    }
+
+    /// Every page of the show index from `first_page` on, with its number,
+    /// in order. TVmaze numbers the pages from 0 and answers with a 404 past
+    /// the last one, which ends the stream. Up to `concurrency` pages are
+    /// requested at once, which still wait for the client's rate limit, so a
+    /// few pages past the end may be requested as well.
+    pub fn show_index_pages(
+        &self,
+        first_page: u64,
+        concurrency: usize,
+    ) -> QueryStream<(u64, Vec<TVShow>)> {
+        let client = self.clone();
+        let pages = stream::iter_ok::<_, Error>(first_page..)
+            .map(move |page| {
+                client
+                    .show_index_page(page)
+                    .map(move |shows| Some((page, shows)))
+                    .or_else(|e| match e {
+                        Error::NotFound(_) => Ok(None),
+                        e => Err(e),
+                    })
+            })
+            .buffered(concurrency.max(1))
+            .take_while(|page| Ok(page.is_some()))
+            .filter_map(|page| page);
+
+        Box::new(pages)
+    }
+
+    fn show_index_page(&self, page: u64) -> QueryFuture<Vec<TVShow>> {
+        let page = page.to_string();
+        let url = create_url(&self.base_url, "/shows", Some(vec![("page", &page)]), None);
+        self.get(url, Endpoint::Other)
+    }
+
+    /// The shows that changed within `since`, mapped to the time of their last
+    /// update as a unix timestamp, the same as their `updated` field. Cached
+    /// shows that are older than these are loaded again.
+    pub fn show_updates(
+        &self,
+        since: UpdatePeriod,
+    ) -> Result<QueryFuture<HashMap<u64, u64>>, Error> {
+        let url = create_url(&self.base_url, "/updates/shows", Some(vec![since.as_query_param()]), None);
+        let show_updates = self.show_updates.clone();
+        Ok(Box::new(self.get(url, Endpoint::Updates).map(
+            move |updates: HashMap<u64, u64>| {
+                show_updates.record(&updates);
+                updates
+            },
+        )))
     }
 
     pub fn person_main_info(&self, person_id: &str) -> Result<QueryFuture<Person>, Error> {
-        let req = create_request(&["/people/", person_id].join(""), None, None)?;
-        Ok(self.create_future(req))
+        let url = create_url(&self.base_url, &["/people/", person_id].join(""), None, None);
+        Ok(self.get(url, Endpoint::Other))
+    }
+}
+
+impl ClientBuilder {
+    pub fn new() -> ClientBuilder {
+        ClientBuilder {
+            base_url: TVMAZE_BASE_URL.to_string(),
+            user_agent: DEFAULT_USER_AGENT.to_string(),
+            timeout: None,
+            proxy: None,
+            pool_size: DEFAULT_POOL_SIZE,
+            limiter: RateLimiter::default(),
+            base_backoff: Duration::from_millis(BASE_BACKOFF_MS),
+            cache: None,
+            cache_policy: CachePolicy::default(),
+            fixtures: None,
+        }
+    }
+
+    /// Sends requests to another server, such as a mirror, a caching proxy
+    /// or a local stub of the API.
+    pub fn base_url(mut self, base_url: &str) -> ClientBuilder {
+        self.base_url = base_url.trim_right_matches('/').to_string();
+        self
     }
+
+    /// TVmaze asks clients to identify themselves, which by default is done
+    /// with this crate's name and version.
+    pub fn user_agent(mut self, user_agent: &str) -> ClientBuilder {
+        self.user_agent = user_agent.to_string();
+        self
+    }
+
+    /// Fails requests whose response hasn't arrived after `timeout` with
+    /// `Error::Timeout`. Every retry gets the full timeout again.
+    pub fn timeout(mut self, timeout: Duration) -> ClientBuilder {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Sends every request through the HTTP proxy at `proxy`, such as
+    /// `http://localhost:3128`. Only plain HTTP base URLs can be proxied,
+    /// since tunnelling TLS through the proxy isn't supported.
+    pub fn proxy(mut self, proxy: &str) -> ClientBuilder {
+        self.proxy = Some(proxy.to_string());
+        self
+    }
+
+    /// Keeps up to `size` idle connections open instead of 4.
+    pub fn pool_size(mut self, size: usize) -> ClientBuilder {
+        self.pool_size = size;
+        self
+    }
+
+    /// Allows `calls` requests every `period` instead of TVmaze's limit. The
+    /// limit is shared by all clones of the built client.
+    pub fn rate_limit(mut self, calls: u32, period: Duration) -> ClientBuilder {
+        self.limiter = RateLimiter::new(calls, period);
+        self
+    }
+
+    /// Waits `base` before the first retry instead of half a second.
+    pub fn backoff(mut self, base: Duration) -> ClientBuilder {
+        self.base_backoff = base;
+        self
+    }
+
+    /// Keeps responses in `cache`, such as a `MemoryCache` or `DiskCache`, and
+    /// revalidates them once they are stale. The cache is shared by all
+    /// clones of the built client.
+    pub fn cache<C: Cache + 'static>(mut self, cache: C) -> ClientBuilder {
+        self.cache = Some(Arc::new(cache));
+        self
+    }
+
+    /// Keeps cached responses fresh for other TTLs than the default ones.
+    pub fn cache_policy(mut self, policy: CachePolicy) -> ClientBuilder {
+        self.cache_policy = policy;
+        self
+    }
+
+    /// Saves every request with its response as a fixture in `dir`, which a
+    /// client built with `replay` serves again.
+    pub fn record<P: Into<PathBuf>>(mut self, dir: P) -> ClientBuilder {
+        self.fixtures = Some(Fixtures::Record(dir.into()));
+        self
+    }
+
+    /// Answers requests with the fixtures in `dir` rather than over the
+    /// network, failing those that weren't recorded.
+    pub fn replay<P: Into<PathBuf>>(mut self, dir: P) -> ClientBuilder {
+        self.fixtures = Some(Fixtures::Replay(dir.into()));
+        self
+    }
+
+    /// Builds a client that connects over plain HTTP.
+    #[cfg(not(feature = "tls"))]
+    pub fn build(self) -> Result<Client, Error> {
+        self.build_with_connector(HttpConnector::new(DNS_THREADS))
+    }
+
+    /// Builds a client that connects over HTTPS, unless the base URL or a
+    /// proxy asks for plain HTTP.
+    #[cfg(feature = "tls")]
+    pub fn build(self) -> Result<Client, Error> {
+        if self.proxy.is_some() {
+            return self.build_with_connector(HttpConnector::new(DNS_THREADS));
+        }
+
+        let mut connector = HttpsConnector::new(DNS_THREADS)?;
+        connector.force_https(self.base_url.starts_with("https:"));
+        self.build_with_connector(connector)
+    }
+
+    /// Builds a client that opens its connections with `connector`, such as
+    /// one that resolves names differently or connects to an in-memory
+    /// server.
+    pub fn build_with_connector<C>(mut self, connector: C) -> Result<Client, Error>
+    where
+        C: Connect + 'static,
+        C::Transport: 'static,
+        C::Future: 'static,
+    {
+        let fixtures = self.fixtures.take();
+        if let Some(Fixtures::Replay(dir)) = fixtures {
+            return self.build_with_transport(Arc::new(Replayer::new(dir)));
+        }
+
+        let mut builder = hyper::Client::builder();
+        builder.max_idle_per_host(self.pool_size);
+
+        let transport: Arc<Transport> = match self.proxy {
+            Some(ref proxy) => {
+                if self.base_url.starts_with("https:") {
+                    return Err(Error::InvalidProxy(
+                        "HTTPS requests can't be sent through a proxy".to_string(),
+                    ));
+                }
+                let connector = ProxyConnector::new(connector, proxy)?;
+                Arc::new(builder.build::<_, Body>(connector))
+            }
+            None => Arc::new(builder.build::<_, Body>(connector)),
+        };
+
+        match fixtures {
+            Some(Fixtures::Record(dir)) => {
+                self.build_with_transport(Arc::new(Recorder::new(transport, dir)))
+            }
+            _ => self.build_with_transport(transport),
+        }
+    }
+
+    fn build_with_transport(self, transport: Arc<Transport>) -> Result<Client, Error> {
+        Ok(Client {
+            transport,
+            base_url: self.base_url,
+            user_agent: HeaderValue::from_str(&self.user_agent)?,
+            timeout: self.timeout,
+            limiter: self.limiter,
+            timer: Timer::new(),
+            base_backoff: self.base_backoff,
+            cache: self.cache,
+            cache_policy: self.cache_policy,
+            cache_counters: Arc::new(CacheCounters::default()),
+            show_updates: Arc::new(ShowUpdates::default()),
+        })
+    }
+}
+
+impl Default for ClientBuilder {
+    fn default() -> ClientBuilder {
+        ClientBuilder::new()
+    }
+}
+
+/// Fails with `Error::Timeout` if `request` hasn't resolved after `timeout`.
+/// Whichever finishes first drops the other, which takes the timeout off
+/// `timer` once the response is in.
+fn with_timeout(
+    request: QueryFuture<Response<Body>>,
+    timeout: Option<Duration>,
+    timer: &Timer,
+) -> QueryFuture<Response<Body>> {
+    match timeout {
+        Some(timeout) => {
+            let expired = timer.delay(timeout).then(|_| Err(Error::Timeout));
+            Box::new(request.select(expired).map(|(res, _)| res).map_err(|(e, _)| e))
+        }
+        None => request,
+    }
+}
+
+/// Fails with the error for an unsuccessful response, which keeps its body.
+fn read_error<R: Send + 'static>(res: Response<Body>) -> QueryFuture<R> {
+    let status = res.status().as_u16();
+    let retry_after = retry_after(&res);
+
+    Box::new(
+        res.into_body()
+            .concat2()
+            .map_err(Error::from)
+            .and_then(move |body| {
+                let body = String::from_utf8_lossy(&body).into_owned();
+                Err(Error::from_response(status, retry_after, body))
+            }),
+    )
+}
+
+enum CacheControl {
+    NoStore,
+    MaxAge(Duration),
+    Unspecified,
+}
+
+/// Whether a response may be stored and for how long. `no-cache` allows
+/// storing the response, but only to revalidate it on every request.
+fn cache_control(headers: &HeaderMap) -> CacheControl {
+    let directives = headers
+        .get_all(CACHE_CONTROL)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(|directive| directive.trim().to_lowercase())
+        .collect::<Vec<_>>();
+
+    if directives.iter().any(|d| d == "no-store" || d == "private") {
+        return CacheControl::NoStore;
+    }
+    if directives.iter().any(|d| d == "no-cache") {
+        return CacheControl::MaxAge(Duration::from_secs(0));
+    }
+    directives
+        .iter()
+        .filter_map(|d| {
+            if d.starts_with("max-age=") {
+                d["max-age=".len()..].parse().ok()
+            } else {
+                None
+            }
+        })
+        .next()
+        .map_or(CacheControl::Unspecified, |secs| {
+            CacheControl::MaxAge(Duration::from_secs(secs))
+        })
 }
 
-fn create_request(
+/// The wait a 429 or 503 asks for in its `Retry-After` header. Only the
+/// number of seconds is understood, which is what TVmaze sends.
+fn retry_after(res: &Response<Body>) -> Option<Duration> {
+    res.headers()
+        .get("Retry-After")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.trim().parse().ok())
+        .map(Duration::from_secs)
+}
+
+fn create_url(
+    base_url: &str,
     path: &str,
     query_params: Option<Vec<(&str, &str)>>,
     embed: Option<LinkEmbed>,
-) -> Result<Request<Body>, Error> {
-    let url = if query_params.is_some() || embed.is_some() {
+) -> String {
+    if query_params.is_some() || embed.is_some() {
         let mut serializer = Serializer::new(String::new());
 
         if let Some(query_params) = query_params {
@@ -316,18 +793,18 @@ fn create_request(
             }
         }
 
-        [TVMAZE_BASE_URL, path, "?", &serializer.finish()].join("")
+        [base_url, path, "?", &serializer.finish()].join("")
     } else {
-        [TVMAZE_BASE_URL, path].join("")
-    };
-
-    Request::get(url).body(Body::empty()).map_err(Error::from)
+        [base_url, path].join("")
+    }
 }
 
 #[cfg(feature = "tls")]
-fn url_to_https(url: &str) -> Result<Uri, Error> {
+fn url_to_https(url: &str) -> Result<String, Error> {
     let uri: Uri = url.parse()?;
     let mut parts: Parts = uri.into();
     parts.scheme = Some(Scheme::HTTPS);
-    Uri::from_parts(parts).map_err(Error::from)
+    Uri::from_parts(parts)
+        .map(|uri| uri.to_string())
+        .map_err(Error::from)
 }
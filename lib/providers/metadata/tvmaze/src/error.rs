@@ -1,23 +1,69 @@
+use std::io;
+use std::path::PathBuf;
+use std::time::Duration;
+
 use http;
+use http::header::InvalidHeaderValue;
 #[cfg(feature = "tls")]
 use http::uri::{InvalidUri, InvalidUriParts};
 use hyper;
 use hyper::header::ToStrError;
+#[cfg(feature = "tls")]
+use hyper_tls;
 use serde_json;
 
 #[derive(Debug)]
 pub enum Error {
+    /// The directory of a disk cache can't be created.
+    CacheError(io::Error),
     #[cfg(feature = "tls")]
     ConvertingLocationFailed(InvalidUriParts),
     DeserializationError(serde_json::Error),
+    /// A fixture can't be saved while recording.
+    FixtureError(io::Error),
     HTTPHeaderConversionError(ToStrError),
     HTTPError(http::Error),
     HTTPClientError(hyper::Error),
+    /// The user agent can't be sent as a header.
+    InvalidUserAgent(InvalidHeaderValue),
+    /// The proxy isn't an `http://` URL, or can't be used for the base URL.
+    InvalidProxy(String),
     MissingLocationHeader,
+    /// No fixture was recorded for a replayed request.
+    MissingFixture(PathBuf),
+    /// A 404 from any request but a show lookup, with the response's body.
+    NotFound(String),
     #[cfg(feature = "tls")]
     ParsingLocationFailed(InvalidUri),
+    /// Still answered with a 429 after retrying, with the wait the last
+    /// response asked for.
+    RateLimited { retry_after: Option<Duration> },
+    /// A 4xx status other than 404 or 429, with the response's body.
+    Client(u16, String),
+    /// Still answered with a 5xx status after retrying, with the last
+    /// response's body.
+    Server(u16, String),
+    /// No show matched a lookup.
     ShowNotFound,
-    UnexpectedResponse,
+    /// No response arrived within the client's timeout.
+    Timeout,
+    #[cfg(feature = "tls")]
+    TLSError(hyper_tls::Error),
+    /// Any other unsuccessful status, with the response's body.
+    UnexpectedResponse(u16, String),
+}
+
+impl Error {
+    /// The error for a response with an unsuccessful `status`.
+    pub fn from_response(status: u16, retry_after: Option<Duration>, body: String) -> Error {
+        match status {
+            404 => Error::NotFound(body),
+            429 => Error::RateLimited { retry_after },
+            400..=499 => Error::Client(status, body),
+            500..=599 => Error::Server(status, body),
+            _ => Error::UnexpectedResponse(status, body),
+        }
+    }
 }
 
 impl From<http::Error> for Error {
@@ -38,6 +84,19 @@ impl From<serde_json::Error> for Error {
     }
 }
 
+impl From<InvalidHeaderValue> for Error {
+    fn from(error: InvalidHeaderValue) -> Self {
+        Error::InvalidUserAgent(error)
+    }
+}
+
+#[cfg(feature = "tls")]
+impl From<hyper_tls::Error> for Error {
+    fn from(error: hyper_tls::Error) -> Self {
+        Error::TLSError(error)
+    }
+}
+
 impl From<ToStrError> for Error {
     fn from(error: ToStrError) -> Self {
         Error::HTTPHeaderConversionError(error)
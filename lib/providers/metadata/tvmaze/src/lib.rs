@@ -1,4 +1,4 @@
-#[macro_use]
+extern crate chrono;
 extern crate futures;
 extern crate http;
 extern crate hyper;
@@ -12,14 +12,11 @@ extern crate tokio;
 extern crate url;
 
 pub mod api;
+pub mod cache;
 pub mod client;
 pub mod error;
+pub mod proxy;
+pub mod rate_limit;
+pub mod replay;
 pub mod resources;
-
-#[cfg(test)]
-mod tests {
-    #[test]
-    fn it_works() {
-        assert_eq!(2 + 2, 4);
-    }
-}
+pub mod timer;
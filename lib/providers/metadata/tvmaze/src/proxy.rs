@@ -0,0 +1,57 @@
+use error::Error;
+
+use futures::future::Map;
+use futures::Future;
+use hyper::client::connect::{Connect, Connected, Destination};
+use hyper::Uri;
+
+/// Connects to an HTTP proxy instead of the requested host. The connection is
+/// marked as proxied, so that hyper sends the full URL in the request line
+/// and the proxy knows where to forward it.
+pub struct ProxyConnector<C> {
+    connector: C,
+    host: String,
+    port: Option<u16>,
+}
+
+impl<C> ProxyConnector<C> {
+    /// Wraps `connector` to connect to the proxy at `proxy`, an `http://`
+    /// URL with a host and an optional port.
+    pub fn new(connector: C, proxy: &str) -> Result<ProxyConnector<C>, Error> {
+        let uri: Uri = proxy
+            .parse()
+            .map_err(|_| Error::InvalidProxy(format!("{} is not a URL", proxy)))?;
+        if uri.scheme_part().map(|s| s.as_str()) != Some("http") {
+            return Err(Error::InvalidProxy(format!(
+                "{} is not an http:// URL",
+                proxy
+            )));
+        }
+        let host = uri
+            .host()
+            .ok_or_else(|| Error::InvalidProxy(format!("{} has no host", proxy)))?;
+
+        Ok(ProxyConnector {
+            connector,
+            host: host.to_string(),
+            port: uri.port_part().map(|p| p.as_u16()),
+        })
+    }
+}
+
+impl<C: Connect> Connect for ProxyConnector<C> {
+    type Transport = C::Transport;
+    type Error = C::Error;
+    type Future = Map<C::Future, fn((C::Transport, Connected)) -> (C::Transport, Connected)>;
+
+    fn connect(&self, mut dst: Destination) -> Self::Future {
+        dst.set_host(&self.host)
+            .expect("The proxy's host was parsed from a URL");
+        dst.set_port(self.port);
+        self.connector.connect(dst).map(proxied)
+    }
+}
+
+fn proxied<T>((transport, connected): (T, Connected)) -> (T, Connected) {
+    (transport, connected.proxy(true))
+}
@@ -0,0 +1,154 @@
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use timer::Timer;
+
+use futures::{future, Future};
+
+/// TVmaze allows at least 20 calls every 10 seconds per IP address.
+pub const DEFAULT_CALLS: u32 = 20;
+pub const DEFAULT_PERIOD_SECS: u64 = 10;
+
+/// A token bucket that holds up to `calls` tokens and refills them evenly
+/// over `period`. Every request takes a token, waiting for one if the bucket
+/// is empty. Clones share the same bucket.
+#[derive(Clone)]
+pub struct RateLimiter {
+    bucket: Arc<Mutex<Bucket>>,
+}
+
+struct Bucket {
+    capacity: f64,
+    /// Tokens refilled per second.
+    rate: f64,
+    /// Can go below zero, when requests have reserved tokens that haven't
+    /// been refilled yet.
+    tokens: f64,
+    refilled: Instant,
+}
+
+impl RateLimiter {
+    pub fn new(calls: u32, period: Duration) -> RateLimiter {
+        RateLimiter {
+            bucket: Arc::new(Mutex::new(Bucket::new(calls, period, Instant::now()))),
+        }
+    }
+
+    /// Resolves once a request may be sent, waiting on `timer`.
+    pub fn acquire(&self, timer: &Timer) -> Box<Future<Item = (), Error = ()> + Send> {
+        let wait = self.bucket.lock().unwrap().take(Instant::now());
+        if wait == Duration::from_secs(0) {
+            Box::new(future::ok(()))
+        } else {
+            Box::new(timer.delay(wait))
+        }
+    }
+}
+
+impl Default for RateLimiter {
+    fn default() -> RateLimiter {
+        RateLimiter::new(DEFAULT_CALLS, Duration::from_secs(DEFAULT_PERIOD_SECS))
+    }
+}
+
+impl Bucket {
+    fn new(calls: u32, period: Duration, now: Instant) -> Bucket {
+        let capacity = f64::from(calls.max(1));
+        Bucket {
+            capacity,
+            rate: capacity / as_secs_f64(period).max(0.001),
+            tokens: capacity,
+            refilled: now,
+        }
+    }
+
+    /// Takes a token and returns how long to wait until it is available.
+    fn take(&mut self, now: Instant) -> Duration {
+        if now > self.refilled {
+            let elapsed = as_secs_f64(now - self.refilled);
+            self.tokens = (self.tokens + elapsed * self.rate).min(self.capacity);
+            self.refilled = now;
+        }
+
+        self.tokens -= 1.0;
+        if self.tokens >= 0.0 {
+            Duration::from_secs(0)
+        } else {
+            from_secs_f64(-self.tokens / self.rate)
+        }
+    }
+}
+
+/// The time to wait before retry number `attempt`, counting from zero: `base`
+/// doubled for every attempt, of which the second half is random so that
+/// clients that were limited together don't all retry at the same moment.
+pub fn backoff(base: Duration, attempt: u32) -> Duration {
+    let full = base * 2u32.pow(attempt.min(16));
+    let half = full / 2;
+    half + jitter(half)
+}
+
+/// A pseudo random duration up to `max`. Taken from the clock, which is good
+/// enough to spread out retries.
+fn jitter(max: Duration) -> Duration {
+    let max_nanos = max.as_secs() * 1_000_000_000 + u64::from(max.subsec_nanos());
+    if max_nanos == 0 {
+        return max;
+    }
+
+    let seed = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| u64::from(d.subsec_nanos()))
+        .unwrap_or(0);
+    let nanos = seed.wrapping_mul(6_364_136_223_846_793_005) % max_nanos;
+    Duration::new(nanos / 1_000_000_000, (nanos % 1_000_000_000) as u32)
+}
+
+fn as_secs_f64(d: Duration) -> f64 {
+    d.as_secs() as f64 + f64::from(d.subsec_nanos()) / 1e9
+}
+
+fn from_secs_f64(secs: f64) -> Duration {
+    Duration::new(secs.trunc() as u64, (secs.fract() * 1e9) as u32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lets_bursts_through_and_then_spaces_out_requests() {
+        let start = Instant::now();
+        let mut bucket = Bucket::new(2, Duration::from_secs(1), start);
+
+        assert_eq!(bucket.take(start), Duration::from_secs(0));
+        assert_eq!(bucket.take(start), Duration::from_secs(0));
+        assert_eq!(bucket.take(start), Duration::from_millis(500));
+        assert_eq!(bucket.take(start), Duration::from_millis(1000));
+
+        // Two tokens were reserved ahead, so a second later the bucket is
+        // empty again rather than full.
+        let later = start + Duration::from_secs(1);
+        assert_eq!(bucket.take(later), Duration::from_millis(500));
+    }
+
+    #[test]
+    fn never_holds_more_than_its_capacity() {
+        let start = Instant::now();
+        let mut bucket = Bucket::new(1, Duration::from_secs(1), start);
+
+        let later = start + Duration::from_secs(60);
+        assert_eq!(bucket.take(later), Duration::from_secs(0));
+        assert_eq!(bucket.take(later), Duration::from_secs(1));
+    }
+
+    #[test]
+    fn backs_off_exponentially_with_jitter() {
+        let base = Duration::from_millis(100);
+        for attempt in 0..4 {
+            let full = base * 2u32.pow(attempt);
+            let wait = backoff(base, attempt);
+            assert!(wait >= full / 2 && wait <= full, "{:?} for {}", wait, attempt);
+        }
+    }
+}
@@ -0,0 +1,201 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use client::{QueryFuture, Transport};
+use error::Error;
+
+use futures::{future, Future, Stream};
+use hyper::header::HeaderMap;
+use hyper::{Body, Request, Response, Uri};
+use serde_json::{self, Value};
+
+/// The response headers that the client looks at. Others, such as the date,
+/// would only make fixtures change whenever they are recorded again.
+const RECORDED_HEADERS: &[&str] = &[
+    "cache-control",
+    "content-type",
+    "etag",
+    "last-modified",
+    "location",
+    "retry-after",
+];
+
+/// A request with its response, saved as `<method>_<path>_<query>.json`.
+#[derive(Debug, Deserialize, Serialize)]
+struct Fixture {
+    method: String,
+    /// Only the path and query, so that fixtures can be replayed whatever the
+    /// base URL is.
+    uri: String,
+    status: u16,
+    headers: BTreeMap<String, String>,
+    /// JSON bodies are kept as they are so that they can be read and edited,
+    /// empty ones are null, and anything else is a string.
+    body: Value,
+}
+
+/// Sends requests with another transport and saves every response as a
+/// fixture, replacing one that was recorded for the same request before.
+pub struct Recorder {
+    transport: Arc<Transport>,
+    dir: PathBuf,
+}
+
+impl Recorder {
+    pub fn new<P: Into<PathBuf>>(transport: Arc<Transport>, dir: P) -> Recorder {
+        Recorder {
+            transport,
+            dir: dir.into(),
+        }
+    }
+}
+
+impl Transport for Recorder {
+    fn send(&self, req: Request<Body>) -> QueryFuture<Response<Body>> {
+        let method = req.method().to_string();
+        let uri = path_and_query(req.uri());
+        let dir = self.dir.clone();
+
+        Box::new(self.transport.send(req).and_then(move |res| {
+            let (parts, body) = res.into_parts();
+            body.concat2().map_err(Error::from).and_then(move |body| {
+                let fixture = Fixture {
+                    status: parts.status.as_u16(),
+                    headers: recorded_headers(&parts.headers),
+                    body: body_to_value(&body),
+                    method,
+                    uri,
+                };
+                save(&dir, &fixture)?;
+                Ok(Response::from_parts(parts, Body::from(body.to_vec())))
+            })
+        }))
+    }
+}
+
+/// Answers requests with recorded fixtures, without a network. Requests that
+/// weren't recorded fail with `Error::MissingFixture`.
+pub struct Replayer {
+    dir: PathBuf,
+}
+
+impl Replayer {
+    pub fn new<P: Into<PathBuf>>(dir: P) -> Replayer {
+        Replayer { dir: dir.into() }
+    }
+}
+
+impl Transport for Replayer {
+    fn send(&self, req: Request<Body>) -> QueryFuture<Response<Body>> {
+        let method = req.method().to_string();
+        let uri = path_and_query(req.uri());
+        Box::new(future::result(
+            load(&self.dir, &method, &uri).and_then(into_response),
+        ))
+    }
+}
+
+fn path_and_query(uri: &Uri) -> String {
+    uri.path_and_query()
+        .map_or_else(|| uri.path().to_string(), |p| p.as_str().to_string())
+}
+
+/// Names a request's fixture after its method, path and query, with every
+/// run of other characters than letters and digits turned into a `_`.
+fn fixture_path(dir: &Path, method: &str, uri: &str) -> PathBuf {
+    let mut name = method.to_lowercase();
+    for c in uri.chars() {
+        if c.is_ascii_alphanumeric() {
+            name.push(c.to_ascii_lowercase());
+        } else if !name.ends_with('_') {
+            name.push('_');
+        }
+    }
+    dir.join(format!("{}.json", name.trim_right_matches('_')))
+}
+
+fn recorded_headers(headers: &HeaderMap) -> BTreeMap<String, String> {
+    RECORDED_HEADERS
+        .iter()
+        .filter_map(|name| {
+            let value = headers.get(*name)?.to_str().ok()?;
+            Some((name.to_string(), value.to_string()))
+        })
+        .collect()
+}
+
+fn body_to_value(body: &[u8]) -> Value {
+    if body.is_empty() {
+        Value::Null
+    } else {
+        serde_json::from_slice(body)
+            .unwrap_or_else(|_| Value::String(String::from_utf8_lossy(body).into_owned()))
+    }
+}
+
+fn save(dir: &Path, fixture: &Fixture) -> Result<(), Error> {
+    let json = serde_json::to_vec_pretty(fixture)?;
+    fs::create_dir_all(dir)
+        .and_then(|_| fs::write(fixture_path(dir, &fixture.method, &fixture.uri), json))
+        .map_err(Error::FixtureError)
+}
+
+fn load(dir: &Path, method: &str, uri: &str) -> Result<Fixture, Error> {
+    let path = fixture_path(dir, method, uri);
+    let file = fs::File::open(&path).map_err(|_| Error::MissingFixture(path.clone()))?;
+    let fixture: Fixture = serde_json::from_reader(file)?;
+
+    // Different requests can share a name, but only one of them is recorded.
+    if fixture.method != method || fixture.uri != uri {
+        return Err(Error::MissingFixture(path));
+    }
+    Ok(fixture)
+}
+
+fn into_response(fixture: Fixture) -> Result<Response<Body>, Error> {
+    let body = match fixture.body {
+        Value::Null => Body::empty(),
+        Value::String(text) => Body::from(text),
+        json => Body::from(json.to_string()),
+    };
+
+    let mut res = Response::builder();
+    res.status(fixture.status);
+    for (name, value) in &fixture.headers {
+        res.header(name.as_str(), value.as_str());
+    }
+    res.body(body).map_err(Error::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_fixtures_after_their_request() {
+        let dir = Path::new("fixtures");
+        assert_eq!(
+            fixture_path(dir, "GET", "/shows/169/episodebynumber?season=1&number=1"),
+            dir.join("get_shows_169_episodebynumber_season_1_number_1.json")
+        );
+        assert_eq!(
+            fixture_path(dir, "GET", "/search/shows?q=breaking+bad"),
+            dir.join("get_search_shows_q_breaking_bad.json")
+        );
+    }
+
+    #[test]
+    fn keeps_json_bodies_readable() {
+        assert_eq!(body_to_value(b""), Value::Null);
+        assert_eq!(
+            body_to_value(b"{\"id\":1}"),
+            serde_json::from_str::<Value>("{\"id\":1}").unwrap()
+        );
+        assert_eq!(
+            body_to_value(b"Not JSON"),
+            Value::String("Not JSON".to_string())
+        );
+    }
+}
@@ -0,0 +1,176 @@
+//! Deserializers for the dates and times that TVmaze sends as strings. Unknown
+//! dates and times are sent as either an empty string or null, so both are
+//! read as `None`.
+
+use chrono::{DateTime, FixedOffset, NaiveDate, NaiveTime};
+use serde::de::{Deserialize, Deserializer, Error};
+
+/// An enum of the values TVmaze documents for a field, which keeps any other
+/// value in `Unknown` instead of failing, since TVmaze adds new ones now and
+/// then.
+macro_rules! string_enum {
+    (
+        $(#[$meta:meta])*
+        pub enum $name:ident {
+            $($variant:ident => $text:expr,)*
+        }
+    ) => {
+        $(#[$meta])*
+        #[derive(Clone, Debug, PartialEq)]
+        pub enum $name {
+            $($variant,)*
+            Unknown(String),
+        }
+
+        impl $name {
+            /// The value as TVmaze sends it.
+            pub fn as_str(&self) -> &str {
+                match *self {
+                    $($name::$variant => $text,)*
+                    $name::Unknown(ref value) => value,
+                }
+            }
+        }
+
+        impl<'de> ::serde::Deserialize<'de> for $name {
+            fn deserialize<D>(deserializer: D) -> Result<$name, D::Error>
+            where
+                D: ::serde::Deserializer<'de>,
+            {
+                let value = <String as ::serde::Deserialize>::deserialize(deserializer)?;
+                $(
+                    if value == $text {
+                        return Ok($name::$variant);
+                    }
+                )*
+                Ok($name::Unknown(value))
+            }
+        }
+    };
+}
+
+/// Reads a string that is empty or null when TVmaze doesn't know a value.
+fn non_empty<'de, D>(deserializer: D) -> Result<Option<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let value: Option<String> = Option::deserialize(deserializer)?;
+    Ok(value.filter(|value| !value.trim().is_empty()))
+}
+
+/// A date such as `2008-01-20`.
+pub fn optional_date<'de, D>(deserializer: D) -> Result<Option<NaiveDate>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    non_empty(deserializer)?
+        .map(|date| NaiveDate::parse_from_str(&date, "%Y-%m-%d").map_err(D::Error::custom))
+        .transpose_result()
+}
+
+/// A time of day such as `22:00`, in the timezone of the show's network.
+pub fn optional_time<'de, D>(deserializer: D) -> Result<Option<NaiveTime>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    non_empty(deserializer)?
+        .map(|time| NaiveTime::parse_from_str(&time, "%H:%M").map_err(D::Error::custom))
+        .transpose_result()
+}
+
+/// A timestamp such as `2008-01-21T03:00:00+00:00`.
+pub fn optional_timestamp<'de, D>(
+    deserializer: D,
+) -> Result<Option<DateTime<FixedOffset>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    non_empty(deserializer)?
+        .map(|stamp| DateTime::parse_from_rfc3339(&stamp).map_err(D::Error::custom))
+        .transpose_result()
+}
+
+/// `Option::transpose`, which isn't stable yet.
+trait TransposeResult<T, E> {
+    fn transpose_result(self) -> Result<Option<T>, E>;
+}
+
+impl<T, E> TransposeResult<T, E> for Option<Result<T, E>> {
+    fn transpose_result(self) -> Result<Option<T>, E> {
+        match self {
+            Some(Ok(value)) => Ok(Some(value)),
+            Some(Err(e)) => Err(e),
+            None => Ok(None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use resources::{ShowStatus, ShowType, TVShow, TVShowSeason, TVShowSchedule};
+    use serde_json;
+
+    use chrono::{NaiveDate, NaiveTime};
+
+    fn season(premiere_date: &str, end_date: &str) -> String {
+        format!(
+            r#"{{"id": 648, "url": "http://www.tvmaze.com/seasons/648", "number": 3,
+                "name": "", "episodeOrder": null, "premiereDate": {},
+                "endDate": {}, "network": null, "webChannel": null, "image": null,
+                "summary": null}}"#,
+            premiere_date, end_date
+        )
+    }
+
+    #[test]
+    fn reads_empty_and_null_dates_as_none() {
+        let s: TVShowSeason = serde_json::from_str(&season("null", r#""""#)).unwrap();
+        assert_eq!(s.premiere_date, None);
+        assert_eq!(s.end_date, None);
+
+        let s: TVShowSeason =
+            serde_json::from_str(&season(r#""2010-03-21""#, r#""2010-06-13""#)).unwrap();
+        assert_eq!(s.premiere_date, Some(NaiveDate::from_ymd(2010, 3, 21)));
+        assert_eq!(s.end_date, Some(NaiveDate::from_ymd(2010, 6, 13)));
+    }
+
+    #[test]
+    fn fails_on_malformed_dates_and_times() {
+        assert!(serde_json::from_str::<TVShowSeason>(&season(r#""21.03.2010""#, "null")).is_err());
+        assert!(
+            serde_json::from_str::<TVShowSchedule>(r#"{"time": "10pm", "days": []}"#).is_err()
+        );
+
+        let schedule: TVShowSchedule =
+            serde_json::from_str(r#"{"time": "21:30", "days": []}"#).unwrap();
+        assert_eq!(schedule.time, Some(NaiveTime::from_hms(21, 30, 0)));
+    }
+
+    #[test]
+    fn reads_shows_that_leave_out_everything_they_can() {
+        // As found in the index for shows that barely have any details.
+        let show: TVShow = serde_json::from_str(
+            r#"{"id": 1, "url": "http://www.tvmaze.com/shows/1/x", "name": "X",
+                "type": "Scripted", "language": null, "genres": [], "status": "Ended",
+                "runtime": null, "premiered": null, "officialSite": null,
+                "schedule": {"time": "", "days": []}, "rating": {"average": null},
+                "weight": 0, "network": null, "webChannel": null,
+                "externals": {"tvrage": null, "thetvdb": null, "imdb": null},
+                "image": null, "summary": null, "updated": 1530131455}"#,
+        ).unwrap();
+        assert_eq!(show.language, None);
+        assert_eq!(show._type, ShowType::Scripted);
+        assert_eq!(show.premiered, None);
+        assert_eq!(show.schedule.time, None);
+    }
+
+    #[test]
+    fn keeps_unknown_enum_values() {
+        let status: ShowStatus = serde_json::from_str(r#""To Be Determined""#).unwrap();
+        assert_eq!(status, ShowStatus::ToBeDetermined);
+
+        let status: ShowStatus = serde_json::from_str(r#""Cancelled""#).unwrap();
+        assert_eq!(status, ShowStatus::Unknown("Cancelled".to_string()));
+        assert_eq!(status.as_str(), "Cancelled");
+    }
+}
@@ -1,5 +1,8 @@
+use resources::de;
 use resources::general::*;
 
+use chrono::{DateTime, FixedOffset, NaiveDate, NaiveTime};
+
 #[derive(Debug, Deserialize)]
 pub struct Episode {
     pub id: u64,
@@ -7,9 +10,12 @@ pub struct Episode {
     pub name: String,
     pub season: u64,
     pub number: Option<u64>,
-    pub airdate: String,
-    pub airtime: String,
-    pub airstamp: Option<String>,
+    #[serde(default, deserialize_with = "de::optional_date")]
+    pub airdate: Option<NaiveDate>,
+    #[serde(default, deserialize_with = "de::optional_time")]
+    pub airtime: Option<NaiveTime>,
+    #[serde(default, deserialize_with = "de::optional_timestamp")]
+    pub airstamp: Option<DateTime<FixedOffset>>,
     pub runtime: Option<u64>,
     pub image: Option<ImageLinks>,
     pub summary: Option<String>,
@@ -1,4 +1,4 @@
-use resources::{CastEmbed, Episode};
+use resources::{CastEmbed, CrewPerson, Episode, TVShowSeason};
 
 #[derive(Debug, Deserialize)]
 pub struct AKA {
@@ -19,8 +19,15 @@ pub struct ImageLinks {
     pub original: String,
 }
 
+/// The resources that were asked to be embedded with `LinkEmbed`. Which of
+/// them can be embedded depends on the endpoint.
 #[derive(Debug, Deserialize)]
 pub struct Embed {
+    pub akas: Option<Vec<AKA>>,
     pub cast: Option<Vec<CastEmbed>>,
+    pub crew: Option<Vec<CrewPerson>>,
     pub episodes: Option<Vec<Episode>>,
+    pub seasons: Option<Vec<TVShowSeason>>,
+    pub nextepisode: Option<Box<Episode>>,
+    pub previousepisode: Option<Box<Episode>>,
 }
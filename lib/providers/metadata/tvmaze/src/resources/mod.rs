@@ -3,6 +3,8 @@ pub use self::general::*;
 pub use self::person::*;
 pub use self::tv_show::*;
 
+#[macro_use]
+mod de;
 pub mod episode;
 pub mod general;
 pub mod person;
@@ -1,5 +1,8 @@
+use resources::de;
 use resources::general::*;
 
+use chrono::NaiveDate;
+
 pub type CastPerson = CastEmbed;
 
 #[derive(Debug, Deserialize)]
@@ -21,8 +24,10 @@ pub struct Person {
     pub url: String,
     pub name: String,
     pub country: Option<Country>,
-    pub birthday: Option<String>,
-    pub deathday: Option<String>,
+    #[serde(default, deserialize_with = "de::optional_date")]
+    pub birthday: Option<NaiveDate>,
+    #[serde(default, deserialize_with = "de::optional_date")]
+    pub deathday: Option<NaiveDate>,
     pub gender: Option<String>,
     pub image: Option<ImageLinks>,
 }
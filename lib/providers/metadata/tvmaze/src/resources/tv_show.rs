@@ -1,5 +1,46 @@
+use resources::de;
 use resources::general::*;
 
+use chrono::{NaiveDate, NaiveTime};
+
+string_enum! {
+    /// Whether a show still airs new episodes.
+    pub enum ShowStatus {
+        Running => "Running",
+        Ended => "Ended",
+        ToBeDetermined => "To Be Determined",
+        InDevelopment => "In Development",
+    }
+}
+
+string_enum! {
+    pub enum ShowType {
+        Scripted => "Scripted",
+        Animation => "Animation",
+        Reality => "Reality",
+        TalkShow => "Talk Show",
+        Documentary => "Documentary",
+        GameShow => "Game Show",
+        News => "News",
+        Sports => "Sports",
+        Variety => "Variety",
+        AwardShow => "Award Show",
+        PanelShow => "Panel Show",
+    }
+}
+
+string_enum! {
+    pub enum Weekday {
+        Monday => "Monday",
+        Tuesday => "Tuesday",
+        Wednesday => "Wednesday",
+        Thursday => "Thursday",
+        Friday => "Friday",
+        Saturday => "Saturday",
+        Sunday => "Sunday",
+    }
+}
+
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct TVShow {
@@ -7,12 +48,13 @@ pub struct TVShow {
     pub url: String,
     pub name: String,
     #[serde(rename = "type")]
-    pub _type: String,
-    pub language: String,
+    pub _type: ShowType,
+    pub language: Option<String>,
     pub genres: Vec<String>,
-    pub status: String,
+    pub status: ShowStatus,
     pub runtime: Option<u64>,
-    pub premiered: Option<String>,
+    #[serde(default, deserialize_with = "de::optional_date")]
+    pub premiered: Option<NaiveDate>,
     pub official_site: Option<String>,
     pub schedule: TVShowSchedule,
     pub rating: TVShowRating,
@@ -35,8 +77,10 @@ pub struct TVShowSeason {
     pub number: u64,
     pub name: String,
     pub episode_order: Option<u64>,
-    pub premiere_date: String,
-    pub end_date: String,
+    #[serde(default, deserialize_with = "de::optional_date")]
+    pub premiere_date: Option<NaiveDate>,
+    #[serde(default, deserialize_with = "de::optional_date")]
+    pub end_date: Option<NaiveDate>,
     pub network: Option<TVShowNetwork>,
     pub web_channel: Option<TVShowWebChannel>,
     pub image: Option<ImageLinks>,
@@ -45,8 +89,9 @@ pub struct TVShowSeason {
 
 #[derive(Debug, Deserialize)]
 pub struct TVShowSchedule {
-    pub time: String,
-    pub days: Vec<String>,
+    #[serde(default, deserialize_with = "de::optional_time")]
+    pub time: Option<NaiveTime>,
+    pub days: Vec<Weekday>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -74,3 +119,25 @@ pub struct TVShowExternals {
     pub thetvdb: Option<u64>,
     pub imdb: Option<String>,
 }
+
+#[derive(Debug, Deserialize)]
+pub struct ShowImage {
+    pub id: u64,
+    #[serde(rename = "type")]
+    pub _type: Option<String>,
+    pub main: bool,
+    pub resolutions: ShowImageResolutions,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ShowImageResolutions {
+    pub original: ShowImageResolution,
+    pub medium: Option<ShowImageResolution>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ShowImageResolution {
+    pub url: String,
+    pub width: Option<u64>,
+    pub height: Option<u64>,
+}
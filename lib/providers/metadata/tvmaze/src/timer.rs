@@ -0,0 +1,181 @@
+use std::collections::BTreeMap;
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use futures::sync::oneshot;
+use futures::{Future, Poll};
+
+/// Resolves delays on a single thread that sleeps until the next one is due,
+/// rather than on a Tokio timer, since the client is also used from event
+/// loops that don't have one. Clones share the thread, which exits once every
+/// clone is dropped and the delays they started are resolved or dropped.
+pub struct Timer {
+    shared: Arc<Shared>,
+}
+
+struct Shared {
+    state: Mutex<State>,
+    changed: Condvar,
+}
+
+struct State {
+    /// The senders of the pending delays by when they're due, along with an
+    /// id that tells apart delays that are due at the same time.
+    pending: BTreeMap<(Instant, u64), oneshot::Sender<()>>,
+    next_id: u64,
+    /// How many clones of the timer are left.
+    handles: usize,
+}
+
+/// Resolves once its time is up. Dropping it before then takes it off the
+/// timer.
+pub struct Delay {
+    key: (Instant, u64),
+    rx: oneshot::Receiver<()>,
+    shared: Arc<Shared>,
+}
+
+impl Timer {
+    pub fn new() -> Timer {
+        let shared = Arc::new(Shared {
+            state: Mutex::new(State {
+                pending: BTreeMap::new(),
+                next_id: 0,
+                handles: 1,
+            }),
+            changed: Condvar::new(),
+        });
+
+        let thread_shared = shared.clone();
+        thread::spawn(move || run(&thread_shared));
+
+        Timer { shared }
+    }
+
+    /// Resolves after `duration`.
+    pub fn delay(&self, duration: Duration) -> Delay {
+        let (tx, rx) = oneshot::channel();
+        let mut state = self.shared.state.lock().unwrap();
+        let key = (Instant::now() + duration, state.next_id);
+        state.next_id += 1;
+        state.pending.insert(key, tx);
+        self.shared.changed.notify_one();
+
+        Delay {
+            key,
+            rx,
+            shared: self.shared.clone(),
+        }
+    }
+}
+
+impl Default for Timer {
+    fn default() -> Timer {
+        Timer::new()
+    }
+}
+
+impl Clone for Timer {
+    fn clone(&self) -> Timer {
+        self.shared.state.lock().unwrap().handles += 1;
+        Timer {
+            shared: self.shared.clone(),
+        }
+    }
+}
+
+impl Drop for Timer {
+    fn drop(&mut self) {
+        self.shared.state.lock().unwrap().handles -= 1;
+        self.shared.changed.notify_one();
+    }
+}
+
+impl Future for Delay {
+    type Item = ();
+    type Error = ();
+
+    fn poll(&mut self) -> Poll<(), ()> {
+        self.rx.poll().map_err(|_| ())
+    }
+}
+
+impl Drop for Delay {
+    fn drop(&mut self) {
+        self.shared.state.lock().unwrap().pending.remove(&self.key);
+        self.shared.changed.notify_one();
+    }
+}
+
+/// Resolves the delays that are due and sleeps until the next one is, or
+/// until a delay is added or dropped.
+fn run(shared: &Shared) {
+    let mut state = shared.state.lock().unwrap();
+    loop {
+        let now = Instant::now();
+        let due: Vec<_> = state
+            .pending
+            .keys()
+            .take_while(|&&(at, _)| at <= now)
+            .cloned()
+            .collect();
+        for key in due {
+            if let Some(tx) = state.pending.remove(&key) {
+                let _ = tx.send(());
+            }
+        }
+
+        let next = state.pending.keys().next().map(|&(at, _)| at);
+        state = match next {
+            Some(at) => shared.changed.wait_timeout(state, at - now).unwrap().0,
+            None if state.handles == 0 => return,
+            None => shared.changed.wait(state).unwrap(),
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pending(timer: &Timer) -> usize {
+        timer.shared.state.lock().unwrap().pending.len()
+    }
+
+    #[test]
+    fn wakes_up_for_delays_that_are_due_sooner() {
+        let timer = Timer::new();
+        let _later = timer.delay(Duration::from_secs(60));
+
+        let start = Instant::now();
+        timer.delay(Duration::from_millis(50)).wait().unwrap();
+        let elapsed = start.elapsed();
+        assert!(elapsed >= Duration::from_millis(50), "{:?}", elapsed);
+        assert!(elapsed < Duration::from_secs(5), "{:?}", elapsed);
+    }
+
+    #[test]
+    fn forgets_delays_that_are_dropped() {
+        let timer = Timer::new();
+        let delay = timer.delay(Duration::from_secs(60));
+        assert_eq!(pending(&timer), 1);
+
+        drop(delay);
+        assert_eq!(pending(&timer), 0);
+    }
+
+    #[test]
+    fn stops_its_thread_once_every_clone_is_dropped() {
+        let timer = Timer::new();
+        let shared = Arc::downgrade(&timer.shared);
+        drop(timer.clone());
+        drop(timer);
+
+        let start = Instant::now();
+        while shared.upgrade().is_some() {
+            assert!(start.elapsed() < Duration::from_secs(5));
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+}
@@ -0,0 +1,499 @@
+//! Runs the client against a local stub of TVmaze's API that serves canned
+//! responses from `fixtures/`, and answers with 429s and 5xxs on demand. The
+//! stub also acts as a proxy, since it only looks at the requested path, and
+//! answers conditional requests for the show with a 304.
+
+extern crate futures;
+extern crate hyper;
+extern crate stub_server;
+extern crate tokio;
+extern crate tvmaze;
+
+use std::env;
+use std::fs;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use tvmaze::api::{ShowLookup, UpdatePeriod};
+use tvmaze::cache::{CachePolicy, CacheStats, DiskCache, MemoryCache};
+use tvmaze::client::{Client, ClientBuilder};
+use tvmaze::error::Error;
+
+use futures::{future, Future, Stream};
+use hyper::client::HttpConnector;
+use hyper::header::HeaderValue;
+use hyper::{Body, Request, Response, StatusCode};
+use stub_server::{json, Stub};
+use tokio::runtime::Runtime;
+
+#[derive(Default)]
+struct Requests {
+    base_url: String,
+    requests: usize,
+    /// How many of the next requests are answered with a 429.
+    throttled: usize,
+    /// The request line and user agent of the last request.
+    last_uri: String,
+    last_user_agent: Option<String>,
+}
+
+fn respond(stub: &mut Requests, req: &Request<Body>, _body: &[u8]) -> Response<Body> {
+    stub.requests += 1;
+    stub.last_uri = req.uri().to_string();
+    stub.last_user_agent = req
+        .headers()
+        .get("User-Agent")
+        .and_then(|ua| ua.to_str().ok())
+        .map(|ua| ua.to_string());
+
+    if stub.throttled > 0 {
+        stub.throttled -= 1;
+        return Response::builder()
+            .status(StatusCode::TOO_MANY_REQUESTS)
+            .header("Retry-After", "0")
+            .body(Body::empty())
+            .unwrap();
+    }
+
+    let etag = req.headers().get("If-None-Match");
+    match (req.uri().path(), req.uri().query().unwrap_or("")) {
+        ("/shows/169", _) if etag.map_or(false, |etag| etag == SHOW_ETAG) => Response::builder()
+            .status(StatusCode::NOT_MODIFIED)
+            .body(Body::empty())
+            .unwrap(),
+        ("/shows/169", _) => {
+            let mut res = json(include_str!("fixtures/show.json"));
+            res.headers_mut()
+                .insert("ETag", HeaderValue::from_static(SHOW_ETAG));
+            res
+        }
+        // The same ended show, but only allowed to be cached for no time at all.
+        ("/shows/170", _) => {
+            let mut res = json(include_str!("fixtures/show.json"));
+            res.headers_mut()
+                .insert("Cache-Control", HeaderValue::from_static("max-age=0"));
+            res
+        }
+        ("/schedule", "country=GB") => {
+            let mut res = json("[]");
+            res.headers_mut()
+                .insert("Cache-Control", HeaderValue::from_static("no-store"));
+            res
+        }
+        // The index has two pages of the same show.
+        ("/shows", "page=0") | ("/shows", "page=1") => {
+            json(format!("[{}]", include_str!("fixtures/show.json")))
+        }
+        ("/updates/shows", "since=week") => json(include_str!("fixtures/updates.json")),
+        // The show changed after it was cached.
+        ("/updates/shows", "since=day") => json("{\"169\":1540000000}"),
+        ("/lookup/shows", "thetvdb=81189") => Response::builder()
+            .status(StatusCode::MOVED_PERMANENTLY)
+            .header("Location", format!("{}/shows/169", stub.base_url))
+            .body(Body::empty())
+            .unwrap(),
+        ("/shows/500", _) => status(StatusCode::SERVICE_UNAVAILABLE),
+        ("/shows/slow", _) => status(StatusCode::NOT_FOUND),
+        ("/search/shows", _) => status(StatusCode::BAD_REQUEST),
+        _ => status(StatusCode::NOT_FOUND),
+    }
+}
+
+const SHOW_ETAG: &'static str = "\"169\"";
+
+const ERROR_BODY: &'static str = "{\"name\":\"Error\",\"message\":\"Stubbed failure\"}";
+
+fn status(status: StatusCode) -> Response<Body> {
+    stub_server::status(status, ERROR_BODY)
+}
+
+/// Starts the stub on a free port and returns its base URL.
+fn start_stub(runtime: &mut Runtime) -> (String, Arc<Mutex<Requests>>) {
+    let (url, stub) = Stub::new(Requests::default())
+        .slow("/shows/slow", Duration::from_millis(500))
+        .start(runtime, respond);
+    stub.lock().unwrap().base_url = url.clone();
+    (url, stub)
+}
+
+fn builder(url: &str) -> ClientBuilder {
+    Client::builder()
+        .base_url(url)
+        .backoff(Duration::from_millis(1))
+}
+
+fn client(url: &str) -> Client {
+    builder(url).build().unwrap()
+}
+
+#[test]
+fn loads_shows_and_updates() {
+    let mut runtime = Runtime::new().unwrap();
+    let (url, _) = start_stub(&mut runtime);
+    let client = client(&url);
+
+    let show = runtime
+        .block_on(client.show_main_info("169", None).unwrap())
+        .unwrap();
+    assert_eq!(show.name, "Breaking Bad");
+    assert_eq!(show.updated, 1530131455);
+
+    let updates = runtime
+        .block_on(client.show_updates(UpdatePeriod::Week).unwrap())
+        .unwrap();
+    assert_eq!(updates.get(&169), Some(&1530131455));
+
+    let show = runtime
+        .block_on(client.lookup_show(ShowLookup::TheTVDB("81189")).unwrap())
+        .unwrap();
+    assert_eq!(show.map(|s| s.id), Some(169));
+}
+
+#[test]
+fn retries_throttled_requests() {
+    let mut runtime = Runtime::new().unwrap();
+    let (url, stub) = start_stub(&mut runtime);
+    stub.lock().unwrap().throttled = 3;
+
+    let show = runtime
+        .block_on(client(&url).show_main_info("169", None).unwrap())
+        .unwrap();
+    assert_eq!(show.id, 169);
+    assert_eq!(stub.lock().unwrap().requests, 4);
+}
+
+#[test]
+fn gives_up_after_retrying() {
+    let mut runtime = Runtime::new().unwrap();
+    let (url, stub) = start_stub(&mut runtime);
+    stub.lock().unwrap().throttled = 100;
+
+    match runtime.block_on(client(&url).show_main_info("169", None).unwrap()) {
+        Err(Error::RateLimited { retry_after }) => {
+            assert_eq!(retry_after, Some(Duration::from_secs(0)))
+        }
+        other => panic!("Expected to be rate limited, got {:?}", other),
+    }
+    assert_eq!(stub.lock().unwrap().requests, 5);
+
+    stub.lock().unwrap().throttled = 0;
+    match runtime.block_on(client(&url).show_main_info("500", None).unwrap()) {
+        Err(Error::Server(503, body)) => assert_eq!(body, ERROR_BODY),
+        other => panic!("Expected a server error, got {:?}", other),
+    }
+    assert_eq!(stub.lock().unwrap().requests, 10);
+}
+
+#[test]
+fn reports_client_errors_without_retrying() {
+    let mut runtime = Runtime::new().unwrap();
+    let (url, stub) = start_stub(&mut runtime);
+    let client = client(&url);
+
+    match runtime.block_on(client.show_main_info("1", None).unwrap()) {
+        Err(Error::NotFound(ref body)) => assert_eq!(body, ERROR_BODY),
+        other => panic!("Expected a missing show, got {:?}", other),
+    }
+    match runtime.block_on(client.search_shows("").unwrap()) {
+        Err(Error::Client(400, _)) => {}
+        other => panic!("Expected a bad request, got {:?}", other),
+    }
+    match runtime.block_on(client.lookup_show(ShowLookup::IMDB("tt0")).unwrap()) {
+        Err(Error::ShowNotFound) => {}
+        other => panic!("Expected no show to be found, got {:?}", other),
+    }
+    assert_eq!(stub.lock().unwrap().requests, 3);
+}
+
+#[test]
+fn walks_the_show_index_until_its_end() {
+    let mut runtime = Runtime::new().unwrap();
+    let (url, stub) = start_stub(&mut runtime);
+
+    let pages = runtime
+        .block_on(client(&url).show_index_pages(0, 3).collect())
+        .unwrap();
+    let numbers: Vec<_> = pages.iter().map(|&(page, _)| page).collect();
+    assert_eq!(numbers, vec![0, 1]);
+    assert_eq!(pages[1].1[0].id, 169);
+
+    // The first page past the end is requested along with the last ones.
+    let requests = stub.lock().unwrap().requests;
+    assert!(requests >= 3 && requests <= 5, "{} requests", requests);
+
+    let pages = runtime
+        .block_on(client(&url).show_index_pages(5, 1).collect())
+        .unwrap();
+    assert!(pages.is_empty());
+}
+
+#[test]
+fn stops_the_show_index_at_the_first_error() {
+    let mut runtime = Runtime::new().unwrap();
+    let (url, stub) = start_stub(&mut runtime);
+    stub.lock().unwrap().throttled = 100;
+
+    match runtime.block_on(client(&url).show_index_pages(0, 1).collect()) {
+        Err(Error::RateLimited { .. }) => {}
+        other => panic!("Expected to be rate limited, got {:?}", other.map(|p| p.len())),
+    }
+}
+
+#[test]
+fn spaces_out_requests_over_the_rate_limit() {
+    let mut runtime = Runtime::new().unwrap();
+    let (url, stub) = start_stub(&mut runtime);
+    // Clones share the limit, so the last two requests wait for a token.
+    let client = builder(&url)
+        .rate_limit(2, Duration::from_millis(400))
+        .build()
+        .unwrap();
+
+    let start = Instant::now();
+    let requests: Vec<_> = (0..4)
+        .map(|_| client.clone().show_main_info("169", None).unwrap())
+        .collect();
+    runtime.block_on(future::join_all(requests)).unwrap();
+
+    assert!(start.elapsed() >= Duration::from_millis(400));
+    assert_eq!(stub.lock().unwrap().requests, 4);
+}
+
+#[test]
+fn identifies_itself_with_a_user_agent() {
+    let mut runtime = Runtime::new().unwrap();
+    let (url, stub) = start_stub(&mut runtime);
+
+    runtime
+        .block_on(client(&url).show_main_info("169", None).unwrap())
+        .unwrap();
+    let user_agent = stub.lock().unwrap().last_user_agent.clone();
+    assert_eq!(user_agent.as_ref().map(|ua| &ua[..7]), Some("tvmaze/"));
+
+    let client = builder(&url).user_agent("unify-test").build().unwrap();
+    runtime.block_on(client.show_main_info("169", None).unwrap()).unwrap();
+    let user_agent = stub.lock().unwrap().last_user_agent.clone();
+    assert_eq!(user_agent, Some("unify-test".to_string()));
+}
+
+#[test]
+fn times_out_slow_responses() {
+    let mut runtime = Runtime::new().unwrap();
+    let (url, _) = start_stub(&mut runtime);
+    let client = builder(&url)
+        .timeout(Duration::from_millis(50))
+        .build()
+        .unwrap();
+
+    match runtime.block_on(client.show_main_info("slow", None).unwrap()) {
+        Err(Error::Timeout) => {}
+        other => panic!("Expected a timeout, got {:?}", other),
+    }
+}
+
+#[test]
+fn sends_requests_through_a_proxy() {
+    let mut runtime = Runtime::new().unwrap();
+    let (proxy, stub) = start_stub(&mut runtime);
+    // Nothing listens on the base URL, so the show can only come from the
+    // proxy.
+    let client = builder("http://tvmaze.invalid")
+        .proxy(&proxy)
+        .build()
+        .unwrap();
+
+    let show = runtime
+        .block_on(client.show_main_info("169", None).unwrap())
+        .unwrap();
+    assert_eq!(show.id, 169);
+    assert_eq!(stub.lock().unwrap().last_uri, "http://tvmaze.invalid/shows/169");
+
+    match builder("https://api.tvmaze.com").proxy(&proxy).build() {
+        Err(Error::InvalidProxy(_)) => {}
+        other => panic!("Expected HTTPS to be refused, got {:?}", other.err()),
+    }
+    match builder(&proxy).proxy("socks5://localhost:1080").build() {
+        Err(Error::InvalidProxy(_)) => {}
+        other => panic!("Expected an invalid proxy, got {:?}", other.err()),
+    }
+}
+
+#[test]
+fn connects_with_an_injected_connector() {
+    let mut runtime = Runtime::new().unwrap();
+    let (url, stub) = start_stub(&mut runtime);
+
+    let mut connector = HttpConnector::new(1);
+    connector.set_nodelay(true);
+    let client = builder(&url)
+        .pool_size(1)
+        .build_with_connector(connector)
+        .unwrap();
+
+    runtime
+        .block_on(client.show_main_info("169", None).unwrap())
+        .unwrap();
+    assert_eq!(stub.lock().unwrap().requests, 1);
+}
+
+#[test]
+fn serves_fresh_responses_from_the_cache() {
+    let mut runtime = Runtime::new().unwrap();
+    let (url, stub) = start_stub(&mut runtime);
+    let client = builder(&url).cache(MemoryCache::new(10)).build().unwrap();
+
+    // The show has ended, so it stays fresh for a week.
+    for _ in 0..2 {
+        let show = runtime
+            .block_on(client.show_main_info("169", None).unwrap())
+            .unwrap();
+        assert_eq!(show.id, 169);
+    }
+    assert_eq!(stub.lock().unwrap().requests, 1);
+    assert_eq!(
+        client.cache_stats(),
+        CacheStats {
+            hits: 1,
+            misses: 1,
+            revalidations: 0,
+        }
+    );
+}
+
+#[test]
+fn revalidates_stale_responses() {
+    let mut runtime = Runtime::new().unwrap();
+    let (url, stub) = start_stub(&mut runtime);
+    let policy = CachePolicy {
+        ended_show_ttl: None,
+        ..CachePolicy::default()
+    };
+    let client = builder(&url)
+        .cache(MemoryCache::new(10))
+        .cache_policy(policy)
+        .build()
+        .unwrap();
+
+    for _ in 0..3 {
+        let show = runtime
+            .block_on(client.show_main_info("169", None).unwrap())
+            .unwrap();
+        assert_eq!(show.name, "Breaking Bad");
+    }
+    assert_eq!(stub.lock().unwrap().requests, 3);
+    assert_eq!(
+        client.cache_stats(),
+        CacheStats {
+            hits: 2,
+            misses: 1,
+            revalidations: 2,
+        }
+    );
+}
+
+#[test]
+fn revalidates_cached_shows_that_were_updated_since() {
+    let mut runtime = Runtime::new().unwrap();
+    let (url, stub) = start_stub(&mut runtime);
+    let client = builder(&url).cache(MemoryCache::new(10)).build().unwrap();
+
+    runtime
+        .block_on(client.show_main_info("169", None).unwrap())
+        .unwrap();
+    // The week's updates don't know of anything newer than the cached show.
+    runtime
+        .block_on(client.show_updates(UpdatePeriod::Week).unwrap())
+        .unwrap();
+    runtime
+        .block_on(client.show_main_info("169", None).unwrap())
+        .unwrap();
+    assert_eq!(stub.lock().unwrap().requests, 2);
+
+    runtime
+        .block_on(client.show_updates(UpdatePeriod::Day).unwrap())
+        .unwrap();
+    runtime
+        .block_on(client.show_main_info("169", None).unwrap())
+        .unwrap();
+    assert_eq!(stub.lock().unwrap().requests, 4);
+    assert_eq!(client.cache_stats().revalidations, 1);
+}
+
+#[test]
+fn keeps_responses_no_longer_than_their_max_age() {
+    let mut runtime = Runtime::new().unwrap();
+    let (url, stub) = start_stub(&mut runtime);
+    let client = builder(&url).cache(MemoryCache::new(10)).build().unwrap();
+
+    // Ended shows would otherwise stay fresh for a week.
+    for _ in 0..2 {
+        runtime
+            .block_on(client.show_main_info("170", None).unwrap())
+            .unwrap();
+    }
+    assert_eq!(stub.lock().unwrap().requests, 2);
+    assert_eq!(client.cache_stats().hits, 0);
+}
+
+#[test]
+fn never_stores_responses_marked_no_store() {
+    let mut runtime = Runtime::new().unwrap();
+    let (url, stub) = start_stub(&mut runtime);
+    let client = builder(&url).cache(MemoryCache::new(10)).build().unwrap();
+
+    for _ in 0..2 {
+        runtime
+            .block_on(client.today_schedule(Some("GB"), None).unwrap())
+            .unwrap();
+    }
+    assert_eq!(stub.lock().unwrap().requests, 2);
+    assert_eq!(client.cache_stats().misses, 2);
+}
+
+#[test]
+fn keeps_cached_responses_on_disk() {
+    let mut runtime = Runtime::new().unwrap();
+    let (url, stub) = start_stub(&mut runtime);
+    let dir = env::temp_dir().join(format!("tvmaze-client-cache-{}", stub_port(&url)));
+
+    for _ in 0..2 {
+        let client = builder(&url)
+            .cache(DiskCache::new(&dir).unwrap())
+            .build()
+            .unwrap();
+        runtime
+            .block_on(client.show_main_info("169", None).unwrap())
+            .unwrap();
+    }
+    assert_eq!(stub.lock().unwrap().requests, 1);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+fn stub_port(url: &str) -> &str {
+    url.rsplit(':').next().unwrap()
+}
+
+#[test]
+fn replays_recorded_responses() {
+    let mut runtime = Runtime::new().unwrap();
+    let (url, stub) = start_stub(&mut runtime);
+    let dir = env::temp_dir().join(format!("tvmaze-fixtures-{}", stub_port(&url)));
+
+    let client = builder(&url).record(&dir).build().unwrap();
+    runtime
+        .block_on(client.lookup_show(ShowLookup::TheTVDB("81189")).unwrap())
+        .unwrap();
+    assert_eq!(stub.lock().unwrap().requests, 2);
+
+    // Without a server to answer, only the fixtures can.
+    let client = builder("http://tvmaze.invalid")
+        .replay(&dir)
+        .build()
+        .unwrap();
+    let show = runtime
+        .block_on(client.lookup_show(ShowLookup::TheTVDB("81189")).unwrap())
+        .unwrap();
+    assert_eq!(show.map(|s| s.name), Some("Breaking Bad".to_string()));
+    assert_eq!(stub.lock().unwrap().requests, 2);
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,193 @@
+//! Replays the fixtures in `fixtures/replay/` through every method of the
+//! client, so that responses that stop deserializing are caught without a
+//! network. Run with `TVMAZE_RECORD=1` to record the fixtures again from
+//! TVmaze's API.
+
+extern crate chrono;
+extern crate futures;
+extern crate tokio;
+extern crate tvmaze;
+
+use std::env;
+use std::time::Duration;
+
+use tvmaze::api::{LinkEmbed, ShowLookup, UpdatePeriod};
+use tvmaze::client::{Client, QueryFuture};
+use tvmaze::error::Error;
+use tvmaze::resources::{ShowStatus, ShowType, Weekday};
+
+use chrono::{NaiveDate, NaiveTime};
+use futures::Stream;
+
+use tokio::runtime::Runtime;
+
+const FIXTURES: &'static str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/replay");
+
+fn recording() -> bool {
+    env::var("TVMAZE_RECORD").is_ok()
+}
+
+fn client() -> Client {
+    if recording() {
+        Client::builder().record(FIXTURES).build().unwrap()
+    } else {
+        Client::builder()
+            .replay(FIXTURES)
+            .rate_limit(1000, Duration::from_secs(1))
+            .build()
+            .unwrap()
+    }
+}
+
+fn run<R: Send + 'static>(query: Result<QueryFuture<R>, Error>) -> Result<R, Error> {
+    Runtime::new().unwrap().block_on(query.unwrap())
+}
+
+#[test]
+fn searches_shows_and_people() {
+    let client = client();
+
+    let results = run(client.search_shows("breaking bad")).unwrap();
+    assert_eq!(results[0].show.id, 169);
+    assert!(results[0].score > 0.0);
+
+    let embed = Some(LinkEmbed::Single("episodes"));
+    let show = run(client.search_show("breaking bad", embed)).unwrap();
+    assert_eq!(show.name, "Breaking Bad");
+    let episodes = show.embeds.and_then(|e| e.episodes).unwrap();
+    assert_eq!(episodes[0].name, "Pilot");
+
+    let results = run(client.search_people("bryan cranston")).unwrap();
+    assert_eq!(results[0].person.name, "Bryan Cranston");
+}
+
+#[test]
+fn looks_up_shows_by_their_external_ids() {
+    let client = client();
+
+    let show = run(client.lookup_show(ShowLookup::TheTVDB("81189"))).unwrap();
+    assert_eq!(show.map(|s| s.externals.thetvdb), Some(Some(81189)));
+
+    match run(client.lookup_show(ShowLookup::IMDB("tt0000000"))) {
+        Err(Error::ShowNotFound) => {}
+        other => panic!("Expected no show to be found, got {:?}", other),
+    }
+}
+
+#[test]
+fn loads_shows_with_their_seasons_and_episodes() {
+    let client = client();
+
+    let show = run(client.show_main_info("169", None)).unwrap();
+    assert_eq!(show.status, ShowStatus::Ended);
+    assert_eq!(show._type, ShowType::Scripted);
+    assert_eq!(show.premiered, Some(NaiveDate::from_ymd(2008, 1, 20)));
+    assert_eq!(show.schedule.days, vec![Weekday::Sunday]);
+    assert_eq!(show.schedule.time, Some(NaiveTime::from_hms(22, 0, 0)));
+    assert_eq!(show.network.map(|n| n.name), Some("AMC".to_string()));
+
+    let episodes = run(client.show_episode_list("169", false, None)).unwrap();
+    assert_eq!(episodes.len(), 3);
+    let episode = run(client.show_episode_by_number("169", 1, 1)).unwrap();
+    assert_eq!(episode.id, episodes[0].id);
+    let episodes = run(client.show_episodes_by_date("169", "2008-01-20")).unwrap();
+    assert_eq!(episodes[0].airdate, Some(NaiveDate::from_ymd(2008, 1, 20)));
+    assert_eq!(episodes[0].airtime, Some(NaiveTime::from_hms(22, 0, 0)));
+    let airstamp = episodes[0].airstamp.unwrap();
+    assert_eq!(airstamp.naive_utc().date(), NaiveDate::from_ymd(2008, 1, 21));
+
+    let seasons = run(client.show_seasons("169")).unwrap();
+    assert_eq!(seasons[0].episode_order, Some(7));
+    assert_eq!(seasons[0].end_date, Some(NaiveDate::from_ymd(2008, 3, 9)));
+    assert!(seasons[1].image.is_none());
+    let episodes = run(client.season_episodes(&seasons[0].id.to_string())).unwrap();
+    assert!(episodes.iter().all(|e| e.season == 1));
+}
+
+#[test]
+fn embeds_resources_in_shows() {
+    let client = client();
+
+    let embed = Some(LinkEmbed::Multiple(vec!["seasons", "akas"]));
+    let show = run(client.show_main_info("169", embed)).unwrap();
+    let embeds = show.embeds.unwrap();
+    assert_eq!(embeds.seasons.map(|s| s.len()), Some(2));
+    assert_eq!(embeds.akas.map(|a| a.len()), Some(3));
+    assert!(embeds.episodes.is_none());
+
+    let embed = Some(LinkEmbed::Single("previousepisode"));
+    let show = run(client.show_main_info("169", embed)).unwrap();
+    let episode = show.embeds.and_then(|e| e.previousepisode).unwrap();
+    assert_eq!(episode.name, "Felina");
+
+    let episodes = run(client.show_episode_list("169", true, None)).unwrap();
+    assert_eq!(episodes.iter().filter(|e| e.number.is_none()).count(), 1);
+}
+
+#[test]
+fn loads_credits_aliases_and_images_of_shows() {
+    let client = client();
+
+    let cast = run(client.show_cast("169")).unwrap();
+    assert_eq!(cast[0].character.name, "Walter White");
+    let crew = run(client.show_crew("169")).unwrap();
+    assert_eq!(crew[0]._type, "Creator");
+
+    let akas = run(client.show_akas("169")).unwrap();
+    assert!(akas.iter().any(|aka| aka.country.is_none()));
+
+    let images = run(client.show_images("169")).unwrap();
+    assert!(images[0].main);
+    assert!(images.iter().any(|image| image.resolutions.medium.is_none()));
+}
+
+#[test]
+fn loads_schedules() {
+    let client = client();
+
+    let episodes = run(client.today_schedule(Some("US"), Some("2013-06-24"))).unwrap();
+    assert_eq!(episodes[0].airdate, Some(NaiveDate::from_ymd(2013, 6, 24)));
+
+    // Web series' episodes can lack a number and an air date.
+    let episodes = run(client.full_schedule()).unwrap();
+    let special = episodes.iter().find(|e| e.number.is_none()).unwrap();
+    assert_eq!(special.airdate, None);
+    assert_eq!(special.airtime, None);
+    assert_eq!(special.airstamp, None);
+}
+
+#[test]
+fn loads_the_show_index_updates_and_people() {
+    let client = client();
+
+    let shows = run(client.show_index(None)).unwrap();
+    assert_eq!(shows[0].id, 1);
+    let web_show = shows.iter().find(|s| s.web_channel.is_some()).unwrap();
+    assert_eq!(web_show.status, ShowStatus::ToBeDetermined);
+    assert_eq!(web_show.premiered, None);
+    assert_eq!(web_show.schedule.time, None);
+
+    let index = client.show_index_pages(1, 1).collect();
+    let pages = Runtime::new().unwrap().block_on(index).unwrap();
+    assert_eq!(pages.len(), 1);
+    assert_eq!(pages[0].1.len(), shows.len());
+
+    let updates = run(client.show_updates(UpdatePeriod::Day)).unwrap();
+    assert_eq!(updates.get(&169), Some(&1530131455));
+
+    let person = run(client.person_main_info("14245")).unwrap();
+    assert_eq!(person.birthday, Some(NaiveDate::from_ymd(1956, 3, 7)));
+    assert_eq!(person.deathday, None);
+}
+
+#[test]
+fn fails_requests_that_were_not_recorded() {
+    if recording() {
+        return;
+    }
+
+    match run(client().show_main_info("0", None)) {
+        Err(Error::MissingFixture(_)) => {}
+        other => panic!("Expected a missing fixture, got {:?}", other),
+    }
+}
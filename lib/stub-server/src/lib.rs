@@ -0,0 +1,100 @@
+//! A local HTTP server that stands in for the APIs of metadata providers in
+//! the tests of their clients. Each test decides how requests are answered,
+//! and the server only takes care of serving them.
+
+extern crate futures;
+extern crate hyper;
+extern crate tokio;
+
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use futures::{future, Future, Stream};
+use hyper::service::service_fn;
+use hyper::{Body, Request, Response, Server, StatusCode};
+use tokio::runtime::Runtime;
+use tokio::timer::Delay;
+
+/// A stub that hasn't been started yet, along with the state that its
+/// responses can look at and change.
+pub struct Stub<S> {
+    state: S,
+    /// Paths whose responses are held back, such as to test timeouts.
+    slow_paths: Vec<(String, Duration)>,
+}
+
+impl<S: Send + 'static> Stub<S> {
+    pub fn new(state: S) -> Stub<S> {
+        Stub {
+            state,
+            slow_paths: vec![],
+        }
+    }
+
+    /// Holds back the responses to requests for `path` by `delay`. They wait
+    /// on the runtime's timer, since the runtime may only have one thread.
+    pub fn slow(mut self, path: &str, delay: Duration) -> Stub<S> {
+        self.slow_paths.push((path.to_string(), delay));
+        self
+    }
+
+    /// Starts the stub on a free port, answering every request with
+    /// `respond`, which is given the state, the request and its body. Returns
+    /// the stub's base URL and its state.
+    pub fn start<F>(self, runtime: &mut Runtime, respond: F) -> (String, Arc<Mutex<S>>)
+    where
+        F: Fn(&mut S, &Request<Body>, &[u8]) -> Response<Body> + Send + Sync + 'static,
+    {
+        let state = Arc::new(Mutex::new(self.state));
+        let service_state = state.clone();
+        let respond = Arc::new(respond);
+        let slow_paths = Arc::new(self.slow_paths);
+
+        let server = Server::bind(&([127, 0, 0, 1], 0).into()).serve(move || {
+            let state = service_state.clone();
+            let respond = respond.clone();
+            let slow_paths = slow_paths.clone();
+
+            service_fn(move |req: Request<Body>| {
+                let state = state.clone();
+                let respond = respond.clone();
+                let (parts, body) = req.into_parts();
+                let wait: Box<Future<Item = (), Error = hyper::Error> + Send> = match slow_paths
+                    .iter()
+                    .find(|&&(ref path, _)| path == parts.uri.path())
+                {
+                    Some(&(_, delay)) => Box::new(Delay::new(Instant::now() + delay).then(|_| Ok(()))),
+                    None => Box::new(future::ok(())),
+                };
+
+                body.concat2().and_then(move |body| {
+                    wait.map(move |_| {
+                        let req = Request::from_parts(parts, Body::empty());
+                        let mut state = state.lock().unwrap();
+                        respond(&mut *state, &req, &body)
+                    })
+                })
+            })
+        });
+        let url = format!("http://{}", server.local_addr());
+        runtime.spawn(server.map_err(|e| panic!("Stub server failed: {}", e)));
+
+        (url, state)
+    }
+}
+
+/// A 200 with a JSON body.
+pub fn json<B: Into<Body>>(body: B) -> Response<Body> {
+    Response::builder()
+        .header("Content-Type", "application/json")
+        .body(body.into())
+        .unwrap()
+}
+
+/// A response with `status` and `body`.
+pub fn status<B: Into<Body>>(status: StatusCode, body: B) -> Response<Body> {
+    Response::builder()
+        .status(status)
+        .body(body.into())
+        .unwrap()
+}
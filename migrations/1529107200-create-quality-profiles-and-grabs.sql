@@ -0,0 +1,54 @@
+-- Qualities are declared from worst to best so that Postgres' enum ordering
+-- can be used to compare them directly (e.g. `quality < cutoff`).
+CREATE TYPE quality AS ENUM (
+  'unknown',
+  'sdtv',
+  'dvd',
+  'webdl480p',
+  'hdtv720p',
+  'webdl720p',
+  'bluray720p',
+  'hdtv1080p',
+  'webdl1080p',
+  'bluray1080p',
+  'hdtv2160p',
+  'webdl2160p',
+  'bluray2160p'
+);
+
+CREATE TABLE quality_profiles (
+  id SERIAL NOT NULL,
+  name TEXT NOT NULL,
+  allowed quality[] NOT NULL,
+  cutoff quality NOT NULL,
+  PRIMARY KEY (id)
+);
+
+INSERT INTO quality_profiles (name, allowed, cutoff) VALUES
+  ('Any',
+   ARRAY['sdtv', 'dvd', 'webdl480p', 'hdtv720p', 'webdl720p', 'bluray720p',
+         'hdtv1080p', 'webdl1080p', 'bluray1080p']::quality[],
+   'webdl720p'),
+  ('HD-1080p',
+   ARRAY['hdtv1080p', 'webdl1080p', 'bluray1080p']::quality[],
+   'webdl1080p');
+
+ALTER TABLE tv_shows
+  ADD COLUMN monitored BOOLEAN NOT NULL DEFAULT TRUE,
+  ADD COLUMN quality_profile_id INT NOT NULL DEFAULT 1 REFERENCES quality_profiles (id);
+
+ALTER TABLE tv_show_episodes
+  ADD COLUMN airdate DATE,
+  ADD COLUMN monitored BOOLEAN NOT NULL DEFAULT TRUE;
+
+CREATE TABLE tv_show_episode_grabs (
+  id SERIAL NOT NULL,
+  episode_id INT NOT NULL,
+  title TEXT NOT NULL,
+  quality quality NOT NULL,
+  indexer TEXT NOT NULL,
+  download_id TEXT NOT NULL,
+  grabbed_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+  PRIMARY KEY (id),
+  FOREIGN KEY (episode_id) REFERENCES tv_show_episodes (id) ON DELETE CASCADE
+);
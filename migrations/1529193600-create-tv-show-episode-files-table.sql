@@ -0,0 +1,14 @@
+CREATE TABLE tv_show_episode_files (
+  id SERIAL NOT NULL,
+  episode_id INT NOT NULL,
+  path TEXT NOT NULL,
+  size BIGINT NOT NULL,
+  quality quality NOT NULL,
+  created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+  PRIMARY KEY (id),
+  UNIQUE (episode_id),
+  FOREIGN KEY (episode_id) REFERENCES tv_show_episodes (id) ON DELETE CASCADE
+);
+
+CREATE INDEX tv_show_episode_grabs_episode_id_idx ON tv_show_episode_grabs (episode_id);
+CREATE INDEX tv_show_episodes_airdate_idx ON tv_show_episodes (airdate);
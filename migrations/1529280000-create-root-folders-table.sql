@@ -0,0 +1,10 @@
+CREATE TABLE root_folders (
+  id SERIAL NOT NULL,
+  path TEXT NOT NULL,
+  PRIMARY KEY (id),
+  UNIQUE (path)
+);
+
+ALTER TABLE tv_shows
+  ADD COLUMN root_folder_id INT REFERENCES root_folders (id) ON DELETE SET NULL,
+  ADD COLUMN path TEXT;
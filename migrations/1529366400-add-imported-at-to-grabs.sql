@@ -0,0 +1,4 @@
+ALTER TABLE tv_show_episode_grabs
+  ADD COLUMN imported_at TIMESTAMPTZ;
+
+CREATE INDEX tv_show_episode_grabs_download_id_idx ON tv_show_episode_grabs (download_id);
@@ -0,0 +1,9 @@
+CREATE TABLE import_plans (
+  id SERIAL NOT NULL,
+  root_folder_id INT NOT NULL,
+  shows JSONB NOT NULL,
+  created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+  confirmed_at TIMESTAMPTZ,
+  PRIMARY KEY (id),
+  FOREIGN KEY (root_folder_id) REFERENCES root_folders (id) ON DELETE CASCADE
+);
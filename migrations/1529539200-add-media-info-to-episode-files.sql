@@ -0,0 +1 @@
+ALTER TABLE tv_show_episode_files ADD COLUMN media_info JSONB;
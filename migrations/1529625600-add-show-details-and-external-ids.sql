@@ -0,0 +1,15 @@
+ALTER TABLE tv_shows
+  ADD COLUMN fanart_img TEXT,
+  ADD COLUMN genres TEXT[] NOT NULL DEFAULT '{}',
+  ADD COLUMN runtime INT,
+  ADD COLUMN premiered DATE,
+  ADD COLUMN imdb_id TEXT,
+  ADD COLUMN thetvdb_id BIGINT,
+  ADD COLUMN tvrage_id BIGINT;
+
+-- Refreshing a show updates its seasons and episodes in place, matched by
+-- their provider ids.
+CREATE UNIQUE INDEX tv_show_seasons_show_id_provider_id_idx
+  ON tv_show_seasons (show_id, provider_id);
+CREATE UNIQUE INDEX tv_show_episodes_show_id_provider_id_idx
+  ON tv_show_episodes (show_id, provider_id);
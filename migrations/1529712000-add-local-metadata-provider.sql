@@ -0,0 +1 @@
+ALTER TYPE tv_metadata_provider ADD VALUE 'local';
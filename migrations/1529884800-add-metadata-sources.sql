@@ -0,0 +1,5 @@
+-- The providers that fields were filled in from when the show's own provider
+-- left them empty, by field name.
+ALTER TABLE tv_shows ADD COLUMN metadata_sources JSONB NOT NULL DEFAULT '{}';
+ALTER TABLE tv_show_seasons ADD COLUMN metadata_sources JSONB NOT NULL DEFAULT '{}';
+ALTER TABLE tv_show_episodes ADD COLUMN metadata_sources JSONB NOT NULL DEFAULT '{}';
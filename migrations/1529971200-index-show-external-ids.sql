@@ -0,0 +1,4 @@
+-- Shows are looked up by their ids in other databases.
+CREATE INDEX tv_shows_imdb_id_idx ON tv_shows (imdb_id);
+CREATE INDEX tv_shows_thetvdb_id_idx ON tv_shows (thetvdb_id);
+CREATE INDEX tv_shows_tvrage_id_idx ON tv_shows (tvrage_id);
@@ -0,0 +1,38 @@
+CREATE TABLE people (
+  id SERIAL NOT NULL,
+  name TEXT NOT NULL,
+  image TEXT,
+  birthday DATE,
+  deathday DATE,
+  gender TEXT,
+  country TEXT,
+  provider tv_metadata_provider NOT NULL,
+  provider_id TEXT NOT NULL,
+  provider_url TEXT NOT NULL,
+  PRIMARY KEY (id),
+  UNIQUE (provider, provider_id)
+);
+
+-- The cast of a show, billed in the order of `ord`.
+CREATE TABLE show_cast (
+  show_id INT NOT NULL,
+  person_id INT NOT NULL,
+  character TEXT NOT NULL,
+  character_img TEXT,
+  ord INT NOT NULL,
+  PRIMARY KEY (show_id, person_id, character),
+  FOREIGN KEY (show_id) REFERENCES tv_shows (id) ON DELETE CASCADE,
+  FOREIGN KEY (person_id) REFERENCES people (id) ON DELETE CASCADE
+);
+
+CREATE TABLE show_crew (
+  show_id INT NOT NULL,
+  person_id INT NOT NULL,
+  job TEXT NOT NULL,
+  PRIMARY KEY (show_id, person_id, job),
+  FOREIGN KEY (show_id) REFERENCES tv_shows (id) ON DELETE CASCADE,
+  FOREIGN KEY (person_id) REFERENCES people (id) ON DELETE CASCADE
+);
+
+CREATE INDEX show_cast_person_id_idx ON show_cast (person_id);
+CREATE INDEX show_crew_person_id_idx ON show_crew (person_id);
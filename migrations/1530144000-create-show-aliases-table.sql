@@ -0,0 +1,11 @@
+-- Alternate titles of shows. Aliases from providers are replaced whenever a
+-- show is refreshed, user defined ones are kept.
+CREATE TABLE show_aliases (
+  id SERIAL NOT NULL,
+  show_id INT NOT NULL,
+  name TEXT NOT NULL,
+  user_defined BOOLEAN NOT NULL DEFAULT FALSE,
+  PRIMARY KEY (id),
+  UNIQUE (show_id, name),
+  FOREIGN KEY (show_id) REFERENCES tv_shows (id) ON DELETE CASCADE
+);
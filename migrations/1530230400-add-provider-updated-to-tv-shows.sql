@@ -0,0 +1,3 @@
+-- When a show last changed at its provider, used to only refresh the shows
+-- that did.
+ALTER TABLE tv_shows ADD COLUMN provider_updated BIGINT;
@@ -0,0 +1,48 @@
+-- A local copy of the shows that metadata providers list, so that searches
+-- can be answered without asking the provider. Filled by `CatalogSyncJob`.
+CREATE EXTENSION IF NOT EXISTS pg_trgm;
+
+CREATE TABLE catalog_shows (
+  id SERIAL NOT NULL,
+  name TEXT NOT NULL,
+  summary TEXT,
+  cover_img TEXT,
+  genres TEXT[] NOT NULL DEFAULT '{}',
+  runtime INT,
+  premiered DATE,
+  imdb_id TEXT,
+  thetvdb_id BIGINT,
+  tvrage_id BIGINT,
+  provider tv_metadata_provider NOT NULL,
+  provider_id TEXT NOT NULL,
+  provider_url TEXT NOT NULL,
+  provider_updated BIGINT,
+  -- The page of the provider's index that lists the show.
+  index_page BIGINT NOT NULL,
+  -- Indexes don't list aliases, so they are loaded show by show afterwards,
+  -- and again whenever the show changes.
+  aliases_loaded_at TIMESTAMPTZ,
+  PRIMARY KEY (id),
+  UNIQUE (provider, provider_id)
+);
+
+CREATE TABLE catalog_show_aliases (
+  catalog_show_id INT NOT NULL,
+  name TEXT NOT NULL,
+  PRIMARY KEY (catalog_show_id, name),
+  FOREIGN KEY (catalog_show_id) REFERENCES catalog_shows (id) ON DELETE CASCADE
+);
+
+-- Names are matched by similarity and by substring.
+CREATE INDEX catalog_shows_name_trgm_idx ON catalog_shows USING GIN (name gin_trgm_ops);
+CREATE INDEX catalog_show_aliases_name_trgm_idx
+  ON catalog_show_aliases USING GIN (name gin_trgm_ops);
+
+-- When a provider's whole index was last walked, and when its catalog was
+-- last brought up to date at all.
+CREATE TABLE catalog_syncs (
+  provider tv_metadata_provider NOT NULL,
+  walked_at TIMESTAMPTZ,
+  synced_at TIMESTAMPTZ NOT NULL,
+  PRIMARY KEY (provider)
+);
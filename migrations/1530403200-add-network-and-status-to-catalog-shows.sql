@@ -0,0 +1,3 @@
+ALTER TABLE catalog_shows
+    ADD COLUMN network TEXT,
+    ADD COLUMN status TEXT;
@@ -0,0 +1,98 @@
+-- A show can only be in the library once per provider. Shows that were added
+-- more than once are merged into their first copy before the later copies are
+-- dropped. Seasons, episodes, aliases and credits that the first copy lacks
+-- are moved over to it, and so are the files and grabs of episodes that both
+-- copies have, unless the first copy already has a file for the episode. The
+-- first copy also takes over the folder of a later one if it has none.
+DO $$
+DECLARE
+  dup RECORD;
+BEGIN
+  FOR dup IN
+    SELECT A.id AS duplicate_id, MIN(B.id) AS kept_id
+    FROM tv_shows A
+    INNER JOIN tv_shows B
+      ON B.provider = A.provider AND B.provider_id = A.provider_id AND B.id < A.id
+    GROUP BY A.id
+    ORDER BY A.id
+  LOOP
+    UPDATE tv_show_episode_files F
+      SET episode_id = K.id
+      FROM tv_show_episodes E, tv_show_episodes K
+      WHERE F.episode_id = E.id
+        AND E.show_id = dup.duplicate_id
+        AND K.show_id = dup.kept_id
+        AND K.provider_id = E.provider_id
+        AND NOT EXISTS (SELECT 1 FROM tv_show_episode_files KF WHERE KF.episode_id = K.id);
+
+    UPDATE tv_show_episode_grabs G
+      SET episode_id = K.id
+      FROM tv_show_episodes E, tv_show_episodes K
+      WHERE G.episode_id = E.id
+        AND E.show_id = dup.duplicate_id
+        AND K.show_id = dup.kept_id
+        AND K.provider_id = E.provider_id;
+
+    UPDATE tv_show_seasons S
+      SET show_id = dup.kept_id
+      WHERE S.show_id = dup.duplicate_id
+        AND NOT EXISTS (
+          SELECT 1 FROM tv_show_seasons K
+          WHERE K.show_id = dup.kept_id AND K.provider_id = S.provider_id
+        );
+
+    -- Episodes follow their season into the first copy, whether it was just
+    -- moved there or was there already.
+    UPDATE tv_show_episodes E
+      SET show_id = dup.kept_id,
+          season_id = KS.id
+      FROM tv_show_seasons DS, tv_show_seasons KS
+      WHERE E.show_id = dup.duplicate_id
+        AND DS.id = E.season_id
+        AND KS.show_id = dup.kept_id
+        AND KS.provider_id = DS.provider_id
+        AND NOT EXISTS (
+          SELECT 1 FROM tv_show_episodes K
+          WHERE K.show_id = dup.kept_id AND K.provider_id = E.provider_id
+        );
+
+    UPDATE show_aliases A
+      SET show_id = dup.kept_id
+      WHERE A.show_id = dup.duplicate_id
+        AND NOT EXISTS (
+          SELECT 1 FROM show_aliases K WHERE K.show_id = dup.kept_id AND K.name = A.name
+        );
+
+    UPDATE show_cast C
+      SET show_id = dup.kept_id
+      WHERE C.show_id = dup.duplicate_id
+        AND NOT EXISTS (
+          SELECT 1 FROM show_cast K
+          WHERE K.show_id = dup.kept_id
+            AND K.person_id = C.person_id
+            AND K.character = C.character
+        );
+
+    UPDATE show_crew C
+      SET show_id = dup.kept_id
+      WHERE C.show_id = dup.duplicate_id
+        AND NOT EXISTS (
+          SELECT 1 FROM show_crew K
+          WHERE K.show_id = dup.kept_id AND K.person_id = C.person_id AND K.job = C.job
+        );
+
+    UPDATE tv_shows K
+      SET root_folder_id = D.root_folder_id,
+          path = D.path
+      FROM tv_shows D
+      WHERE K.id = dup.kept_id
+        AND D.id = dup.duplicate_id
+        AND K.path IS NULL
+        AND D.path IS NOT NULL;
+
+    DELETE FROM tv_shows WHERE id = dup.duplicate_id;
+  END LOOP;
+END
+$$;
+
+ALTER TABLE tv_shows ADD CONSTRAINT tv_shows_provider_id_key UNIQUE (provider, provider_id);
@@ -0,0 +1,127 @@
+use std::env;
+
+use resources::tv_show::TVMetadataProvider;
+
+/// How imported files are placed into a show's folder.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ImportMode {
+    /// Hardlinks the file, falling back to a copy when the download and the
+    /// library are on different filesystems.
+    Hardlink,
+    Copy,
+    Move,
+}
+
+/// A media center whose metadata files (NFOs and artwork) are written next to
+/// the library's media.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MetadataConsumer {
+    Kodi,
+    Jellyfin,
+}
+
+/// Settings that can be changed through environment variables.
+#[derive(Clone, Debug)]
+pub struct Config {
+    /// Template for a show's folder inside its root folder. See `naming`.
+    pub show_folder_format: String,
+    /// Template for a season's folder inside its show's folder.
+    pub season_folder_format: String,
+    /// Template for an episode's file name, without its extension.
+    pub episode_file_format: String,
+    pub import_mode: ImportMode,
+    /// Media centers to write metadata for. Nothing is written if empty.
+    pub metadata_consumers: Vec<MetadataConsumer>,
+    /// Shows can only be added from TheTVDB when an API key is set.
+    pub thetvdb_api_key: Option<String>,
+    /// The subscriber PIN that user-supported API keys require.
+    pub thetvdb_pin: Option<String>,
+    /// Where TVmaze's API is reached instead of api.tvmaze.com, such as a
+    /// mirror or a caching proxy.
+    pub tvmaze_base_url: Option<String>,
+    /// An HTTP proxy that requests to TVmaze are sent through.
+    pub tvmaze_proxy: Option<String>,
+    /// Keeps TVmaze's responses in this directory instead of in memory, so
+    /// that they survive restarts.
+    pub tvmaze_cache_dir: Option<String>,
+    /// Providers that fill in what a show's own provider leaves empty, in the
+    /// order they are tried.
+    pub metadata_fallbacks: Vec<TVMetadataProvider>,
+    /// Providers whose shows are copied into the local catalog, which
+    /// searches are then answered from. See `library::catalog`.
+    pub catalog_providers: Vec<TVMetadataProvider>,
+}
+
+/// The settings used when no environment variables are set.
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            show_folder_format: "{Show Name}".to_string(),
+            season_folder_format: "Season {season:00}".to_string(),
+            episode_file_format: "{Show Name} - S{season:00}E{episode:00} - {Episode Title}"
+                .to_string(),
+            import_mode: ImportMode::Hardlink,
+            metadata_consumers: vec![],
+            thetvdb_api_key: None,
+            thetvdb_pin: None,
+            tvmaze_base_url: None,
+            tvmaze_proxy: None,
+            tvmaze_cache_dir: None,
+            metadata_fallbacks: vec![],
+            catalog_providers: vec![],
+        }
+    }
+}
+
+impl Config {
+    pub fn from_env() -> Config {
+        let defaults = Config::default();
+        Config {
+            show_folder_format: env_or("UNIFY_SHOW_FOLDER_FORMAT", &defaults.show_folder_format),
+            season_folder_format: env_or(
+                "UNIFY_SEASON_FOLDER_FORMAT",
+                &defaults.season_folder_format,
+            ),
+            episode_file_format: env_or(
+                "UNIFY_EPISODE_FILE_FORMAT",
+                &defaults.episode_file_format,
+            ),
+            import_mode: match env_or("UNIFY_IMPORT_MODE", "hardlink").as_str() {
+                "copy" => ImportMode::Copy,
+                "move" => ImportMode::Move,
+                _ => defaults.import_mode,
+            },
+            metadata_consumers: env_or("UNIFY_METADATA_CONSUMERS", "")
+                .split(',')
+                .filter_map(|consumer| match consumer.trim().to_lowercase().as_str() {
+                    "kodi" => Some(MetadataConsumer::Kodi),
+                    "jellyfin" => Some(MetadataConsumer::Jellyfin),
+                    _ => None,
+                })
+                .collect(),
+            thetvdb_api_key: env::var("UNIFY_THETVDB_API_KEY").ok(),
+            thetvdb_pin: env::var("UNIFY_THETVDB_PIN").ok(),
+            tvmaze_base_url: env::var("UNIFY_TVMAZE_BASE_URL").ok(),
+            tvmaze_proxy: env::var("UNIFY_TVMAZE_PROXY").ok(),
+            tvmaze_cache_dir: env::var("UNIFY_TVMAZE_CACHE_DIR").ok(),
+            metadata_fallbacks: env_providers("UNIFY_METADATA_FALLBACKS"),
+            catalog_providers: env_providers("UNIFY_CATALOG_PROVIDERS"),
+        }
+    }
+}
+
+fn env_or(key: &str, default: &str) -> String {
+    env::var(key).unwrap_or_else(|_| default.to_string())
+}
+
+/// A comma separated list of remote providers, such as `tvmaze,thetvdb`.
+fn env_providers(key: &str) -> Vec<TVMetadataProvider> {
+    env_or(key, "")
+        .split(',')
+        .filter_map(|provider| match provider.trim().to_lowercase().as_str() {
+            "tvmaze" => Some(TVMetadataProvider::TVMaze),
+            "thetvdb" => Some(TVMetadataProvider::TheTVDB),
+            _ => None,
+        })
+        .collect()
+}
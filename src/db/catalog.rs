@@ -0,0 +1,347 @@
+use std::collections::HashMap;
+
+use super::*;
+use error::WebError;
+use resources::quality::DEFAULT_QUALITY_PROFILE_ID;
+use resources::tv_show::*;
+
+use chrono::{DateTime, Utc};
+use futures::{future, Future};
+use postgres::rows::Row;
+use postgres::types::ToSql;
+
+const SELECT_CATALOG_SHOW: &'static str = "SELECT C.name,
+            C.summary,
+            C.cover_img,
+            C.genres,
+            C.runtime,
+            C.premiered,
+            C.imdb_id,
+            C.thetvdb_id,
+            C.tvrage_id,
+            C.provider,
+            C.provider_id,
+            C.provider_url,
+            C.provider_updated,
+            C.network,
+            C.status,
+            ARRAY(SELECT name
+                  FROM catalog_show_aliases
+                  WHERE catalog_show_id = C.id
+                  ORDER BY name) as aliases";
+
+/// A show of the catalog, as it would be found by searching its provider. It
+/// isn't in the library, so it has no id.
+fn catalog_show_from_row(row: &Row, score: Option<f64>) -> ShowSearchResult {
+    let show = TVShow {
+        id: -1,
+        name: row.get("name"),
+        summary: row.get("summary"),
+        cover_img: row.get("cover_img"),
+        fanart_img: None,
+        genres: row.get("genres"),
+        runtime: row.get("runtime"),
+        premiered: row.get("premiered"),
+        external_ids: ExternalIds {
+            imdb: row.get("imdb_id"),
+            thetvdb: row.get("thetvdb_id"),
+            tvrage: row.get("tvrage_id"),
+        },
+        provider: row.get("provider"),
+        provider_id: row.get("provider_id"),
+        provider_url: row.get("provider_url"),
+        provider_updated: row.get("provider_updated"),
+        monitored: true,
+        quality_profile_id: DEFAULT_QUALITY_PROFILE_ID,
+        root_folder_id: None,
+        path: None,
+        aliases: row.get("aliases"),
+        metadata_sources: MetadataSources::new(),
+        seasons: None,
+    };
+
+    ShowSearchResult::new(show, score, row.get("network"), row.get("status"))
+}
+
+/// Finds up to `limit` shows from `provider` in the catalog whose name or one
+/// of whose aliases contains `q` or is similar to it, the most similar first.
+/// Results are scored by their trigram similarity to `q`, from 0 to 1.
+pub fn search_catalog(
+    provider: TVMetadataProvider,
+    q: &str,
+    limit: i64,
+    pool: Addr<Syn, DBExecutor>,
+) -> Box<Future<Item = Vec<ShowSearchResult>, Error = WebError>> {
+    Box::new(
+        pool.send(DBQuery::new(
+            [
+                SELECT_CATALOG_SHOW,
+                ", GREATEST(similarity(C.name, $2),
+                            (SELECT MAX(similarity(L.name, $2))
+                             FROM catalog_show_aliases L
+                             WHERE L.catalog_show_id = C.id)) as score
+                 FROM catalog_shows C
+                 WHERE C.provider = $1
+                   AND (C.name ILIKE '%' || $4 || '%'
+                        OR C.name % $2
+                        OR EXISTS (SELECT 1
+                                   FROM catalog_show_aliases L
+                                   WHERE L.catalog_show_id = C.id
+                                     AND (L.name ILIKE '%' || $4 || '%' OR L.name % $2)))
+                 ORDER BY score DESC, C.name
+                 LIMIT $3",
+            ].join(""),
+            db_params![provider, q, limit, escape_like(q)],
+        )).map_err(WebError::from)
+            .and_then(|rows| {
+                rows.map(|rows| {
+                    rows.iter()
+                        .map(|r| {
+                            let score: f32 = r.get("score");
+                            catalog_show_from_row(&r, Some(score as f64))
+                        })
+                        .collect()
+                })
+            }),
+    )
+}
+
+/// Adds the shows listed on `page` of their provider's index to the catalog,
+/// or updates them. Shows that changed since they were stored have their
+/// aliases loaded again. Resolves to how many shows were stored.
+pub fn upsert_catalog_shows(
+    page: u64,
+    shows: Vec<ShowSearchResult>,
+    pool: Addr<Syn, DBExecutor>,
+) -> Box<Future<Item = usize, Error = WebError>> {
+    if shows.is_empty() {
+        return Box::new(future::ok(0));
+    }
+
+    let page = page as i64;
+    let params = shows
+        .iter()
+        .map(|r| {
+            let s = &r.show;
+            db_params![
+                s.name,
+                s.summary,
+                s.cover_img,
+                s.genres,
+                s.runtime,
+                s.premiered,
+                s.external_ids.imdb,
+                s.external_ids.thetvdb,
+                s.external_ids.tvrage,
+                s.provider,
+                s.provider_id,
+                s.provider_url,
+                s.provider_updated,
+                r.network,
+                r.status,
+                page
+            ]
+        })
+        .collect();
+
+    Box::new(
+        pool.send(DBInsertMany::new(
+            "INSERT INTO catalog_shows (
+                name,
+                summary,
+                cover_img,
+                genres,
+                runtime,
+                premiered,
+                imdb_id,
+                thetvdb_id,
+                tvrage_id,
+                provider,
+                provider_id,
+                provider_url,
+                provider_updated,
+                network,
+                status,
+                index_page
+            ) VALUES ",
+            " ON CONFLICT (provider, provider_id) DO UPDATE
+              SET name = EXCLUDED.name,
+                  summary = EXCLUDED.summary,
+                  cover_img = EXCLUDED.cover_img,
+                  genres = EXCLUDED.genres,
+                  runtime = EXCLUDED.runtime,
+                  premiered = EXCLUDED.premiered,
+                  imdb_id = EXCLUDED.imdb_id,
+                  thetvdb_id = EXCLUDED.thetvdb_id,
+                  tvrage_id = EXCLUDED.tvrage_id,
+                  provider_url = EXCLUDED.provider_url,
+                  provider_updated = EXCLUDED.provider_updated,
+                  network = EXCLUDED.network,
+                  status = EXCLUDED.status,
+                  index_page = EXCLUDED.index_page,
+                  aliases_loaded_at = CASE
+                      WHEN catalog_shows.provider_updated IS DISTINCT FROM EXCLUDED.provider_updated
+                      THEN NULL
+                      ELSE catalog_shows.aliases_loaded_at
+                  END
+              RETURNING id",
+            16,
+            params,
+        )).map_err(WebError::from)
+            .and_then(|rows| rows.map(|rows| rows.len())),
+    )
+}
+
+/// Maps the provider ids among `ids` that are in the catalog to the index page
+/// they were listed on and the time they were last updated at the provider.
+pub fn get_catalog_updates(
+    provider: TVMetadataProvider,
+    ids: Vec<String>,
+    pool: Addr<Syn, DBExecutor>,
+) -> Box<Future<Item = HashMap<String, (i64, Option<i64>)>, Error = WebError>> {
+    Box::new(
+        pool.send(DBQuery::new(
+            "SELECT provider_id, index_page, provider_updated
+             FROM catalog_shows
+             WHERE provider = $1 AND provider_id = ANY($2)"
+                .into(),
+            db_params![provider, ids],
+        )).map_err(WebError::from)
+            .and_then(|rows| {
+                rows.map(|rows| {
+                    rows.iter()
+                        .map(|r| {
+                            let page: i64 = r.get("index_page");
+                            (r.get("provider_id"), (page, r.get("provider_updated")))
+                        })
+                        .collect()
+                })
+            }),
+    )
+}
+
+/// The last page of `provider`'s index that the catalog has shows from.
+pub fn get_last_catalog_page(
+    provider: TVMetadataProvider,
+    pool: Addr<Syn, DBExecutor>,
+) -> Box<Future<Item = Option<i64>, Error = WebError>> {
+    Box::new(
+        pool.send(DBQuery::new(
+            "SELECT MAX(index_page) as page FROM catalog_shows WHERE provider = $1".into(),
+            db_params![provider],
+        )).map_err(WebError::from)
+            .and_then(|rows| rows.map(|rows| rows.iter().next().and_then(|r| r.get("page")))),
+    )
+}
+
+/// Up to `limit` catalog shows from `provider` whose aliases haven't been
+/// loaded since they last changed, as their ids and provider ids. The most
+/// recently updated shows come first, since they are the likeliest to be
+/// searched for.
+pub fn get_catalog_shows_without_aliases(
+    provider: TVMetadataProvider,
+    limit: i64,
+    pool: Addr<Syn, DBExecutor>,
+) -> Box<Future<Item = Vec<(i32, String)>, Error = WebError>> {
+    Box::new(
+        pool.send(DBQuery::new(
+            "SELECT id, provider_id
+             FROM catalog_shows
+             WHERE provider = $1 AND aliases_loaded_at IS NULL
+             ORDER BY provider_updated DESC NULLS LAST
+             LIMIT $2"
+                .into(),
+            db_params![provider, limit],
+        )).map_err(WebError::from)
+            .and_then(|rows| {
+                rows.map(|rows| {
+                    rows.iter()
+                        .map(|r| (r.get("id"), r.get("provider_id")))
+                        .collect()
+                })
+            }),
+    )
+}
+
+/// Replaces the aliases of a catalog show and marks them as loaded.
+pub fn replace_catalog_aliases(
+    id: i32,
+    aliases: Vec<String>,
+    pool: Addr<Syn, DBExecutor>,
+) -> Box<Future<Item = (), Error = WebError>> {
+    // Aliases that are kept are neither deleted nor inserted, since both
+    // parts of the query see the aliases as they were before it.
+    Box::new(
+        pool.send(DBQuery::new(
+            "WITH removed AS (
+                 DELETE FROM catalog_show_aliases
+                 WHERE catalog_show_id = $1 AND NOT (name = ANY($2))
+             ), added AS (
+                 INSERT INTO catalog_show_aliases (catalog_show_id, name)
+                 SELECT $1, unnest($2::TEXT[])
+                 ON CONFLICT DO NOTHING
+             )
+             UPDATE catalog_shows SET aliases_loaded_at = now() WHERE id = $1"
+                .into(),
+            db_params![id, aliases],
+        )).map_err(WebError::from)
+            .and_then(|rows| rows.map(|_| ())),
+    )
+}
+
+/// When the catalog of `provider` was last walked in full and when it was
+/// last brought up to date, if ever.
+pub fn get_catalog_sync(
+    provider: TVMetadataProvider,
+    pool: Addr<Syn, DBExecutor>,
+) -> Box<Future<Item = (Option<DateTime<Utc>>, Option<DateTime<Utc>>), Error = WebError>> {
+    Box::new(
+        pool.send(DBQuery::new(
+            "SELECT walked_at, synced_at FROM catalog_syncs WHERE provider = $1".into(),
+            db_params![provider],
+        )).map_err(WebError::from)
+            .and_then(|rows| {
+                rows.map(|rows| match rows.iter().next() {
+                    Some(r) => (r.get("walked_at"), Some(r.get("synced_at"))),
+                    None => (None, None),
+                })
+            }),
+    )
+}
+
+/// Records that the catalog of `provider` was brought up to date, by walking
+/// its whole index if `walked`.
+pub fn set_catalog_synced(
+    provider: TVMetadataProvider,
+    walked: bool,
+    pool: Addr<Syn, DBExecutor>,
+) -> Box<Future<Item = (), Error = WebError>> {
+    Box::new(
+        pool.send(DBQuery::new(
+            "INSERT INTO catalog_syncs (provider, walked_at, synced_at)
+             VALUES ($1, CASE WHEN $2 THEN now() END, now())
+             ON CONFLICT (provider) DO UPDATE
+             SET walked_at = COALESCE(EXCLUDED.walked_at, catalog_syncs.walked_at),
+                 synced_at = EXCLUDED.synced_at"
+                .into(),
+            db_params![provider, walked],
+        )).map_err(WebError::from)
+            .and_then(|rows| rows.map(|_| ())),
+    )
+}
+
+/// Marks the aliases of a catalog show as loaded without changing them, for
+/// shows whose aliases can't be loaded, so that they aren't tried again
+/// until they change.
+pub fn set_catalog_aliases_loaded(
+    id: i32,
+    pool: Addr<Syn, DBExecutor>,
+) -> Box<Future<Item = (), Error = WebError>> {
+    Box::new(
+        pool.send(DBQuery::new(
+            "UPDATE catalog_shows SET aliases_loaded_at = now() WHERE id = $1".into(),
+            db_params![id],
+        )).map_err(WebError::from)
+            .and_then(|rows| rows.map(|_| ())),
+    )
+}
@@ -0,0 +1,180 @@
+use super::*;
+use error::WebError;
+use resources::grab::EpisodeGrab;
+use resources::quality::Quality;
+use resources::wanted::WantedEpisode;
+
+use futures::Future;
+use postgres::types::ToSql;
+
+/// Selects episodes along with their show's name and aliases, quality profile, the quality
+/// of their file and the best quality grabbed so far, in the shape expected by
+/// `WantedEpisode::from`.
+pub const SELECT_WANTED_EPISODE: &'static str = "SELECT E.id as episode_id,
+            E.show_id as episode_show_id,
+            E.season_id as episode_season_id,
+            E.season_num as episode_season_num,
+            E.num as episode_num,
+            E.name as episode_name,
+            E.airdate as episode_airdate,
+            S.name as show_name,
+            ARRAY(SELECT A.name
+                  FROM show_aliases A
+                  WHERE A.show_id = E.show_id
+                  ORDER BY A.name) as show_aliases,
+            P.id as profile_id,
+            P.name as profile_name,
+            P.allowed as profile_allowed,
+            P.cutoff as profile_cutoff,
+            F.quality as file_quality,
+            (SELECT MAX(G.quality)
+             FROM tv_show_episode_grabs G
+             WHERE G.episode_id = E.id) as grab_quality
+     FROM tv_show_episodes E
+     INNER JOIN tv_shows S on S.id = E.show_id
+     INNER JOIN quality_profiles P on P.id = S.quality_profile_id
+     LEFT OUTER JOIN tv_show_episode_files F on F.episode_id = E.id ";
+
+/// Restricts `SELECT_WANTED_EPISODE` to monitored episodes that have aired.
+pub const WHERE_MONITORED_AIRED: &'static str = " WHERE S.monitored
+       AND E.monitored
+       AND E.airdate <= CURRENT_DATE ";
+
+pub fn query_wanted_episodes(
+    query: String,
+    params: Vec<ToSqlWrapper>,
+    pool: Addr<Syn, DBExecutor>,
+) -> Box<Future<Item = Vec<WantedEpisode>, Error = WebError>> {
+    Box::new(
+        pool.send(DBQuery::new(query, params))
+            .map_err(WebError::from)
+            .and_then(|rows| {
+                rows.map(|rows| rows.iter().map(|r| WantedEpisode::from(&r)).collect())
+            }),
+    )
+}
+
+/// Loads a single episode as a search target, regardless of whether it is
+/// monitored. Used when a search is explicitly requested for it.
+pub fn get_wanted_episode(
+    id: i32,
+    pool: Addr<Syn, DBExecutor>,
+) -> Box<Future<Item = WantedEpisode, Error = WebError>> {
+    Box::new(
+        query_wanted_episodes(
+            [SELECT_WANTED_EPISODE, " WHERE E.id = $1"].join(""),
+            db_params![id],
+            pool,
+        ).and_then(move |mut episodes| episodes.pop().ok_or(WebError::ResourceNotFound(id))),
+    )
+}
+
+/// Loads the monitored, aired episodes of a season.
+pub fn get_wanted_season_episodes(
+    season_id: i32,
+    pool: Addr<Syn, DBExecutor>,
+) -> Box<Future<Item = Vec<WantedEpisode>, Error = WebError>> {
+    query_wanted_episodes(
+        [
+            SELECT_WANTED_EPISODE,
+            WHERE_MONITORED_AIRED,
+            " AND E.season_id = $1 ORDER BY E.num",
+        ].join(""),
+        db_params![season_id],
+        pool,
+    )
+}
+
+/// Loads the monitored episodes that aired within the last `days` days.
+pub fn get_recently_aired_episodes(
+    days: i32,
+    pool: Addr<Syn, DBExecutor>,
+) -> Box<Future<Item = Vec<WantedEpisode>, Error = WebError>> {
+    query_wanted_episodes(
+        [
+            SELECT_WANTED_EPISODE,
+            WHERE_MONITORED_AIRED,
+            " AND E.airdate >= CURRENT_DATE - $1 ORDER BY E.airdate",
+        ].join(""),
+        db_params![days],
+        pool,
+    )
+}
+
+pub fn insert_grab(
+    grab: &EpisodeGrab,
+    pool: Addr<Syn, DBExecutor>,
+) -> Box<Future<Item = EpisodeGrab, Error = WebError>> {
+    Box::new(
+        pool.send(DBQuery::new(
+            "INSERT INTO tv_show_episode_grabs (
+                episode_id,
+                title,
+                quality,
+                indexer,
+                download_id,
+                grabbed_at
+            ) VALUES ($1, $2, $3, $4, $5, $6)
+            RETURNING id as grab_id,
+                      episode_id as grab_episode_id,
+                      title as grab_title,
+                      quality as grab_quality,
+                      indexer as grab_indexer,
+                      download_id as grab_download_id,
+                      grabbed_at as grab_grabbed_at"
+                .into(),
+            grab.as_db_params(),
+        )).map_err(WebError::from)
+            .and_then(|rows| rows.map(|rows| EpisodeGrab::from(&rows.get(0)))),
+    )
+}
+
+/// A grab that is still waiting for its download to be imported.
+pub struct PendingImport {
+    pub show_id: i32,
+    pub quality: Quality,
+}
+
+/// Finds the show a download was grabbed for, if it hasn't been imported yet.
+pub fn get_pending_import(
+    download_id: String,
+    pool: Addr<Syn, DBExecutor>,
+) -> Box<Future<Item = Option<PendingImport>, Error = WebError>> {
+    Box::new(
+        pool.send(DBQuery::new(
+            "SELECT E.show_id as show_id,
+                    MAX(G.quality) as quality
+             FROM tv_show_episode_grabs G
+             INNER JOIN tv_show_episodes E on E.id = G.episode_id
+             WHERE G.download_id = $1
+               AND G.imported_at IS NULL
+             GROUP BY E.show_id"
+                .into(),
+            db_params![download_id],
+        )).map_err(WebError::from)
+            .and_then(|rows| {
+                rows.map(|rows| {
+                    rows.iter().next().map(|r| PendingImport {
+                        show_id: r.get("show_id"),
+                        quality: r.get("quality"),
+                    })
+                })
+            }),
+    )
+}
+
+pub fn mark_grab_imported(
+    download_id: String,
+    pool: Addr<Syn, DBExecutor>,
+) -> Box<Future<Item = (), Error = WebError>> {
+    Box::new(
+        pool.send(DBQuery::new(
+            "UPDATE tv_show_episode_grabs
+             SET imported_at = NOW()
+             WHERE download_id = $1"
+                .into(),
+            db_params![download_id],
+        )).map_err(WebError::from)
+            .and_then(|rows| rows.map(|_| ())),
+    )
+}
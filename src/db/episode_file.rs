@@ -0,0 +1,65 @@
+use super::*;
+use error::WebError;
+use resources::episode_file::EpisodeFile;
+
+use futures::Future;
+use postgres::types::ToSql;
+
+/// Returns the files of all of a show's episodes.
+pub fn get_show_episode_files(
+    show_id: i32,
+    pool: Addr<Syn, DBExecutor>,
+) -> Box<Future<Item = Vec<EpisodeFile>, Error = WebError>> {
+    Box::new(
+        pool.send(DBQuery::new(
+            "SELECT F.id as file_id,
+                    F.episode_id as file_episode_id,
+                    F.path as file_path,
+                    F.size as file_size,
+                    F.quality as file_quality,
+                    F.created_at as file_created_at,
+                    F.media_info as file_media_info
+             FROM tv_show_episode_files F
+             INNER JOIN tv_show_episodes E on E.id = F.episode_id
+             WHERE E.show_id = $1"
+                .into(),
+            db_params![show_id],
+        )).map_err(WebError::from)
+            .and_then(|rows| rows.map(|rows| rows.iter().map(|r| EpisodeFile::from(&r)).collect())),
+    )
+}
+
+/// Records the file for an episode, replacing the one it had before.
+pub fn upsert_episode_file(
+    file: &EpisodeFile,
+    pool: Addr<Syn, DBExecutor>,
+) -> Box<Future<Item = EpisodeFile, Error = WebError>> {
+    Box::new(
+        pool.send(DBQuery::new(
+            "INSERT INTO tv_show_episode_files (
+                episode_id,
+                path,
+                size,
+                quality,
+                created_at,
+                media_info
+            ) VALUES ($1, $2, $3, $4, $5, $6)
+            ON CONFLICT (episode_id) DO UPDATE
+            SET path = EXCLUDED.path,
+                size = EXCLUDED.size,
+                quality = EXCLUDED.quality,
+                created_at = EXCLUDED.created_at,
+                media_info = EXCLUDED.media_info
+            RETURNING id as file_id,
+                      episode_id as file_episode_id,
+                      path as file_path,
+                      size as file_size,
+                      quality as file_quality,
+                      created_at as file_created_at,
+                      media_info as file_media_info"
+                .into(),
+            file.as_db_params(),
+        )).map_err(WebError::from)
+            .and_then(|rows| rows.map(|rows| EpisodeFile::from(&rows.get(0)))),
+    )
+}
@@ -0,0 +1,74 @@
+use super::*;
+use error::WebError;
+use resources::import_plan::{ImportPlan, PlannedShow};
+
+use futures::{future, Future};
+use postgres::types::ToSql;
+use serde_json;
+
+const RETURNING_PLAN: &'static str = " RETURNING id as plan_id,
+            root_folder_id as plan_root_folder_id,
+            shows as plan_shows,
+            created_at as plan_created_at,
+            confirmed_at as plan_confirmed_at";
+
+pub fn insert_import_plan(
+    root_folder_id: i32,
+    shows: &[PlannedShow],
+    pool: Addr<Syn, DBExecutor>,
+) -> Box<Future<Item = ImportPlan, Error = WebError>> {
+    let shows = match serde_json::to_value(shows) {
+        Ok(shows) => shows,
+        Err(e) => return Box::new(future::err(WebError::from(e))),
+    };
+
+    Box::new(
+        pool.send(DBQuery::new(
+            ["INSERT INTO import_plans (root_folder_id, shows) VALUES ($1, $2)", RETURNING_PLAN]
+                .join(""),
+            db_params![root_folder_id, shows],
+        )).map_err(WebError::from)
+            .and_then(|rows| rows.map(|rows| ImportPlan::from(&rows.get(0)))),
+    )
+}
+
+pub fn get_import_plan(
+    id: i32,
+    pool: Addr<Syn, DBExecutor>,
+) -> Box<Future<Item = ImportPlan, Error = WebError>> {
+    Box::new(
+        pool.send(DBQuery::new(
+            "SELECT id as plan_id,
+                    root_folder_id as plan_root_folder_id,
+                    shows as plan_shows,
+                    created_at as plan_created_at,
+                    confirmed_at as plan_confirmed_at
+             FROM import_plans
+             WHERE id = $1"
+                .into(),
+            db_params![id],
+        )).map_err(WebError::from)
+            .and_then(move |rows| {
+                let rows = rows?;
+                if rows.is_empty() {
+                    Err(WebError::ResourceNotFound(id))
+                } else {
+                    Ok(ImportPlan::from(&rows.get(0)))
+                }
+            }),
+    )
+}
+
+pub fn mark_import_plan_confirmed(
+    id: i32,
+    pool: Addr<Syn, DBExecutor>,
+) -> Box<Future<Item = ImportPlan, Error = WebError>> {
+    Box::new(
+        pool.send(DBQuery::new(
+            ["UPDATE import_plans SET confirmed_at = NOW() WHERE id = $1", RETURNING_PLAN]
+                .join(""),
+            db_params![id],
+        )).map_err(WebError::from)
+            .and_then(|rows| rows.map(|rows| ImportPlan::from(&rows.get(0)))),
+    )
+}
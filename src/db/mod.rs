@@ -19,7 +19,28 @@ macro_rules! db_params {
     };
 }
 
+pub mod catalog;
+pub mod episode;
+pub mod episode_file;
+pub mod import_plan;
+pub mod person;
+pub mod quality_profile;
+pub mod root_folder;
 pub mod tv_show;
+pub mod wanted;
+
+/// Escapes the wildcards of `LIKE` and `ILIKE` patterns in `text`, so that it
+/// only matches itself.
+pub fn escape_like(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if c == '\\' || c == '%' || c == '_' {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
 
 /// A thread-safe wrapper around the ToSql trait.
 ///
@@ -167,3 +188,15 @@ impl DBInsertMany {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escapes_like_wildcards() {
+        assert_eq!(escape_like("100%_real"), "100\\%\\_real");
+        assert_eq!(escape_like("C:\\Shows"), "C:\\\\Shows");
+        assert_eq!(escape_like("Breaking Bad"), "Breaking Bad");
+    }
+}
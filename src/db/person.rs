@@ -0,0 +1,279 @@
+use std::collections::HashMap;
+
+use super::*;
+use error::WebError;
+use resources::person::*;
+
+use futures::{future, Future};
+use postgres::types::ToSql;
+
+const PERSON_COLUMNS: &'static str = "P.id as person_id,
+            P.name as person_name,
+            P.image as person_image,
+            P.birthday as person_birthday,
+            P.deathday as person_deathday,
+            P.gender as person_gender,
+            P.country as person_country,
+            P.provider as person_provider,
+            P.provider_id as person_provider_id,
+            P.provider_url as person_provider_url";
+
+pub fn get_person(
+    id: i32,
+    pool: Addr<Syn, DBExecutor>,
+) -> Box<Future<Item = Person, Error = WebError>> {
+    Box::new(
+        pool.send(DBQuery::new(
+            ["SELECT ", PERSON_COLUMNS, " FROM people P WHERE P.id = $1"].join(""),
+            db_params![id],
+        )).map_err(WebError::from)
+            .and_then(move |rows| {
+                let rows = rows?;
+                if rows.is_empty() {
+                    Err(WebError::ResourceNotFound(id))
+                } else {
+                    Ok(Person::from(&rows.get(0)))
+                }
+            }),
+    )
+}
+
+/// Loads the shows in the library that a person is in the cast or crew of.
+pub fn get_person_shows(
+    id: i32,
+    pool: Addr<Syn, DBExecutor>,
+) -> Box<Future<Item = Vec<PersonShow>, Error = WebError>> {
+    Box::new(
+        pool.send(DBQuery::new(
+            "SELECT S.id as show_id,
+                    S.name as show_name,
+                    S.cover_img as show_cover_img,
+                    ARRAY(SELECT C.character
+                          FROM show_cast C
+                          WHERE C.show_id = S.id AND C.person_id = $1
+                          ORDER BY C.ord) as person_characters,
+                    ARRAY(SELECT W.job
+                          FROM show_crew W
+                          WHERE W.show_id = S.id AND W.person_id = $1
+                          ORDER BY W.job) as person_jobs
+             FROM tv_shows S
+             WHERE S.id IN (SELECT show_id FROM show_cast WHERE person_id = $1
+                            UNION
+                            SELECT show_id FROM show_crew WHERE person_id = $1)
+             ORDER BY S.name"
+                .into(),
+            db_params![id],
+        )).map_err(WebError::from)
+            .and_then(|rows| {
+                rows.map(|rows| rows.iter().map(|r| PersonShow::from(&r)).collect())
+            }),
+    )
+}
+
+pub fn get_show_cast(
+    show_id: i32,
+    pool: Addr<Syn, DBExecutor>,
+) -> Box<Future<Item = Vec<CastMember>, Error = WebError>> {
+    Box::new(
+        pool.send(DBQuery::new(
+            [
+                "SELECT ",
+                PERSON_COLUMNS,
+                ",
+                    C.character as cast_character,
+                    C.character_img as cast_character_img
+                 FROM show_cast C
+                 INNER JOIN people P on P.id = C.person_id
+                 WHERE C.show_id = $1
+                 ORDER BY C.ord",
+            ].join(""),
+            db_params![show_id],
+        )).map_err(WebError::from)
+            .and_then(|rows| {
+                rows.map(|rows| rows.iter().map(|r| CastMember::from(&r)).collect())
+            }),
+    )
+}
+
+pub fn get_show_crew(
+    show_id: i32,
+    pool: Addr<Syn, DBExecutor>,
+) -> Box<Future<Item = Vec<CrewMember>, Error = WebError>> {
+    Box::new(
+        pool.send(DBQuery::new(
+            [
+                "SELECT ",
+                PERSON_COLUMNS,
+                ",
+                    W.job as crew_job
+                 FROM show_crew W
+                 INNER JOIN people P on P.id = W.person_id
+                 WHERE W.show_id = $1
+                 ORDER BY W.job, P.name",
+            ].join(""),
+            db_params![show_id],
+        )).map_err(WebError::from)
+            .and_then(|rows| {
+                rows.map(|rows| rows.iter().map(|r| CrewMember::from(&r)).collect())
+            }),
+    )
+}
+
+/// Replaces the cast and crew of a show. People are shared by all shows, so
+/// they are updated in place, matched by their provider ids.
+pub fn replace_show_credits(
+    show_id: i32,
+    credits: ShowCredits,
+    pool: Addr<Syn, DBExecutor>,
+) -> Box<Future<Item = (), Error = WebError>> {
+    let mut people: Vec<Person> = vec![];
+    {
+        let credited = credits
+            .cast
+            .iter()
+            .map(|c| &c.person)
+            .chain(credits.crew.iter().map(|c| &c.person));
+        for person in credited {
+            let known = people
+                .iter()
+                .any(|p| p.provider == person.provider && p.provider_id == person.provider_id);
+            if !known {
+                people.push(person.clone());
+            }
+        }
+    }
+
+    let delete_pool = pool.clone();
+    Box::new(
+        upsert_people(people, pool.clone())
+            .join(delete_show_credits(show_id, delete_pool))
+            .and_then(move |(people, _)| {
+                let ids: HashMap<_, _> = people
+                    .into_iter()
+                    .map(|p| ((p.provider, p.provider_id), p.id))
+                    .collect();
+                let person_id = |p: &Person| ids[&(p.provider, p.provider_id.clone())];
+
+                let cast = credits
+                    .cast
+                    .iter()
+                    .enumerate()
+                    .map(|(ord, c)| {
+                        db_params![
+                            show_id,
+                            person_id(&c.person),
+                            c.character,
+                            c.character_img,
+                            ord as i32
+                        ]
+                    })
+                    .collect();
+                let crew = credits
+                    .crew
+                    .iter()
+                    .map(|c| db_params![show_id, person_id(&c.person), c.job])
+                    .collect();
+
+                insert_credits(
+                    "INSERT INTO show_cast (show_id, person_id, character, character_img, ord)
+                     VALUES ",
+                    5,
+                    cast,
+                    pool.clone(),
+                ).join(insert_credits(
+                    "INSERT INTO show_crew (show_id, person_id, job) VALUES ",
+                    3,
+                    crew,
+                    pool,
+                ))
+            })
+            .map(|_| ()),
+    )
+}
+
+fn upsert_people(
+    people: Vec<Person>,
+    pool: Addr<Syn, DBExecutor>,
+) -> Box<Future<Item = Vec<Person>, Error = WebError>> {
+    if people.is_empty() {
+        return Box::new(future::ok(vec![]));
+    }
+
+    let params = people.iter().map(|p| p.as_db_params()).collect();
+
+    Box::new(
+        pool.send(DBInsertMany::new(
+            "INSERT INTO people (
+                name,
+                image,
+                birthday,
+                deathday,
+                gender,
+                country,
+                provider,
+                provider_id,
+                provider_url
+            ) VALUES ",
+            " ON CONFLICT (provider, provider_id) DO UPDATE
+              SET name = EXCLUDED.name,
+                  image = EXCLUDED.image,
+                  birthday = EXCLUDED.birthday,
+                  deathday = EXCLUDED.deathday,
+                  gender = EXCLUDED.gender,
+                  country = EXCLUDED.country,
+                  provider_url = EXCLUDED.provider_url
+              RETURNING id as person_id,
+                        name as person_name,
+                        image as person_image,
+                        birthday as person_birthday,
+                        deathday as person_deathday,
+                        gender as person_gender,
+                        country as person_country,
+                        provider as person_provider,
+                        provider_id as person_provider_id,
+                        provider_url as person_provider_url",
+            9,
+            params,
+        ))
+            .map_err(WebError::from)
+            .and_then(|rows| rows.map(|rows| rows.iter().map(|r| Person::from(&r)).collect())),
+    )
+}
+
+fn delete_show_credits(
+    show_id: i32,
+    pool: Addr<Syn, DBExecutor>,
+) -> Box<Future<Item = (), Error = WebError>> {
+    let delete = |table: &str| {
+        pool.send(DBQuery::new(
+            ["DELETE FROM ", table, " WHERE show_id = $1"].join(""),
+            db_params![show_id],
+        )).map_err(WebError::from)
+            .and_then(|rows| rows.map(|_| ()))
+    };
+
+    Box::new(delete("show_cast").join(delete("show_crew")).map(|_| ()))
+}
+
+/// Providers can credit a person for the same character or job more than
+/// once, which is only stored once.
+fn insert_credits(
+    query: &'static str,
+    values: u8,
+    params: Vec<Vec<ToSqlWrapper>>,
+    pool: Addr<Syn, DBExecutor>,
+) -> Box<Future<Item = (), Error = WebError>> {
+    if params.is_empty() {
+        return Box::new(future::ok(()));
+    }
+
+    Box::new(
+        pool.send(DBInsertMany::new(
+            query,
+            " ON CONFLICT DO NOTHING",
+            values,
+            params,
+        )).map_err(WebError::from)
+            .and_then(|rows| rows.map(|_| ())),
+    )
+}
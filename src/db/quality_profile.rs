@@ -0,0 +1,32 @@
+use super::*;
+use error::WebError;
+use resources::quality::QualityProfile;
+
+use futures::Future;
+use postgres::types::ToSql;
+
+pub fn get_quality_profile(
+    id: i32,
+    pool: Addr<Syn, DBExecutor>,
+) -> Box<Future<Item = QualityProfile, Error = WebError>> {
+    Box::new(
+        pool.send(DBQuery::new(
+            "SELECT id as profile_id,
+                    name as profile_name,
+                    allowed as profile_allowed,
+                    cutoff as profile_cutoff
+             FROM quality_profiles
+             WHERE id = $1"
+                .into(),
+            db_params![id],
+        )).map_err(WebError::from)
+            .and_then(move |rows| {
+                let rows = rows?;
+                if rows.is_empty() {
+                    Err(WebError::ResourceNotFound(id))
+                } else {
+                    Ok(QualityProfile::from(&rows.get(0)))
+                }
+            }),
+    )
+}
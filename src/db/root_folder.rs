@@ -0,0 +1,103 @@
+use super::*;
+use error::WebError;
+use resources::root_folder::RootFolder;
+
+use futures::Future;
+use postgres::types::ToSql;
+
+const SELECT_ROOT_FOLDER: &'static str = "SELECT id as root_folder_id,
+            path as root_folder_path
+     FROM root_folders ";
+
+pub fn get_all_root_folders(
+    pool: Addr<Syn, DBExecutor>,
+) -> Box<Future<Item = Vec<RootFolder>, Error = WebError>> {
+    Box::new(
+        pool.send(DBQuery::new(
+            [SELECT_ROOT_FOLDER, " ORDER BY path"].join(""),
+            vec![],
+        )).map_err(WebError::from)
+            .and_then(|rows| {
+                rows.map(|rows| rows.iter().map(|r| RootFolder::from(&r)).collect())
+            }),
+    )
+}
+
+pub fn get_root_folder(
+    id: i32,
+    pool: Addr<Syn, DBExecutor>,
+) -> Box<Future<Item = RootFolder, Error = WebError>> {
+    Box::new(
+        pool.send(DBQuery::new(
+            [SELECT_ROOT_FOLDER, " WHERE id = $1"].join(""),
+            db_params![id],
+        )).map_err(WebError::from)
+            .and_then(move |rows| {
+                let rows = rows?;
+                if rows.is_empty() {
+                    Err(WebError::ResourceNotFound(id))
+                } else {
+                    Ok(RootFolder::from(&rows.get(0)))
+                }
+            }),
+    )
+}
+
+pub fn insert_root_folder(
+    path: String,
+    pool: Addr<Syn, DBExecutor>,
+) -> Box<Future<Item = RootFolder, Error = WebError>> {
+    Box::new(
+        pool.send(DBQuery::new(
+            "INSERT INTO root_folders (path) VALUES ($1)
+             RETURNING id as root_folder_id,
+                       path as root_folder_path"
+                .into(),
+            db_params![path],
+        )).map_err(WebError::from)
+            .and_then(|rows| rows.map(|rows| RootFolder::from(&rows.get(0)))),
+    )
+}
+
+pub fn update_root_folder(
+    id: i32,
+    path: String,
+    pool: Addr<Syn, DBExecutor>,
+) -> Box<Future<Item = RootFolder, Error = WebError>> {
+    Box::new(
+        pool.send(DBQuery::new(
+            "UPDATE root_folders SET path = $2 WHERE id = $1
+             RETURNING id as root_folder_id,
+                       path as root_folder_path"
+                .into(),
+            db_params![id, path],
+        )).map_err(WebError::from)
+            .and_then(move |rows| {
+                let rows = rows?;
+                if rows.is_empty() {
+                    Err(WebError::ResourceNotFound(id))
+                } else {
+                    Ok(RootFolder::from(&rows.get(0)))
+                }
+            }),
+    )
+}
+
+pub fn delete_root_folder(
+    id: i32,
+    pool: Addr<Syn, DBExecutor>,
+) -> Box<Future<Item = (), Error = WebError>> {
+    Box::new(
+        pool.send(DBQuery::new(
+            "DELETE FROM root_folders WHERE id = $1 RETURNING id".into(),
+            db_params![id],
+        )).map_err(WebError::from)
+            .and_then(move |rows| {
+                if rows?.is_empty() {
+                    Err(WebError::ResourceNotFound(id))
+                } else {
+                    Ok(())
+                }
+            }),
+    )
+}
@@ -15,6 +15,20 @@ pub const SELECT_JOIN_SHOW: &'static str = "SELECT A.id as show_id,
             A.provider as show_provider,
             A.provider_id as show_provider_id,
             A.provider_url as show_provider_url,
+            A.monitored as show_monitored,
+            A.quality_profile_id as show_quality_profile_id,
+            A.root_folder_id as show_root_folder_id,
+            A.path as show_path,
+            A.fanart_img as show_fanart_img,
+            A.genres as show_genres,
+            A.runtime as show_runtime,
+            A.premiered as show_premiered,
+            A.imdb_id as show_imdb_id,
+            A.thetvdb_id as show_thetvdb_id,
+            A.tvrage_id as show_tvrage_id,
+            A.metadata_sources as show_metadata_sources,
+            A.provider_updated as show_provider_updated,
+            ARRAY(SELECT name FROM show_aliases WHERE show_id = A.id ORDER BY name) as show_aliases,
             B.id as season_id,
             B.show_id as season_show_id,
             B.num as season_num,
@@ -23,6 +37,7 @@ pub const SELECT_JOIN_SHOW: &'static str = "SELECT A.id as show_id,
             B.cover_img as season_cover_img,
             B.provider_id as season_provider_id,
             B.provider_url as season_provider_url,
+            B.metadata_sources as season_metadata_sources,
             C.id as episode_id,
             C.show_id as episode_show_id,
             C.season_id as episode_season_id,
@@ -32,12 +47,36 @@ pub const SELECT_JOIN_SHOW: &'static str = "SELECT A.id as show_id,
             C.summary as episode_summary,
             C.cover_img as episode_cover_img,
             C.runtime as episode_runtime,
+            C.airdate as episode_airdate,
+            C.monitored as episode_monitored,
             C.provider_id as episode_provider_id,
-            C.provider_url as episode_provider_url
+            C.provider_url as episode_provider_url,
+            C.metadata_sources as episode_metadata_sources
      FROM tv_shows A
      LEFT OUTER JOIN tv_show_seasons B on B.show_id = A.id
      LEFT OUTER JOIN tv_show_episodes C on C.show_id = A.id AND C.season_id = B.id ";
 
+const RETURNING_SHOW: &'static str = " RETURNING id as show_id,
+            name as show_name,
+            summary as show_summary,
+            cover_img as show_cover_img,
+            provider as show_provider,
+            provider_id as show_provider_id,
+            provider_url as show_provider_url,
+            monitored as show_monitored,
+            quality_profile_id as show_quality_profile_id,
+            root_folder_id as show_root_folder_id,
+            path as show_path,
+            fanart_img as show_fanart_img,
+            genres as show_genres,
+            runtime as show_runtime,
+            premiered as show_premiered,
+            imdb_id as show_imdb_id,
+            thetvdb_id as show_thetvdb_id,
+            tvrage_id as show_tvrage_id,
+            metadata_sources as show_metadata_sources,
+            provider_updated as show_provider_updated";
+
 pub fn process_single_joined_show<'a>(rows: Vec<Row<'a>>) -> TVShow {
     let mut show = TVShow::from(&rows[0]);
     let mut show_seasons = HashMap::new();
@@ -125,34 +164,179 @@ pub fn get_all_shows(
     )
 }
 
+/// Finds the shows in the library whose name or one of whose aliases contains
+/// `q`, ignoring case.
+pub fn search_shows(
+    q: &str,
+    pool: Addr<Syn, DBExecutor>,
+) -> Box<Future<Item = Result<Vec<TVShow>, WebError>, Error = WebError>> {
+    Box::new(
+        pool.send(DBQuery::new(
+            [
+                SELECT_JOIN_SHOW,
+                " WHERE A.name ILIKE '%' || $1 || '%'
+                     OR EXISTS (SELECT 1
+                                FROM show_aliases L
+                                WHERE L.show_id = A.id AND L.name ILIKE '%' || $1 || '%')",
+            ].join(""),
+            db_params![escape_like(q)],
+        )).map(|rows| match rows {
+                Ok(rows) => Ok(process_multiple_joined_shows(rows)),
+                Err(e) => Err(e.into()),
+            })
+            .map_err(WebError::from),
+    )
+}
+
+/// Finds the show in the library with the given id in another database.
+pub fn get_show_by_external_id(
+    id: &ExternalId,
+    pool: Addr<Syn, DBExecutor>,
+) -> Box<Future<Item = Option<TVShow>, Error = WebError>> {
+    let (condition, params) = match *id {
+        ExternalId::IMDB(ref imdb) => (" WHERE A.imdb_id = $1", db_params![imdb]),
+        ExternalId::TheTVDB(id) => (" WHERE A.thetvdb_id = $1", db_params![id as i64]),
+        ExternalId::TVRage(id) => (" WHERE A.tvrage_id = $1", db_params![id as i64]),
+    };
+
+    Box::new(
+        pool.send(DBQuery::new([SELECT_JOIN_SHOW, condition].join(""), params))
+            .map_err(WebError::from)
+            .and_then(|rows| {
+                let rows = rows?;
+                if rows.is_empty() {
+                    Ok(None)
+                } else {
+                    let mut shows = process_multiple_joined_shows(rows);
+                    shows.sort_by_key(|show| show.id);
+                    Ok(shows.into_iter().next())
+                }
+            }),
+    )
+}
+
+/// Maps the provider ids of the library's shows from `provider` to their ids
+/// and the time they were last updated at the provider, if known.
+pub fn get_show_updates_by_provider(
+    provider: TVMetadataProvider,
+    pool: Addr<Syn, DBExecutor>,
+) -> Box<Future<Item = HashMap<String, (i32, Option<i64>)>, Error = WebError>> {
+    Box::new(
+        pool.send(DBQuery::new(
+            "SELECT id, provider_id, provider_updated FROM tv_shows WHERE provider = $1".into(),
+            db_params![provider],
+        )).map_err(WebError::from)
+            .and_then(|rows| {
+                rows.map(|rows| {
+                    rows.iter()
+                        .map(|r| (r.get("provider_id"), (r.get("id"), r.get("provider_updated"))))
+                        .collect()
+                })
+            }),
+    )
+}
+
+/// Maps the provider ids of the library's shows from `provider` to their ids.
+pub fn get_show_ids_by_provider(
+    provider: TVMetadataProvider,
+    pool: Addr<Syn, DBExecutor>,
+) -> Box<Future<Item = HashMap<String, i32>, Error = WebError>> {
+    Box::new(
+        pool.send(DBQuery::new(
+            "SELECT id, provider_id FROM tv_shows WHERE provider = $1".into(),
+            db_params![provider],
+        )).map_err(WebError::from)
+            .and_then(|rows| {
+                rows.map(|rows| {
+                    rows.iter()
+                        .map(|r| (r.get("provider_id"), r.get("id")))
+                        .collect()
+                })
+            }),
+    )
+}
+
+/// Maps the provider ids among `ids` of the library's shows from `provider` to
+/// their ids.
+pub fn get_show_ids_by_provider_ids(
+    provider: TVMetadataProvider,
+    ids: Vec<String>,
+    pool: Addr<Syn, DBExecutor>,
+) -> Box<Future<Item = HashMap<String, i32>, Error = WebError>> {
+    if ids.is_empty() {
+        return Box::new(future::ok(HashMap::new()));
+    }
+
+    Box::new(
+        pool.send(DBQuery::new(
+            "SELECT id, provider_id FROM tv_shows WHERE provider = $1 AND provider_id = ANY($2)"
+                .into(),
+            db_params![provider, ids],
+        )).map_err(WebError::from)
+            .and_then(|rows| {
+                rows.map(|rows| {
+                    rows.iter()
+                        .map(|r| (r.get("provider_id"), r.get("id")))
+                        .collect()
+                })
+            }),
+    )
+}
+
+/// Adds a show to the library, failing with `ShowExists` if it already has
+/// the show from the same provider.
 pub fn insert_show(
     show: &TVShow,
     pool: Addr<Syn, DBExecutor>,
 ) -> Box<Future<Item = TVShow, Error = WebError>> {
+    let aliases = show.aliases.clone();
+    let aliases_pool = pool.clone();
+    let provider = show.provider;
+    let provider_id = show.provider_id.clone();
+    let existing_pool = pool.clone();
     let insert_show =
         pool.send(DBQuery::new(
-            "INSERT INTO tv_shows (
+            ["INSERT INTO tv_shows (
                 name,
                 summary,
                 cover_img,
                 provider,
                 provider_id,
-                provider_url
-            ) VALUES ($1, $2, $3, $4, $5, $6)
-            RETURNING id as show_id,
-                      name as show_name,
-                      summary as show_summary,
-                      cover_img as show_cover_img,
-                      provider as show_provider,
-                      provider_id as show_provider_id,
-                      provider_url as show_provider_url"
-                .into(),
+                provider_url,
+                monitored,
+                quality_profile_id,
+                root_folder_id,
+                path,
+                fanart_img,
+                genres,
+                runtime,
+                premiered,
+                imdb_id,
+                thetvdb_id,
+                tvrage_id,
+                metadata_sources,
+                provider_updated
+            ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10,
+                      $11, $12, $13, $14, $15, $16, $17, $18, $19)
+            ON CONFLICT (provider, provider_id) DO NOTHING",
+             RETURNING_SHOW].join(""),
             show.as_db_params(),
-        )).map(|rows| match rows {
-                Ok(rows) => TVShow::from(&rows.get(0)),
-                Err(e) => panic!("An ERROR: {:?}", e),
+        )).map_err(WebError::from)
+            .and_then(move |rows| -> Box<Future<Item = TVShow, Error = WebError>> {
+                match rows {
+                    Ok(ref rows) if rows.is_empty() => {
+                        Box::new(show_exists(provider, provider_id, existing_pool))
+                    }
+                    Ok(rows) => Box::new(future::ok(TVShow::from(&rows.get(0)))),
+                    Err(e) => Box::new(future::err(e)),
+                }
             })
-            .map_err(WebError::from);
+            .and_then(move |mut show| {
+                replace_provider_aliases(show.id, aliases, aliases_pool).map(move |aliases| {
+                    show.aliases = aliases;
+                    show
+                })
+            });
 
     if show.seasons.is_some() {
         let seasons = show.seasons.clone().unwrap();
@@ -175,6 +359,199 @@ pub fn insert_show(
     }
 }
 
+/// Fails with the id of the library's show from `provider` with `provider_id`,
+/// which another insert added first.
+fn show_exists(
+    provider: TVMetadataProvider,
+    provider_id: String,
+    pool: Addr<Syn, DBExecutor>,
+) -> Box<Future<Item = TVShow, Error = WebError>> {
+    Box::new(
+        get_show_ids_by_provider_ids(provider, vec![provider_id.clone()], pool).and_then(
+            move |ids| match ids.get(&provider_id) {
+                Some(&id) => Err(WebError::ShowExists(id)),
+                None => Err(WebError::InvalidRequest(format!(
+                    "Unable to add show {} from {:?}",
+                    provider_id, provider
+                ))),
+            },
+        ),
+    )
+}
+
+/// Updates the metadata of a show that is already in the library. Settings
+/// such as its path and quality profile, and user defined aliases, are left
+/// as they are.
+pub fn update_show(
+    show: &TVShow,
+    pool: Addr<Syn, DBExecutor>,
+) -> Box<Future<Item = TVShow, Error = WebError>> {
+    let show_id = show.id;
+    let aliases = show.aliases.clone();
+    let aliases_pool = pool.clone();
+
+    Box::new(
+        pool.send(DBQuery::new(
+            ["UPDATE tv_shows
+             SET name = $2,
+                 summary = $3,
+                 cover_img = $4,
+                 provider_url = $5,
+                 fanart_img = $6,
+                 genres = $7,
+                 runtime = $8,
+                 premiered = $9,
+                 imdb_id = $10,
+                 thetvdb_id = $11,
+                 tvrage_id = $12,
+                 metadata_sources = $13,
+                 provider_updated = $14
+             WHERE id = $1",
+             RETURNING_SHOW].join(""),
+            db_params![
+                show.id,
+                show.name,
+                show.summary,
+                show.cover_img,
+                show.provider_url,
+                show.fanart_img,
+                show.genres,
+                show.runtime,
+                show.premiered,
+                show.external_ids.imdb,
+                show.external_ids.thetvdb,
+                show.external_ids.tvrage,
+                metadata_sources_value(&show.metadata_sources),
+                show.provider_updated
+            ],
+        )).map_err(WebError::from)
+            .and_then(move |rows| {
+                let rows = rows?;
+                if rows.is_empty() {
+                    Err(WebError::ResourceNotFound(show_id))
+                } else {
+                    Ok(TVShow::from(&rows.get(0)))
+                }
+            })
+            .and_then(move |mut show| {
+                replace_provider_aliases(show_id, aliases, aliases_pool).map(move |aliases| {
+                    show.aliases = aliases;
+                    show
+                })
+            }),
+    )
+}
+
+const SELECT_ALIAS: &'static str = "SELECT id as alias_id,
+            show_id as alias_show_id,
+            name as alias_name,
+            user_defined as alias_user_defined
+     FROM show_aliases ";
+
+const RETURNING_ALIAS: &'static str = " RETURNING id as alias_id,
+            show_id as alias_show_id,
+            name as alias_name,
+            user_defined as alias_user_defined";
+
+pub fn get_show_aliases(
+    show_id: i32,
+    pool: Addr<Syn, DBExecutor>,
+) -> Box<Future<Item = Vec<ShowAlias>, Error = WebError>> {
+    Box::new(
+        pool.send(DBQuery::new(
+            [SELECT_ALIAS, "WHERE show_id = $1 ORDER BY name"].join(""),
+            db_params![show_id],
+        )).map_err(WebError::from)
+            .and_then(|rows| rows.map(|rows| rows.iter().map(|r| ShowAlias::from(&r)).collect())),
+    )
+}
+
+/// Adds an alias that the user gave a show. If the show's providers already
+/// list the same name, it becomes user defined so that it is kept.
+pub fn insert_show_alias(
+    show_id: i32,
+    name: &str,
+    pool: Addr<Syn, DBExecutor>,
+) -> Box<Future<Item = ShowAlias, Error = WebError>> {
+    Box::new(
+        pool.send(DBQuery::new(
+            [
+                "INSERT INTO show_aliases (show_id, name, user_defined) VALUES ($1, $2, TRUE)
+                 ON CONFLICT (show_id, name) DO UPDATE SET user_defined = TRUE",
+                RETURNING_ALIAS,
+            ].join(""),
+            db_params![show_id, name],
+        )).map_err(WebError::from)
+            .and_then(|rows| rows.map(|rows| ShowAlias::from(&rows.get(0)))),
+    )
+}
+
+pub fn delete_show_alias(
+    show_id: i32,
+    id: i32,
+    pool: Addr<Syn, DBExecutor>,
+) -> Box<Future<Item = (), Error = WebError>> {
+    Box::new(
+        pool.send(DBQuery::new(
+            "DELETE FROM show_aliases WHERE id = $1 AND show_id = $2 RETURNING id".into(),
+            db_params![id, show_id],
+        )).map_err(WebError::from)
+            .and_then(move |rows| {
+                let rows = rows?;
+                if rows.is_empty() {
+                    Err(WebError::ResourceNotFound(id))
+                } else {
+                    Ok(())
+                }
+            }),
+    )
+}
+
+/// Replaces the aliases that a show's providers list with `aliases`, and
+/// returns all of the show's aliases, including user defined ones.
+fn replace_provider_aliases(
+    show_id: i32,
+    aliases: Vec<String>,
+    pool: Addr<Syn, DBExecutor>,
+) -> Box<Future<Item = Vec<String>, Error = WebError>> {
+    let insert_pool = pool.clone();
+    let select_pool = pool.clone();
+
+    let delete = pool.send(DBQuery::new(
+        "DELETE FROM show_aliases WHERE show_id = $1 AND NOT user_defined".into(),
+        db_params![show_id],
+    )).map_err(WebError::from)
+        .and_then(|rows| rows.map(|_| ()));
+
+    let insert = move |_| -> Box<Future<Item = (), Error = WebError>> {
+        if aliases.is_empty() {
+            return Box::new(future::ok(()));
+        }
+
+        let params = aliases.iter().map(|a| db_params![show_id, a]).collect();
+        Box::new(
+            insert_pool
+                .send(DBInsertMany::new(
+                    "INSERT INTO show_aliases (show_id, name) VALUES ",
+                    " ON CONFLICT DO NOTHING",
+                    2,
+                    params,
+                ))
+                .map_err(WebError::from)
+                .and_then(|rows| rows.map(|_| ())),
+        )
+    };
+
+    Box::new(
+        delete
+            .and_then(insert)
+            .and_then(move |_| get_show_aliases(show_id, select_pool))
+            .map(|aliases| aliases.into_iter().map(|a| a.name).collect()),
+    )
+}
+
+/// Inserts seasons along with their episodes. Seasons and episodes that are
+/// already stored for the show are updated instead.
 pub fn insert_seasons(
     seasons: Vec<TVShowSeason>,
     pool: Addr<Syn, DBExecutor>,
@@ -195,8 +572,16 @@ pub fn insert_seasons(
                     summary,
                     cover_img,
                     provider_id,
-                    provider_url
-                ) VALUES ($1, $2, $3, $4, $5, $6, $7)
+                    provider_url,
+                    metadata_sources
+                ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+                ON CONFLICT (show_id, provider_id) DO UPDATE
+                SET num = EXCLUDED.num,
+                    name = EXCLUDED.name,
+                    summary = EXCLUDED.summary,
+                    cover_img = EXCLUDED.cover_img,
+                    provider_url = EXCLUDED.provider_url,
+                    metadata_sources = EXCLUDED.metadata_sources
                 RETURNING id as season_id,
                           show_id as season_show_id,
                           num as season_num,
@@ -204,14 +589,12 @@ pub fn insert_seasons(
                           summary as season_summary,
                           cover_img as season_cover_img,
                           provider_id as season_provider_id,
-                          provider_url as season_provider_url"
+                          provider_url as season_provider_url,
+                          metadata_sources as season_metadata_sources"
                     .into(),
                 season.as_db_params(),
-            )).map(|row| match row {
-                    Ok(row) => (seasons, TVShowSeason::from(&row.get(0))),
-                    Err(e) => panic!("An ERROR: {:?}", e),
-                })
-                .map_err(WebError::from)
+            )).map_err(WebError::from)
+                .and_then(|rows| rows.map(|rows| (seasons, TVShowSeason::from(&rows.get(0)))))
         }));
 
         fut = if episodes.is_some() {
@@ -247,6 +630,10 @@ pub fn insert_episodes(
     episodes: Vec<TVShowEpisode>,
     pool: Addr<Syn, DBExecutor>,
 ) -> Box<Future<Item = Vec<TVShowEpisode>, Error = WebError>> {
+    if episodes.is_empty() {
+        return Box::new(future::ok(vec![]));
+    }
+
     let params = episodes.into_iter().map(|e| e.as_db_params()).collect();
 
     Box::new(
@@ -260,10 +647,24 @@ pub fn insert_episodes(
                 summary,
                 cover_img,
                 runtime,
+                airdate,
+                monitored,
                 provider_id,
-                provider_url
+                provider_url,
+                metadata_sources
             ) VALUES ",
-            " RETURNING id as episode_id,
+            " ON CONFLICT (show_id, provider_id) DO UPDATE
+              SET season_id = EXCLUDED.season_id,
+                  num = EXCLUDED.num,
+                  season_num = EXCLUDED.season_num,
+                  name = EXCLUDED.name,
+                  summary = EXCLUDED.summary,
+                  cover_img = EXCLUDED.cover_img,
+                  runtime = EXCLUDED.runtime,
+                  airdate = EXCLUDED.airdate,
+                  provider_url = EXCLUDED.provider_url,
+                  metadata_sources = EXCLUDED.metadata_sources
+              RETURNING id as episode_id,
                         show_id as episode_show_id,
                         season_id as episode_season_id,
                         num as episode_num,
@@ -272,14 +673,16 @@ pub fn insert_episodes(
                         summary as episode_summary,
                         cover_img as episode_cover_img,
                         runtime as episode_runtime,
+                        airdate as episode_airdate,
+                        monitored as episode_monitored,
                         provider_id as episode_provider_id,
-                        provider_url as episode_provider_url",
-            10,
+                        provider_url as episode_provider_url,
+                        metadata_sources as episode_metadata_sources",
+            13,
             params,
-        )).map(|rows| match rows {
-                Ok(rows) => rows.into_iter().map(|r| TVShowEpisode::from(&r)).collect(),
-                Err(e) => panic!("An ERROR: {:?}", e),
-            })
-            .map_err(WebError::from),
+        )).map_err(WebError::from)
+            .and_then(|rows| {
+                rows.map(|rows| rows.into_iter().map(|r| TVShowEpisode::from(&r)).collect())
+            }),
     )
 }
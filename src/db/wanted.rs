@@ -0,0 +1,88 @@
+use super::episode::{query_wanted_episodes, SELECT_WANTED_EPISODE, WHERE_MONITORED_AIRED};
+use super::*;
+use error::WebError;
+use resources::page::{Page, Paging};
+use resources::wanted::{WantedEpisode, WantedSortKey};
+
+use futures::Future;
+use postgres::types::ToSql;
+
+/// Joins each episode's latest grab, if any, as `LG`. A grab whose
+/// `imported_at` is unset is still downloading.
+const JOIN_LATEST_GRAB: &'static str = " LEFT OUTER JOIN LATERAL (
+         SELECT G.id, G.quality, G.imported_at
+         FROM tv_show_episode_grabs G
+         WHERE G.episode_id = E.id
+         ORDER BY G.grabbed_at DESC, G.id DESC
+         LIMIT 1
+     ) LG ON TRUE ";
+
+/// Aired, monitored episodes that have no file yet and aren't being
+/// downloaded.
+const WHERE_MISSING: &'static str = " AND F.id IS NULL
+       AND (LG.id IS NULL OR LG.imported_at IS NOT NULL) ";
+
+/// Aired, monitored episodes whose file is below their profile's cutoff,
+/// unless a download that meets the cutoff is already under way.
+const WHERE_BELOW_CUTOFF: &'static str = " AND F.quality < P.cutoff
+       AND (LG.id IS NULL OR LG.imported_at IS NOT NULL OR LG.quality < P.cutoff) ";
+
+fn get_wanted_page(
+    filter: &'static str,
+    paging: Paging,
+    sort_key: WantedSortKey,
+    pool: Addr<Syn, DBExecutor>,
+) -> Box<Future<Item = Page<WantedEpisode>, Error = WebError>> {
+    let count = pool
+        .send(DBQuery::new(
+            [
+                "SELECT COUNT(*) as total
+                 FROM tv_show_episodes E
+                 INNER JOIN tv_shows S on S.id = E.show_id
+                 INNER JOIN quality_profiles P on P.id = S.quality_profile_id
+                 LEFT OUTER JOIN tv_show_episode_files F on F.episode_id = E.id ",
+                JOIN_LATEST_GRAB,
+                WHERE_MONITORED_AIRED,
+                filter,
+            ].join(""),
+            vec![],
+        ))
+        .map_err(WebError::from)
+        .and_then(|rows| rows.map(|rows| rows.get(0).get::<_, i64>("total")));
+
+    let order = sort_key.order_by(paging.sort_dir) + " LIMIT $1 OFFSET $2";
+    let records = query_wanted_episodes(
+        [
+            SELECT_WANTED_EPISODE,
+            JOIN_LATEST_GRAB,
+            WHERE_MONITORED_AIRED,
+            filter,
+            &order,
+        ].join(""),
+        db_params![paging.page_size, paging.offset()],
+        pool,
+    );
+
+    Box::new(count.join(records).map(move |(total, records)| Page {
+        page: paging.page,
+        page_size: paging.page_size,
+        total,
+        records,
+    }))
+}
+
+pub fn get_missing_episodes(
+    paging: Paging,
+    sort_key: WantedSortKey,
+    pool: Addr<Syn, DBExecutor>,
+) -> Box<Future<Item = Page<WantedEpisode>, Error = WebError>> {
+    get_wanted_page(WHERE_MISSING, paging, sort_key, pool)
+}
+
+pub fn get_cutoff_unmet_episodes(
+    paging: Paging,
+    sort_key: WantedSortKey,
+    pool: Addr<Syn, DBExecutor>,
+) -> Box<Future<Item = Page<WantedEpisode>, Error = WebError>> {
+    get_wanted_page(WHERE_BELOW_CUTOFF, paging, sort_key, pool)
+}
@@ -1,17 +1,32 @@
 use std::fmt;
+use std::io;
 
 use actix::MailboxError;
 use actix_web::{HttpResponse, ResponseError};
 use postgres;
 use r2d2;
+use serde_json;
+use resources::tv_show::{ExternalId, TVMetadataProvider};
+use thetvdb;
 use tvmaze;
 
 #[derive(Debug)]
 pub enum WebError {
     ActixMailboxError(MailboxError),
+    ArtworkError(String),
     DBError(postgres::Error),
     DBPoolError(r2d2::Error),
+    DownloadError(String),
+    ExternalIdNotFound(ExternalId),
+    IndexerError(String),
+    InvalidRequest(String),
+    IOError(io::Error),
+    JSONError(serde_json::Error),
+    NoDownloadProvider,
+    NoMetadataProvider(TVMetadataProvider),
     ResourceNotFound(i32),
+    ShowExists(i32),
+    TheTVDBError(thetvdb::error::Error),
     TVMazeError(tvmaze::error::Error),
 }
 
@@ -21,12 +36,30 @@ impl From<MailboxError> for WebError {
     }
 }
 
+impl From<io::Error> for WebError {
+    fn from(err: io::Error) -> WebError {
+        WebError::IOError(err)
+    }
+}
+
+impl From<serde_json::Error> for WebError {
+    fn from(err: serde_json::Error) -> WebError {
+        WebError::JSONError(err)
+    }
+}
+
 impl From<tvmaze::error::Error> for WebError {
     fn from(err: tvmaze::error::Error) -> WebError {
         WebError::TVMazeError(err)
     }
 }
 
+impl From<thetvdb::error::Error> for WebError {
+    fn from(err: thetvdb::error::Error) -> WebError {
+        WebError::TheTVDBError(err)
+    }
+}
+
 impl From<postgres::Error> for WebError {
     fn from(err: postgres::Error) -> WebError {
         WebError::DBError(err)
@@ -63,6 +96,27 @@ impl ResponseError for WebError {
             ResourceNotFound(id) => {
                 HttpResponse::NotFound().body(format!("Resource not found with id {}", id))
             }
+            ExternalIdNotFound(ref id) => {
+                HttpResponse::NotFound().body(format!("No show found with {:?}", id))
+            }
+            InvalidRequest(ref reason) => HttpResponse::BadRequest().body(reason.clone()),
+            ShowExists(id) => {
+                HttpResponse::Conflict().body(format!("The show is already added with id {}", id))
+            }
+            NoDownloadProvider => {
+                HttpResponse::ServiceUnavailable().body("No download provider is configured")
+            }
+            NoMetadataProvider(kind) => HttpResponse::ServiceUnavailable()
+                .body(format!("No {:?} metadata provider is configured", kind)),
+            TVMazeError(tvmaze::error::Error::NotFound(_)) => {
+                HttpResponse::NotFound().body("Not found on TVmaze")
+            }
+            TVMazeError(tvmaze::error::Error::RateLimited { .. }) => {
+                HttpResponse::ServiceUnavailable().body("TVmaze is rate limiting requests")
+            }
+            TVMazeError(tvmaze::error::Error::Timeout) => {
+                HttpResponse::GatewayTimeout().body("TVmaze didn't respond in time")
+            }
             _ => HttpResponse::InternalServerError().body(format!("Internal Error: {:?}", self)),
         }
     }
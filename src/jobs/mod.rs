@@ -0,0 +1,302 @@
+use std::rc::Rc;
+use std::time::Duration;
+
+use config::Config;
+use db::episode::get_recently_aired_episodes;
+use db::DBExecutor;
+use error::WebError;
+use library::catalog::sync_catalog;
+use library::import::import_download;
+use library::search::{grab_episodes, Searcher};
+use library::show::refresh_updated_shows;
+use providers::download::DownloadProvider;
+use providers::metadata::{MetadataProviders, UpdatePeriod};
+use resources::tv_show::TVMetadataProvider;
+
+use actix::prelude::*;
+use futures::{future, Future};
+
+/// How often, in seconds, recently aired episodes are searched for.
+const EPISODE_SEARCH_INTERVAL_SECS: u64 = 15 * 60;
+
+/// How often, in seconds, the download provider is checked for finished
+/// downloads to import.
+const IMPORT_INTERVAL_SECS: u64 = 60;
+
+/// How far back an episode's airdate may be for it to count as recently aired.
+const RECENTLY_AIRED_DAYS: i32 = 14;
+
+/// How often, in seconds, providers are asked which shows changed. Must stay
+/// well below a day, the period that is asked about after the first sync.
+const SHOW_UPDATE_INTERVAL_SECS: u64 = 60 * 60;
+
+/// How often, in seconds, the catalog is synced with its providers. Must stay
+/// well below a day for the same reason.
+const CATALOG_SYNC_INTERVAL_SECS: u64 = 60 * 60;
+
+/// Periodically searches for monitored episodes that aired recently and
+/// grabs the best release for each.
+pub struct EpisodeSearchJob {
+    searcher: Rc<Searcher>,
+    db_pool: Addr<Syn, DBExecutor>,
+}
+
+impl EpisodeSearchJob {
+    pub fn new(searcher: Rc<Searcher>, db_pool: Addr<Syn, DBExecutor>) -> EpisodeSearchJob {
+        EpisodeSearchJob { searcher, db_pool }
+    }
+
+    fn run(&mut self, ctx: &mut Context<Self>) {
+        let searcher = self.searcher.clone();
+        let pool = self.db_pool.clone();
+        let search = get_recently_aired_episodes(RECENTLY_AIRED_DAYS, self.db_pool.clone())
+            .and_then(move |episodes| grab_episodes(searcher, episodes, pool))
+            .map(|grabs| {
+                if !grabs.is_empty() {
+                    println!("Grabbed {} recently aired episode(s).", grabs.len());
+                }
+            })
+            .map_err(|e| eprintln!("Recently aired episode search failed: {:?}", e));
+
+        ctx.spawn(search.into_actor(self));
+    }
+}
+
+impl Actor for EpisodeSearchJob {
+    type Context = Context<Self>;
+
+    fn started(&mut self, ctx: &mut Self::Context) {
+        self.run(ctx);
+        ctx.run_interval(
+            Duration::from_secs(EPISODE_SEARCH_INTERVAL_SECS),
+            |job, ctx| job.run(ctx),
+        );
+    }
+}
+
+/// Periodically imports downloads that the download provider has finished.
+pub struct ImportJob {
+    downloader: Rc<DownloadProvider>,
+    config: Rc<Config>,
+    db_pool: Addr<Syn, DBExecutor>,
+}
+
+impl ImportJob {
+    pub fn new(
+        downloader: Rc<DownloadProvider>,
+        config: Rc<Config>,
+        db_pool: Addr<Syn, DBExecutor>,
+    ) -> ImportJob {
+        ImportJob {
+            downloader,
+            config,
+            db_pool,
+        }
+    }
+
+    fn run(&mut self, ctx: &mut Context<Self>) {
+        let config = self.config.clone();
+        let pool = self.db_pool.clone();
+        let import = self
+            .downloader
+            .completed()
+            .and_then(move |downloads| {
+                let mut fut: Box<Future<Item = usize, Error = WebError>> =
+                    Box::new(future::ok(0));
+
+                for download in downloads {
+                    let config = config.clone();
+                    let pool = pool.clone();
+
+                    fut = Box::new(fut.and_then(move |imported| {
+                        let name = download.name.clone();
+                        import_download(download, config, pool).then(move |files| match files {
+                            Ok(files) => {
+                                for rejection in files.rejections {
+                                    eprintln!("Skipped {:?} while importing {}", rejection, name);
+                                }
+                                Ok(imported + files.files.len())
+                            }
+                            Err(e) => {
+                                eprintln!("Importing {} failed: {:?}", name, e);
+                                Ok(imported)
+                            }
+                        })
+                    }));
+                }
+
+                fut
+            })
+            .map(|imported| {
+                if imported > 0 {
+                    println!("Imported {} episode file(s).", imported);
+                }
+            })
+            .map_err(|e| eprintln!("Checking for finished downloads failed: {:?}", e));
+
+        ctx.spawn(import.into_actor(self));
+    }
+}
+
+impl Actor for ImportJob {
+    type Context = Context<Self>;
+
+    fn started(&mut self, ctx: &mut Self::Context) {
+        self.run(ctx);
+        ctx.run_interval(Duration::from_secs(IMPORT_INTERVAL_SECS), |job, ctx| {
+            job.run(ctx)
+        });
+    }
+}
+
+/// Periodically refreshes the library's shows that changed at their provider
+/// since they were last loaded, instead of reloading every show.
+///
+/// Syncs look at the changes of the past week to catch up on anything missed
+/// while Unify wasn't running, and at the past day once one succeeded.
+pub struct ShowUpdateJob {
+    providers: MetadataProviders,
+    config: Rc<Config>,
+    db_pool: Addr<Syn, DBExecutor>,
+    synced: bool,
+}
+
+impl ShowUpdateJob {
+    pub fn new(
+        providers: MetadataProviders,
+        config: Rc<Config>,
+        db_pool: Addr<Syn, DBExecutor>,
+    ) -> ShowUpdateJob {
+        ShowUpdateJob {
+            providers,
+            config,
+            db_pool,
+            synced: false,
+        }
+    }
+
+    fn run(&mut self, ctx: &mut Context<Self>) {
+        let since = if self.synced {
+            UpdatePeriod::Day
+        } else {
+            UpdatePeriod::Week
+        };
+
+        // Resolves to the number of refreshed shows and whether every provider
+        // was checked, as the past week is looked at again until they were.
+        let mut sync: Box<Future<Item = (usize, bool), Error = ()>> =
+            Box::new(future::ok((0, true)));
+        for kind in self.providers.kinds() {
+            let provider = match self.providers.get(kind) {
+                Ok(provider) => provider,
+                Err(_) => continue,
+            };
+            let config = self.config.clone();
+            let pool = self.db_pool.clone();
+
+            sync = Box::new(sync.and_then(move |(refreshed, synced)| {
+                refresh_updated_shows(kind, provider, since, config, pool)
+                    .map(move |count| (refreshed + count, synced))
+                    .or_else(move |e| {
+                        eprintln!("Checking {:?} for updated shows failed: {:?}", kind, e);
+                        Ok((refreshed, false))
+                    })
+            }));
+        }
+
+        let sync = sync.into_actor(self).map(|(refreshed, synced), job, _| {
+            if refreshed > 0 {
+                println!("Refreshed {} updated show(s).", refreshed);
+            }
+            if synced {
+                job.synced = true;
+            }
+        });
+
+        ctx.spawn(sync);
+    }
+}
+
+impl Actor for ShowUpdateJob {
+    type Context = Context<Self>;
+
+    fn started(&mut self, ctx: &mut Self::Context) {
+        self.run(ctx);
+        ctx.run_interval(Duration::from_secs(SHOW_UPDATE_INTERVAL_SECS), |job, ctx| {
+            job.run(ctx)
+        });
+    }
+}
+
+/// Periodically syncs the local catalog with the shows that `kinds` list, so
+/// that searches don't have to ask the providers. See `library::catalog`.
+pub struct CatalogSyncJob {
+    providers: MetadataProviders,
+    kinds: Vec<TVMetadataProvider>,
+    db_pool: Addr<Syn, DBExecutor>,
+    /// Whether a sync is still going, which walking a whole index can be when
+    /// the next one is due.
+    running: bool,
+}
+
+impl CatalogSyncJob {
+    pub fn new(
+        providers: MetadataProviders,
+        kinds: Vec<TVMetadataProvider>,
+        db_pool: Addr<Syn, DBExecutor>,
+    ) -> CatalogSyncJob {
+        CatalogSyncJob {
+            providers,
+            kinds,
+            db_pool,
+            running: false,
+        }
+    }
+
+    fn run(&mut self, ctx: &mut Context<Self>) {
+        if self.running {
+            return;
+        }
+        self.running = true;
+
+        let mut sync: Box<Future<Item = (), Error = ()>> = Box::new(future::ok(()));
+        for &kind in &self.kinds {
+            let provider = match self.providers.get(kind) {
+                Ok(provider) => provider,
+                Err(e) => {
+                    eprintln!("Unable to sync the {:?} catalog: {:?}", kind, e);
+                    continue;
+                }
+            };
+            let pool = self.db_pool.clone();
+
+            sync = Box::new(sync.and_then(move |_| {
+                sync_catalog(kind, provider, pool).then(move |res| -> Result<(), ()> {
+                    match res {
+                        Ok(ref synced) if synced.shows > 0 || synced.aliases > 0 => println!(
+                            "Synced {} show(s) and the aliases of {} show(s) in the {:?} catalog.",
+                            synced.shows, synced.aliases, kind
+                        ),
+                        Ok(_) => {}
+                        Err(e) => eprintln!("Syncing the {:?} catalog failed: {:?}", kind, e),
+                    }
+                    Ok(())
+                })
+            }));
+        }
+
+        // Failed syncs are only logged, so the sync always finishes.
+        ctx.spawn(sync.into_actor(self).map(|_, job, _| job.running = false));
+    }
+}
+
+impl Actor for CatalogSyncJob {
+    type Context = Context<Self>;
+
+    fn started(&mut self, ctx: &mut Self::Context) {
+        self.run(ctx);
+        ctx.run_interval(Duration::from_secs(CATALOG_SYNC_INTERVAL_SECS), |job, ctx| {
+            job.run(ctx)
+        });
+    }
+}
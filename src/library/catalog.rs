@@ -0,0 +1,421 @@
+use std::collections::{BTreeSet, HashMap};
+use std::rc::Rc;
+
+use db::catalog::*;
+use db::tv_show::get_show_ids_by_provider_ids;
+use db::DBExecutor;
+use error::WebError;
+use providers::metadata::{MetadataProvider, UpdatePeriod};
+use resources::tv_show::*;
+
+use actix::prelude::*;
+use chrono::{DateTime, Duration, Utc};
+use futures::{future, Future, Stream};
+
+/// How many shows a search finds unless asked for another number.
+pub const DEFAULT_SEARCH_LIMIT: usize = 20;
+
+/// How many shows a search can be asked to find at most.
+pub const MAX_SEARCH_LIMIT: usize = 100;
+
+/// How long after its last sync the catalog still answers searches. Syncs run
+/// every hour, so it only goes stale when they keep failing.
+const MAX_CATALOG_AGE_HOURS: i64 = 48;
+
+/// How often the whole index is walked again, to catch changes that the
+/// updates feed missed while Unify wasn't running.
+const WALK_INTERVAL_DAYS: i64 = 7;
+
+/// How many shows have their aliases loaded per sync. Every show takes a
+/// request, which shares the provider's rate limit with everything else. At
+/// TVmaze's 20 requests every 10 seconds, that's about 8 minutes of every
+/// hour, and its 60,000 or so shows have their aliases within 3 days.
+const ALIASES_PER_SYNC: i64 = 1000;
+
+/// What a sync of the catalog changed.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct CatalogSync {
+    /// Shows that were added or updated from the provider's index.
+    pub shows: usize,
+    /// Shows whose aliases were loaded.
+    pub aliases: usize,
+}
+
+#[derive(Debug, PartialEq)]
+enum SyncPlan {
+    /// Walks the whole index from its first page.
+    Walk,
+    /// Reloads the index pages of the shows that changed within the period,
+    /// and walks on from the last known page to find new shows.
+    Update(UpdatePeriod),
+}
+
+/// Searches the catalog of `kind` if it is `mirrored` and was synced recently,
+/// and asks `provider` otherwise. Finds up to `limit` shows, with the ones
+/// that are in the library already marked as such.
+pub fn search_shows(
+    kind: TVMetadataProvider,
+    provider: Rc<MetadataProvider>,
+    q: &str,
+    limit: usize,
+    mirrored: bool,
+    pool: Addr<Syn, DBExecutor>,
+) -> Box<Future<Item = Vec<ShowSearchResult>, Error = WebError>> {
+    let library_pool = pool.clone();
+    let results: Box<Future<Item = Vec<ShowSearchResult>, Error = WebError>> = if mirrored {
+        let q = q.to_string();
+        Box::new(
+            get_catalog_sync(kind, pool.clone()).and_then(move |(_, synced_at)| {
+                if is_fresh(synced_at, Utc::now()) {
+                    search_catalog(kind, &q, limit as i64, pool)
+                } else {
+                    provider.search(&q)
+                }
+            }),
+        )
+    } else {
+        provider.search(q)
+    };
+
+    Box::new(results.and_then(move |mut results| {
+        // Providers don't take a limit, so their results are cut here.
+        results.truncate(limit);
+        mark_library_shows(kind, results, library_pool)
+    }))
+}
+
+/// How many shows a search asked to find `limit` shows finds.
+pub fn search_limit(limit: Option<usize>) -> usize {
+    limit.unwrap_or(DEFAULT_SEARCH_LIMIT).max(1).min(MAX_SEARCH_LIMIT)
+}
+
+/// Sets the library ids of the results whose show is in the library.
+fn mark_library_shows(
+    kind: TVMetadataProvider,
+    results: Vec<ShowSearchResult>,
+    pool: Addr<Syn, DBExecutor>,
+) -> Box<Future<Item = Vec<ShowSearchResult>, Error = WebError>> {
+    let ids = results.iter().map(|r| r.show.provider_id.clone()).collect();
+    Box::new(
+        get_show_ids_by_provider_ids(kind, ids, pool).map(move |library_ids| {
+            let mut results = results;
+            set_library_ids(&mut results, &library_ids);
+            results
+        }),
+    )
+}
+
+/// Sets the library ids of the results by the provider ids of their shows, and
+/// clears them for the results whose show isn't in `library_ids`.
+fn set_library_ids(results: &mut [ShowSearchResult], library_ids: &HashMap<String, i32>) {
+    for result in results.iter_mut() {
+        let library_id = library_ids.get(&result.show.provider_id).cloned();
+        result.set_library_id(library_id);
+    }
+}
+
+/// Brings the catalog of `kind` up to date with `provider`'s index, then loads
+/// the aliases of some of the shows that don't have theirs yet.
+pub fn sync_catalog(
+    kind: TVMetadataProvider,
+    provider: Rc<MetadataProvider>,
+    pool: Addr<Syn, DBExecutor>,
+) -> Box<Future<Item = CatalogSync, Error = WebError>> {
+    Box::new(
+        get_catalog_sync(kind, pool.clone()).and_then(move |(walked_at, synced_at)| {
+            let plan = plan_sync(walked_at, synced_at, Utc::now());
+            let walked = plan == SyncPlan::Walk;
+            let shows = match plan {
+                SyncPlan::Walk => walk_index(provider.clone(), 0, pool.clone()),
+                SyncPlan::Update(since) => {
+                    update_catalog(kind, provider.clone(), since, pool.clone())
+                }
+            };
+
+            shows.and_then(move |shows| -> Box<Future<Item = CatalogSync, Error = WebError>> {
+                // A provider that lists no shows at all can't answer searches.
+                if walked && shows == 0 {
+                    return Box::new(future::ok(CatalogSync::default()));
+                }
+
+                let aliases_pool = pool.clone();
+                Box::new(
+                    set_catalog_synced(kind, walked, pool)
+                        .and_then(move |_| load_aliases(kind, provider, aliases_pool))
+                        .map(move |aliases| CatalogSync { shows, aliases }),
+                )
+            })
+        }),
+    )
+}
+
+/// Stores every page of the index from `first_page` on, and resolves to how
+/// many shows were stored.
+fn walk_index(
+    provider: Rc<MetadataProvider>,
+    first_page: u64,
+    pool: Addr<Syn, DBExecutor>,
+) -> Box<Future<Item = usize, Error = WebError>> {
+    Box::new(
+        provider
+            .get_show_index(first_page)
+            .fold(0, move |stored, (page, shows)| {
+                upsert_catalog_shows(page, shows, pool.clone()).map(move |count| stored + count)
+            }),
+    )
+}
+
+fn update_catalog(
+    kind: TVMetadataProvider,
+    provider: Rc<MetadataProvider>,
+    since: UpdatePeriod,
+    pool: Addr<Syn, DBExecutor>,
+) -> Box<Future<Item = usize, Error = WebError>> {
+    let updates_pool = pool.clone();
+    let load_changed = provider.get_show_updates(since).and_then(move |updates| {
+        let ids = updates.keys().cloned().collect();
+        get_catalog_updates(kind, ids, updates_pool)
+            .map(move |stored| changed_pages(&updates, &stored))
+    });
+    let load_pages = load_changed.join(get_last_catalog_page(kind, pool.clone()));
+
+    Box::new(load_pages.and_then(move |(pages, last_page)| {
+        let last_page = last_page.map_or(0, |page| page as u64);
+        let mut fut: Box<Future<Item = usize, Error = WebError>> = Box::new(future::ok(0));
+
+        // The last page and the ones after it are walked below.
+        for page in pages.into_iter().filter(|&page| page < last_page) {
+            let provider = provider.clone();
+            let pool = pool.clone();
+
+            fut = Box::new(fut.and_then(move |stored| {
+                provider
+                    .get_show_index_page(page)
+                    .and_then(move |shows| -> Box<Future<Item = usize, Error = WebError>> {
+                        match shows {
+                            Some(shows) => upsert_catalog_shows(page, shows, pool),
+                            None => Box::new(future::ok(0)),
+                        }
+                    })
+                    .map(move |count| stored + count)
+            }));
+        }
+
+        // New shows are added at the end of the index.
+        fut.and_then(move |stored| {
+            walk_index(provider, last_page, pool).map(move |count| stored + count)
+        })
+    }))
+}
+
+/// Loads the aliases of up to `ALIASES_PER_SYNC` shows one at a time, and
+/// resolves to how many were loaded. Shows that fail to load are logged and
+/// skipped until they change.
+fn load_aliases(
+    kind: TVMetadataProvider,
+    provider: Rc<MetadataProvider>,
+    pool: Addr<Syn, DBExecutor>,
+) -> Box<Future<Item = usize, Error = WebError>> {
+    Box::new(
+        get_catalog_shows_without_aliases(kind, ALIASES_PER_SYNC, pool.clone()).and_then(
+            move |shows| {
+                let mut fut: Box<Future<Item = usize, Error = WebError>> =
+                    Box::new(future::ok(0));
+
+                for (id, provider_id) in shows {
+                    let provider = provider.clone();
+                    let pool = pool.clone();
+
+                    fut = Box::new(fut.and_then(move |loaded| {
+                        let skip_pool = pool.clone();
+                        let load = provider.get_show_aliases(&provider_id);
+                        load.and_then(move |aliases| replace_catalog_aliases(id, aliases, pool))
+                            .then(move |res| -> Box<Future<Item = usize, Error = WebError>> {
+                                match res {
+                                    Ok(_) => Box::new(future::ok(loaded + 1)),
+                                    Err(e) => {
+                                        eprintln!(
+                                            "Unable to load the aliases of {:?} show {}: {:?}",
+                                            kind, provider_id, e
+                                        );
+                                        Box::new(
+                                            set_catalog_aliases_loaded(id, skip_pool)
+                                                .map(move |_| loaded),
+                                        )
+                                    }
+                                }
+                            })
+                    }));
+                }
+
+                fut
+            },
+        ),
+    )
+}
+
+/// Walks the whole index when the catalog is new or wasn't walked for a while,
+/// and follows the updates since the last sync otherwise.
+fn plan_sync(
+    walked_at: Option<DateTime<Utc>>,
+    synced_at: Option<DateTime<Utc>>,
+    now: DateTime<Utc>,
+) -> SyncPlan {
+    match (walked_at, synced_at) {
+        (Some(walked_at), Some(synced_at))
+            if now.signed_duration_since(walked_at) < Duration::days(WALK_INTERVAL_DAYS) =>
+        {
+            if now.signed_duration_since(synced_at) < Duration::hours(23) {
+                SyncPlan::Update(UpdatePeriod::Day)
+            } else {
+                SyncPlan::Update(UpdatePeriod::Week)
+            }
+        }
+        _ => SyncPlan::Walk,
+    }
+}
+
+fn is_fresh(synced_at: Option<DateTime<Utc>>, now: DateTime<Utc>) -> bool {
+    synced_at.map_or(false, |synced_at| {
+        now.signed_duration_since(synced_at) < Duration::hours(MAX_CATALOG_AGE_HOURS)
+    })
+}
+
+/// The index pages of the stored shows whose last update at their provider is
+/// newer than the one stored with them, in order.
+fn changed_pages(
+    updates: &HashMap<String, i64>,
+    stored: &HashMap<String, (i64, Option<i64>)>,
+) -> Vec<u64> {
+    let pages: BTreeSet<u64> = stored
+        .iter()
+        .filter_map(|(provider_id, &(page, updated))| match updates.get(provider_id) {
+            Some(&latest) if updated.map_or(true, |u| latest > u) => Some(page as u64),
+            _ => None,
+        })
+        .collect();
+    pages.into_iter().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use resources::quality::DEFAULT_QUALITY_PROFILE_ID;
+
+    use chrono::{NaiveDate, TimeZone};
+    use serde_json;
+
+    fn result(provider_id: &str) -> ShowSearchResult {
+        let show = TVShow {
+            id: -1,
+            name: "Breaking Bad".to_string(),
+            summary: None,
+            cover_img: None,
+            fanart_img: None,
+            genres: vec![],
+            runtime: None,
+            premiered: NaiveDate::from_ymd_opt(2008, 1, 20),
+            external_ids: ExternalIds {
+                imdb: None,
+                thetvdb: None,
+                tvrage: None,
+            },
+            provider: TVMetadataProvider::TVMaze,
+            provider_id: provider_id.to_string(),
+            provider_url: String::new(),
+            provider_updated: None,
+            monitored: true,
+            quality_profile_id: DEFAULT_QUALITY_PROFILE_ID,
+            root_folder_id: None,
+            path: None,
+            aliases: vec![],
+            metadata_sources: MetadataSources::new(),
+            seasons: None,
+        };
+        ShowSearchResult::new(show, Some(0.5), Some("AMC".to_string()), None)
+    }
+
+    #[test]
+    fn walks_new_and_outdated_catalogs() {
+        let now = Utc.ymd(2018, 6, 30).and_hms(12, 0, 0);
+        let hours_ago = |hours| Some(now - Duration::hours(hours));
+
+        assert_eq!(plan_sync(None, None, now), SyncPlan::Walk);
+        assert_eq!(plan_sync(hours_ago(8 * 24), hours_ago(1), now), SyncPlan::Walk);
+        assert_eq!(
+            plan_sync(hours_ago(30), hours_ago(1), now),
+            SyncPlan::Update(UpdatePeriod::Day)
+        );
+        assert_eq!(
+            plan_sync(hours_ago(30), hours_ago(30), now),
+            SyncPlan::Update(UpdatePeriod::Week)
+        );
+    }
+
+    #[test]
+    fn only_answers_searches_from_recently_synced_catalogs() {
+        let now = Utc.ymd(2018, 6, 30).and_hms(12, 0, 0);
+
+        assert!(!is_fresh(None, now));
+        assert!(is_fresh(Some(now - Duration::hours(2)), now));
+        assert!(!is_fresh(Some(now - Duration::days(3)), now));
+    }
+
+    #[test]
+    fn keeps_search_limits_within_bounds() {
+        assert_eq!(search_limit(None), DEFAULT_SEARCH_LIMIT);
+        assert_eq!(search_limit(Some(5)), 5);
+        assert_eq!(search_limit(Some(0)), 1);
+        assert_eq!(search_limit(Some(1000)), MAX_SEARCH_LIMIT);
+    }
+
+    #[test]
+    fn reloads_the_pages_of_changed_shows() {
+        let updates: HashMap<String, i64> = vec![
+            ("1".to_string(), 1530000000),
+            ("2".to_string(), 1530000000),
+            ("260".to_string(), 1530000000),
+            ("600".to_string(), 1530000000),
+        ].into_iter()
+            .collect();
+        let stored: HashMap<String, (i64, Option<i64>)> = vec![
+            ("1".to_string(), (0, Some(1520000000))),
+            ("2".to_string(), (0, None)),
+            ("260".to_string(), (1, Some(1530000000))),
+            ("600".to_string(), (2, Some(1520000000))),
+        ].into_iter()
+            .collect();
+
+        assert_eq!(changed_pages(&updates, &stored), vec![0, 2]);
+    }
+
+    #[test]
+    fn marks_the_results_that_are_in_the_library() {
+        let mut results = vec![result("169"), result("170")];
+        results[1].set_library_id(Some(3));
+        let library_ids: HashMap<String, i32> =
+            vec![("169".to_string(), 12)].into_iter().collect();
+
+        set_library_ids(&mut results, &library_ids);
+        assert_eq!(results[0].library_id, Some(12));
+        assert!(results[0].in_library);
+        assert_eq!(results[1].library_id, None);
+        assert!(!results[1].in_library);
+    }
+
+    #[test]
+    fn serializes_results_without_the_placeholder_id() {
+        let mut result = result("169");
+        result.set_library_id(Some(12));
+
+        let value = serde_json::to_value(&result).unwrap();
+        assert!(value.get("id").is_none());
+        assert_eq!(value["name"], "Breaking Bad");
+        assert_eq!(value["provider_id"], "169");
+        assert_eq!(value["year"], 2008);
+        assert_eq!(value["network"], "AMC");
+        assert_eq!(value["library_id"], 12);
+        assert_eq!(value["in_library"], true);
+    }
+}
@@ -0,0 +1,601 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use config::{Config, ImportMode};
+use db::episode::{get_pending_import, mark_grab_imported};
+use db::episode_file::upsert_episode_file;
+use db::tv_show::get_entire_show;
+use db::DBExecutor;
+use error::WebError;
+use library::metadata::try_update_show_metadata;
+use media::{self, MediaInfo};
+use naming::{episode_file_name, season_folder_name};
+use providers::download::CompletedDownload;
+use release::{self, title_matches, ParsedRelease, VIDEO_EXTENSIONS};
+use resources::episode_file::EpisodeFile;
+use resources::quality::Quality;
+use resources::tv_show::{TVShow, TVShowEpisode};
+
+use actix::prelude::*;
+use chrono::Utc;
+use futures::{future, Future};
+
+/// Folders whose contents are never imported.
+const IGNORED_FOLDERS: &'static [&'static str] =
+    &["extras", "featurettes", "proof", "sample", "samples", "subs"];
+
+/// How far a file's duration may be from its episodes' runtime, as a fraction
+/// of the runtime, before it is assumed to be a fake or the wrong episode.
+const MAX_RUNTIME_DEVIATION: f64 = 0.5;
+
+/// A video file that will be placed into the library for one or more
+/// episodes.
+#[derive(Debug)]
+pub struct ImportDecision {
+    pub source: PathBuf,
+    pub destination: PathBuf,
+    pub episodes: Vec<TVShowEpisode>,
+    pub quality: Quality,
+    pub size: u64,
+    pub media_info: Option<MediaInfo>,
+}
+
+/// Why a video file wasn't imported.
+#[derive(Debug)]
+pub enum Rejection {
+    Unparseable(PathBuf),
+    WrongShow(PathBuf, String),
+    UnknownEpisode(PathBuf, i32, Vec<i32>),
+    /// The file's duration and the expected runtime, in seconds.
+    WrongDuration(PathBuf, u64, u64),
+}
+
+/// The files an import recorded on their episodes, and the ones it skipped.
+#[derive(Debug, Default)]
+pub struct ImportedFiles {
+    pub files: Vec<EpisodeFile>,
+    pub rejections: Vec<Rejection>,
+}
+
+/// Returns the video files at `path`, which may be a single file or a folder
+/// that is searched recursively. Samples and extras are skipped.
+pub fn find_video_files(path: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+
+    if path.is_file() {
+        if is_video(path) && !is_sample(path) {
+            files.push(path.to_path_buf());
+        }
+        return Ok(files);
+    }
+
+    for entry in fs::read_dir(path)? {
+        let entry_path = entry?.path();
+
+        if entry_path.is_dir() {
+            if !is_ignored_folder(&entry_path) {
+                files.extend(find_video_files(&entry_path)?);
+            }
+        } else if is_video(&entry_path) && !is_sample(&entry_path) {
+            files.push(entry_path);
+        }
+    }
+
+    files.sort();
+    Ok(files)
+}
+
+pub fn is_video(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .map_or(false, |ext| VIDEO_EXTENSIONS.contains(&ext.to_lowercase().as_str()))
+}
+
+fn is_sample(path: &Path) -> bool {
+    path.file_stem()
+        .and_then(|stem| stem.to_str())
+        .map_or(false, |stem| {
+            stem.to_lowercase()
+                .split(|c: char| !c.is_alphanumeric())
+                .any(|token| token == "sample")
+        })
+}
+
+fn is_ignored_folder(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .map_or(false, |name| IGNORED_FOLDERS.contains(&name.to_lowercase().as_str()))
+}
+
+/// Parses a video file's name, falling back to the name of its folder since
+/// files inside a release folder are often obfuscated (e.g. `abc123.mkv`).
+pub fn parse_video_file(path: &Path) -> Option<ParsedRelease> {
+    let from_folder = path
+        .parent()
+        .and_then(|p| p.file_name())
+        .and_then(|name| name.to_str())
+        .and_then(release::parse);
+    let from_file = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .and_then(release::parse);
+
+    match (from_file, from_folder) {
+        (Some(mut file), Some(folder)) => {
+            if file.quality == Quality::Unknown {
+                file.quality = folder.quality;
+            }
+            Some(file)
+        }
+        (file, folder) => file.or(folder),
+    }
+}
+
+/// Matches each file to episodes of `show` and works out where it should be
+/// placed inside `show_path`.
+pub fn plan_import(
+    files: Vec<PathBuf>,
+    show: &TVShow,
+    show_path: &Path,
+    fallback_quality: Option<Quality>,
+    config: &Config,
+) -> (Vec<ImportDecision>, Vec<Rejection>) {
+    let episodes: Vec<&TVShowEpisode> = show
+        .seasons
+        .iter()
+        .flat_map(|seasons| seasons.iter())
+        .flat_map(|season| season.episodes.iter().flat_map(|e| e.iter()))
+        .collect();
+
+    let mut decisions = Vec::new();
+    let mut rejections = Vec::new();
+
+    for file in files {
+        let parsed = match parse_video_file(&file) {
+            Some(parsed) => parsed,
+            None => {
+                rejections.push(Rejection::Unparseable(file));
+                continue;
+            }
+        };
+
+        if !title_matches(&parsed.title, &show.name, &show.aliases) {
+            rejections.push(Rejection::WrongShow(file, parsed.title));
+            continue;
+        }
+
+        let mut matched: Vec<TVShowEpisode> = episodes
+            .iter()
+            .filter(|e| {
+                e.season_num == parsed.season
+                    && e.num.map_or(false, |n| parsed.episodes.contains(&n))
+            })
+            .map(|e| (*e).clone())
+            .collect();
+
+        if matched.is_empty() || matched.len() != parsed.episodes.len() {
+            rejections.push(Rejection::UnknownEpisode(file, parsed.season, parsed.episodes));
+            continue;
+        }
+        matched.sort_by_key(|e| e.num);
+
+        let media_info = media::probe(&file).ok();
+        let duration = media_info.as_ref().and_then(|info| info.duration);
+        if let (Some(duration), Some(expected)) = (duration, expected_duration(&matched)) {
+            if (duration - expected).abs() > expected * MAX_RUNTIME_DEVIATION {
+                rejections.push(Rejection::WrongDuration(file, duration as u64, expected as u64));
+                continue;
+            }
+        }
+
+        let quality = match (parsed.quality, fallback_quality) {
+            (Quality::Unknown, Some(fallback)) => fallback,
+            (quality, _) => quality,
+        };
+
+        let mut file_name = episode_file_name(&config.episode_file_format, show, &matched[0]);
+        if let Some(ext) = file.extension().and_then(|ext| ext.to_str()) {
+            file_name.push('.');
+            file_name.push_str(&ext.to_lowercase());
+        }
+        let destination = show_path
+            .join(season_folder_name(&config.season_folder_format, parsed.season))
+            .join(file_name);
+        let size = fs::metadata(&file).map(|m| m.len()).unwrap_or(0);
+
+        decisions.push(ImportDecision {
+            source: file,
+            destination,
+            episodes: matched,
+            quality,
+            size,
+            media_info,
+        });
+    }
+
+    (decisions, rejections)
+}
+
+/// The combined runtime of `episodes` in seconds, if all of them have one.
+fn expected_duration(episodes: &[TVShowEpisode]) -> Option<f64> {
+    episodes
+        .iter()
+        .fold(Some(0), |total, e| Some(total? + e.runtime?))
+        .map(|minutes| minutes as f64 * 60.0)
+}
+
+/// Places a file into the library, replacing whatever was at its
+/// destination. The old file is only replaced once the new one is complete,
+/// and a file that is already at its destination is left as it is.
+pub fn execute(decision: &ImportDecision, mode: ImportMode) -> io::Result<()> {
+    let source = &decision.source;
+    let destination = &decision.destination;
+
+    if is_same_file(source, destination) {
+        return Ok(());
+    }
+    if let Some(parent) = destination.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    match mode {
+        ImportMode::Hardlink => replace_with(destination, |tmp| {
+            fs::hard_link(source, tmp).or_else(|_| fs::copy(source, tmp).map(|_| ()))
+        }),
+        ImportMode::Copy => replace_with(destination, |tmp| fs::copy(source, tmp).map(|_| ())),
+        // A rename replaces the destination in one step already.
+        ImportMode::Move => fs::rename(source, destination).or_else(|_| {
+            replace_with(destination, |tmp| fs::copy(source, tmp).map(|_| ()))
+                .and_then(|_| fs::remove_file(source))
+        }),
+    }
+}
+
+/// Writes a file next to `destination` with `write`, then renames it over
+/// `destination`. The written file is removed if either step fails.
+fn replace_with<F>(destination: &Path, write: F) -> io::Result<()>
+where
+    F: FnOnce(&Path) -> io::Result<()>,
+{
+    let name = destination
+        .file_name()
+        .map_or_else(String::new, |name| name.to_string_lossy().into_owned());
+    let tmp = destination.with_file_name(format!(".{}.partial", name));
+
+    // Left behind by an import that was interrupted.
+    if tmp.exists() {
+        fs::remove_file(&tmp)?;
+    }
+
+    let res = write(&tmp).and_then(|_| fs::rename(&tmp, destination));
+    // Renaming a hardlink over another link to the same file leaves both.
+    if tmp.exists() {
+        let _ = fs::remove_file(&tmp);
+    }
+    res
+}
+
+fn is_same_file(a: &Path, b: &Path) -> bool {
+    match (fs::canonicalize(a), fs::canonicalize(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false,
+    }
+}
+
+/// Imports the video files at `path` into `show`'s folder and returns the
+/// file records for the imported episodes, along with the files that were
+/// skipped.
+pub fn import_path(
+    path: &Path,
+    show: &TVShow,
+    fallback_quality: Option<Quality>,
+    config: &Config,
+) -> Result<ImportedFiles, WebError> {
+    let show_path = match show.path {
+        Some(ref show_path) => PathBuf::from(show_path),
+        None => {
+            return Err(WebError::InvalidRequest(format!(
+                "Show {} has no path to import into",
+                show.id
+            )))
+        }
+    };
+
+    let files = find_video_files(path)?;
+    let (decisions, rejections) = plan_import(files, show, &show_path, fallback_quality, config);
+
+    let mut episode_files = Vec::new();
+    for decision in decisions {
+        execute(&decision, config.import_mode)?;
+
+        for episode in decision.episodes.iter() {
+            episode_files.push(EpisodeFile {
+                id: -1,
+                episode_id: episode.id,
+                path: decision.destination.to_string_lossy().into_owned(),
+                size: decision.size as i64,
+                quality: decision.quality,
+                created_at: Utc::now(),
+                media_info: decision.media_info.clone(),
+            });
+        }
+    }
+
+    Ok(ImportedFiles {
+        files: episode_files,
+        rejections,
+    })
+}
+
+/// Imports a finished download if it was grabbed by Unify and records the
+/// imported files on their episodes.
+pub fn import_download(
+    download: CompletedDownload,
+    config: Rc<Config>,
+    pool: Addr<Syn, DBExecutor>,
+) -> Box<Future<Item = ImportedFiles, Error = WebError>> {
+    let pool_clone = pool.clone();
+    let download_id = download.id.clone();
+
+    Box::new(
+        get_pending_import(download.id.clone(), pool.clone()).and_then(move |pending| {
+            let pending = match pending {
+                Some(pending) => pending,
+                None => {
+                    return Box::new(future::ok(ImportedFiles::default()))
+                        as Box<Future<Item = ImportedFiles, Error = WebError>>
+                }
+            };
+
+            let show_id = pending.show_id;
+            let metadata_config = config.clone();
+            let metadata_pool = pool.clone();
+
+            Box::new(
+                get_entire_show(pending.show_id, pool.clone())
+                    .and_then(|show| show)
+                    .and_then(move |show| {
+                        import_path(
+                            Path::new(&download.path),
+                            &show,
+                            Some(pending.quality),
+                            &config,
+                        )
+                    })
+                    .and_then(move |imported| {
+                        let inserts: Vec<_> = imported
+                            .files
+                            .iter()
+                            .map(|f| upsert_episode_file(f, pool_clone.clone()))
+                            .collect();
+                        let rejections = imported.rejections;
+                        future::join_all(inserts).map(move |files| ImportedFiles {
+                            files,
+                            rejections,
+                        })
+                    })
+                    .and_then(move |imported| {
+                        mark_grab_imported(download_id, pool).map(move |_| imported)
+                    })
+                    .and_then(move |imported| {
+                        try_update_show_metadata(show_id, false, metadata_config, metadata_pool)
+                            .map(move |_| imported)
+                    }),
+            )
+        }),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::File;
+    use std::io::Write;
+
+    use super::*;
+    use resources::quality::DEFAULT_QUALITY_PROFILE_ID;
+    use resources::tv_show::{ExternalIds, MetadataSources, TVMetadataProvider, TVShowSeason};
+
+    use tempfile::TempDir;
+
+    fn episode(id: i32, num: i32, name: &str) -> TVShowEpisode {
+        TVShowEpisode {
+            id,
+            show_id: 1,
+            season_id: 1,
+            num: Some(num),
+            season_num: 1,
+            name: name.to_string(),
+            summary: None,
+            cover_img: None,
+            runtime: Some(45),
+            airdate: None,
+            monitored: true,
+            provider_id: id.to_string(),
+            provider_url: String::new(),
+            metadata_sources: MetadataSources::new(),
+        }
+    }
+
+    fn show(path: &Path) -> TVShow {
+        TVShow {
+            id: 1,
+            name: "Show Name".to_string(),
+            summary: None,
+            cover_img: None,
+            fanart_img: None,
+            genres: vec![],
+            runtime: Some(45),
+            premiered: None,
+            external_ids: ExternalIds::default(),
+            provider: TVMetadataProvider::TVMaze,
+            provider_id: "1".to_string(),
+            provider_url: String::new(),
+            provider_updated: None,
+            monitored: true,
+            quality_profile_id: DEFAULT_QUALITY_PROFILE_ID,
+            root_folder_id: None,
+            path: Some(path.to_string_lossy().into_owned()),
+            aliases: vec![],
+            metadata_sources: MetadataSources::new(),
+            seasons: Some(vec![TVShowSeason {
+                id: 1,
+                show_id: 1,
+                num: 1,
+                name: String::new(),
+                summary: None,
+                cover_img: None,
+                provider_id: "1".to_string(),
+                provider_url: String::new(),
+                metadata_sources: MetadataSources::new(),
+                episodes: Some(vec![episode(10, 1, "Pilot"), episode(11, 2, "Second")]),
+            }]),
+        }
+    }
+
+    fn touch(path: &Path, contents: &[u8]) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        File::create(path).unwrap().write_all(contents).unwrap();
+    }
+
+    fn config(import_mode: ImportMode) -> Config {
+        Config {
+            import_mode,
+            ..Config::default()
+        }
+    }
+
+    #[test]
+    fn finds_videos_and_skips_samples() {
+        let dir = TempDir::new().unwrap();
+        let release = dir.path().join("Show.Name.S01E01.720p.HDTV.x264-GRP");
+        touch(&release.join("show.name.s01e01.720p.hdtv.x264-grp.mkv"), b"video");
+        touch(&release.join("show.name.s01e01.sample.mkv"), b"sample");
+        touch(&release.join("Sample").join("clip.mkv"), b"sample");
+        touch(&release.join("show.name.s01e01.nfo"), b"nfo");
+
+        let files = find_video_files(dir.path()).unwrap();
+        assert_eq!(files, vec![release.join("show.name.s01e01.720p.hdtv.x264-grp.mkv")]);
+    }
+
+    #[test]
+    fn imports_into_show_folder() {
+        let downloads = TempDir::new().unwrap();
+        let library = TempDir::new().unwrap();
+        let release = downloads.path().join("Show.Name.S01E02.1080p.WEB-DL-GRP");
+        let source = release.join("a1b2c3.mkv");
+        touch(&source, b"video");
+
+        let show = show(&library.path().join("Show Name"));
+        let files = import_path(&release, &show, None, &config(ImportMode::Copy))
+            .unwrap()
+            .files;
+
+        let expected = library
+            .path()
+            .join("Show Name")
+            .join("Season 01")
+            .join("Show Name - S01E02 - Second.mkv");
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].episode_id, 11);
+        assert_eq!(files[0].quality, Quality::WEBDL1080p);
+        assert_eq!(files[0].size, 5);
+        assert_eq!(files[0].path, expected.to_string_lossy());
+        assert!(expected.exists());
+        assert!(source.exists());
+    }
+
+    #[test]
+    fn move_removes_source_and_rejects_unknown_episodes() {
+        let downloads = TempDir::new().unwrap();
+        let library = TempDir::new().unwrap();
+        let source = downloads.path().join("Show.Name.S01E01.HDTV.mp4");
+        let unknown = downloads.path().join("Show.Name.S02E01.HDTV.mp4");
+        touch(&source, b"video");
+        touch(&unknown, b"video");
+
+        let show = show(&library.path().join("Show Name"));
+        let imported = import_path(
+            downloads.path(),
+            &show,
+            Some(Quality::HDTV720p),
+            &config(ImportMode::Move),
+        ).unwrap();
+
+        let files = imported.files;
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].episode_id, 10);
+        assert_eq!(files[0].quality, Quality::SDTV);
+        assert!(!source.exists());
+        assert!(unknown.exists());
+        assert_eq!(imported.rejections.len(), 1);
+        match imported.rejections[0] {
+            Rejection::UnknownEpisode(ref path, 2, _) => assert_eq!(path, &unknown),
+            ref other => panic!("unexpected rejection: {:?}", other),
+        }
+    }
+
+    fn decision(source: PathBuf, destination: PathBuf) -> ImportDecision {
+        ImportDecision {
+            source,
+            destination,
+            episodes: vec![],
+            quality: Quality::HDTV720p,
+            size: 0,
+            media_info: None,
+        }
+    }
+
+    #[test]
+    fn keeps_the_old_file_until_the_new_one_is_in_place() {
+        let dir = TempDir::new().unwrap();
+        let destination = dir.path().join("Season 01").join("episode.mkv");
+        touch(&destination, b"old");
+
+        let missing = decision(dir.path().join("missing.mkv"), destination.clone());
+        assert!(execute(&missing, ImportMode::Copy).is_err());
+        assert_eq!(fs::read(&destination).unwrap(), b"old");
+
+        let source = dir.path().join("new.mkv");
+        for &mode in &[ImportMode::Hardlink, ImportMode::Copy, ImportMode::Move] {
+            touch(&source, b"new");
+            execute(&decision(source.clone(), destination.clone()), mode).unwrap();
+            assert_eq!(fs::read(&destination).unwrap(), b"new");
+        }
+        assert_eq!(fs::read_dir(destination.parent().unwrap()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn leaves_files_that_are_already_in_place() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("episode.mkv");
+        touch(&path, b"video");
+
+        for &mode in &[ImportMode::Hardlink, ImportMode::Copy, ImportMode::Move] {
+            execute(&decision(path.clone(), path.clone()), mode).unwrap();
+            assert_eq!(fs::read(&path).unwrap(), b"video");
+        }
+    }
+
+    #[test]
+    fn rejects_files_with_wrong_duration() {
+        let downloads = TempDir::new().unwrap();
+        let library = TempDir::new().unwrap();
+        let episode = downloads.path().join("Show.Name.S01E01.720p.HDTV.mkv");
+        let fake = downloads.path().join("Show.Name.S01E02.720p.HDTV.mkv");
+        touch(&episode, &media::sample_mkv(43.0 * 60.0));
+        touch(&fake, &media::sample_mkv(90.0));
+
+        let show = show(&library.path().join("Show Name"));
+        let files = import_path(downloads.path(), &show, None, &config(ImportMode::Copy))
+            .unwrap()
+            .files;
+
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].episode_id, 10);
+        let info = files[0].media_info.as_ref().unwrap();
+        assert_eq!(info.height, Some(1080));
+        assert_eq!(info.audio_languages(), vec!["eng", "jpn"]);
+    }
+}
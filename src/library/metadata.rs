@@ -0,0 +1,387 @@
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use config::{Config, MetadataConsumer};
+use db::episode_file::get_show_episode_files;
+use db::tv_show::get_entire_show;
+use db::DBExecutor;
+use error::WebError;
+use naming::season_folder_name;
+use nfo::{episode_nfo, season_nfo, show_nfo};
+use resources::episode_file::EpisodeFile;
+use resources::tv_show::{TVMetadataProvider, TVShow, TVShowEpisode};
+
+use actix::prelude::*;
+use actix_web::{client, HttpMessage};
+use futures::{future, Future};
+
+/// The largest image that is downloaded as artwork.
+const MAX_ARTWORK_SIZE: usize = 20 * 1024 * 1024;
+
+/// The metadata files to write for a show.
+#[derive(Debug, Default)]
+pub struct MetadataFiles {
+    /// NFO files and their contents.
+    pub nfos: Vec<(PathBuf, String)>,
+    /// Image files and the URLs to download them from.
+    pub artwork: Vec<(PathBuf, String)>,
+}
+
+/// Works out which NFO and image files `config.metadata_consumers` expect for
+/// a show and its episode files. Season files are only placed into season
+/// folders that already hold episodes. The NFO files of local shows are where
+/// their metadata comes from, so those are left alone.
+pub fn plan_metadata(show: &TVShow, files: &[EpisodeFile], config: &Config) -> MetadataFiles {
+    let mut planned = MetadataFiles::default();
+    let show_path = match show.path {
+        Some(ref path) => PathBuf::from(path),
+        None => return planned,
+    };
+    let kodi = config.metadata_consumers.contains(&MetadataConsumer::Kodi);
+    let jellyfin = config.metadata_consumers.contains(&MetadataConsumer::Jellyfin);
+    if !kodi && !jellyfin {
+        return planned;
+    }
+
+    planned
+        .nfos
+        .push((show_path.join("tvshow.nfo"), show_nfo(show, kodi)));
+    if let Some(ref poster) = show.cover_img {
+        planned
+            .artwork
+            .push((image_path(&show_path, "poster", poster), poster.clone()));
+    }
+    if let Some(ref fanart) = show.fanart_img {
+        planned
+            .artwork
+            .push((image_path(&show_path, "fanart", fanart), fanart.clone()));
+    }
+
+    let file_folders: HashSet<&Path> = files
+        .iter()
+        .filter_map(|f| Path::new(&f.path).parent())
+        .collect();
+    let seasons = show.seasons.iter().flat_map(|seasons| seasons.iter());
+
+    for season in seasons.clone() {
+        let season_path = show_path.join(season_folder_name(&config.season_folder_format, season.num));
+        let has_folder = season_path != show_path && file_folders.contains(season_path.as_path());
+
+        if has_folder {
+            planned
+                .nfos
+                .push((season_path.join("season.nfo"), season_nfo(season, kodi)));
+        }
+
+        if let Some(ref poster) = season.cover_img {
+            if kodi {
+                let name = match season.num {
+                    0 => "season-specials-poster".to_string(),
+                    num => format!("season{:02}-poster", num),
+                };
+                planned
+                    .artwork
+                    .push((image_path(&show_path, &name, poster), poster.clone()));
+            }
+            if jellyfin && has_folder {
+                planned
+                    .artwork
+                    .push((image_path(&season_path, "poster", poster), poster.clone()));
+            }
+        }
+    }
+
+    // Files holding several episodes have a record for each of them.
+    let mut episodes_by_file: BTreeMap<&str, Vec<&TVShowEpisode>> = BTreeMap::new();
+    for file in files {
+        let episode = seasons
+            .clone()
+            .flat_map(|s| s.episodes.iter().flat_map(|e| e.iter()))
+            .find(|e| e.id == file.episode_id);
+        if let Some(episode) = episode {
+            episodes_by_file
+                .entry(&file.path)
+                .or_insert_with(Vec::new)
+                .push(episode);
+        }
+    }
+
+    for (path, mut episodes) in episodes_by_file {
+        episodes.sort_by_key(|e| e.num);
+        let path = Path::new(path);
+        planned
+            .nfos
+            .push((path.with_extension("nfo"), episode_nfo(show, &episodes, kodi)));
+
+        let thumb = episodes[0].cover_img.as_ref();
+        if let (Some(thumb), Some(stem), Some(folder)) = (thumb, path.file_stem(), path.parent()) {
+            let name = format!("{}-thumb", stem.to_string_lossy());
+            planned
+                .artwork
+                .push((image_path(folder, &name, thumb), thumb.clone()));
+        }
+    }
+
+    if show.provider == TVMetadataProvider::Local {
+        planned.nfos.clear();
+    }
+    planned
+}
+
+/// Names an image after the extension of the URL it comes from.
+fn image_path(folder: &Path, name: &str, url: &str) -> PathBuf {
+    let ext = Path::new(url)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| ext.to_lowercase())
+        .filter(|ext| ext == "png" || ext == "jpg" || ext == "jpeg")
+        .unwrap_or_else(|| "jpg".to_string());
+
+    folder.join(format!("{}.{}", name, ext))
+}
+
+/// Writes the planned NFO files, replacing existing ones, and downloads the
+/// artwork. Images that already exist are only downloaded again when
+/// `refresh_artwork` is set.
+pub fn write_metadata(
+    planned: MetadataFiles,
+    refresh_artwork: bool,
+) -> Box<Future<Item = (), Error = WebError>> {
+    for (path, contents) in planned.nfos.iter() {
+        let written = path
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|_| fs::write(path, contents));
+        if let Err(e) = written {
+            return Box::new(future::err(WebError::from(e)));
+        }
+    }
+
+    let mut fut: Box<Future<Item = (), Error = WebError>> = Box::new(future::ok(()));
+    for (path, url) in planned.artwork {
+        if path.exists() && !refresh_artwork {
+            continue;
+        }
+
+        fut = Box::new(fut.and_then(move |_| {
+            download_image(&url, path.clone()).then(move |res| {
+                // A missing image shouldn't stop the rest from being written.
+                if let Err(e) = res {
+                    eprintln!("Unable to download {:?}: {:?}", path, e);
+                }
+                Ok(())
+            })
+        }));
+    }
+
+    fut
+}
+
+fn download_image(url: &str, path: PathBuf) -> Box<Future<Item = (), Error = WebError>> {
+    let request = match client::get(url).finish() {
+        Ok(request) => request,
+        Err(e) => return Box::new(future::err(WebError::ArtworkError(format!("{:?}", e)))),
+    };
+
+    Box::new(
+        request
+            .send()
+            .map_err(|e| WebError::ArtworkError(format!("{:?}", e)))
+            .and_then(|res| {
+                if res.status().is_success() {
+                    Ok(res)
+                } else {
+                    Err(WebError::ArtworkError(format!("Unexpected status {}", res.status())))
+                }
+            })
+            .and_then(|res| {
+                res.body()
+                    .limit(MAX_ARTWORK_SIZE)
+                    .map_err(|e| WebError::ArtworkError(format!("{:?}", e)))
+            })
+            .and_then(move |body| {
+                if let Some(parent) = path.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                fs::write(&path, &body).map_err(WebError::from)
+            }),
+    )
+}
+
+/// Writes the metadata of a show in the library for the configured media
+/// centers. Does nothing if none are configured or the show has no folder.
+pub fn update_show_metadata(
+    show_id: i32,
+    refresh_artwork: bool,
+    config: Rc<Config>,
+    pool: Addr<Syn, DBExecutor>,
+) -> Box<Future<Item = (), Error = WebError>> {
+    if config.metadata_consumers.is_empty() {
+        return Box::new(future::ok(()));
+    }
+
+    let show = get_entire_show(show_id, pool.clone()).and_then(|show| show);
+    Box::new(
+        show.join(get_show_episode_files(show_id, pool))
+            .and_then(move |(show, files)| {
+                write_metadata(plan_metadata(&show, &files, &config), refresh_artwork)
+            }),
+    )
+}
+
+/// Like `update_show_metadata`, but failures are only logged. Used where
+/// writing metadata is a side effect of something else.
+pub fn try_update_show_metadata(
+    show_id: i32,
+    refresh_artwork: bool,
+    config: Rc<Config>,
+    pool: Addr<Syn, DBExecutor>,
+) -> Box<Future<Item = (), Error = WebError>> {
+    Box::new(
+        update_show_metadata(show_id, refresh_artwork, config, pool).or_else(move |e| {
+            eprintln!("Unable to write metadata for show {}: {:?}", show_id, e);
+            Ok(())
+        }),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use resources::quality::{Quality, DEFAULT_QUALITY_PROFILE_ID};
+    use resources::tv_show::{ExternalIds, MetadataSources, TVShowSeason};
+
+    use chrono::Utc;
+
+    fn show() -> TVShow {
+        let episode = |id: i32, num: i32| TVShowEpisode {
+            id,
+            show_id: 1,
+            season_id: 1,
+            num: Some(num),
+            season_num: 1,
+            name: format!("Episode {}", num),
+            summary: None,
+            cover_img: Some(format!("http://img/episode{}.png", num)),
+            runtime: None,
+            airdate: None,
+            monitored: true,
+            provider_id: id.to_string(),
+            provider_url: String::new(),
+            metadata_sources: MetadataSources::new(),
+        };
+
+        TVShow {
+            id: 1,
+            name: "Show Name".to_string(),
+            summary: None,
+            cover_img: Some("http://img/poster.jpg".to_string()),
+            fanart_img: Some("http://img/fanart.jpg".to_string()),
+            genres: vec![],
+            runtime: None,
+            premiered: None,
+            external_ids: ExternalIds::default(),
+            provider: TVMetadataProvider::TVMaze,
+            provider_id: "1".to_string(),
+            provider_url: String::new(),
+            provider_updated: None,
+            monitored: true,
+            quality_profile_id: DEFAULT_QUALITY_PROFILE_ID,
+            root_folder_id: None,
+            path: Some("/tv/Show Name".to_string()),
+            aliases: vec![],
+            metadata_sources: MetadataSources::new(),
+            seasons: Some(vec![TVShowSeason {
+                id: 1,
+                show_id: 1,
+                num: 1,
+                name: String::new(),
+                summary: None,
+                cover_img: Some("http://img/season1.jpg".to_string()),
+                provider_id: "1".to_string(),
+                provider_url: String::new(),
+                metadata_sources: MetadataSources::new(),
+                episodes: Some(vec![episode(10, 1), episode(11, 2), episode(12, 3)]),
+            }]),
+        }
+    }
+
+    fn file(episode_id: i32, path: &str) -> EpisodeFile {
+        EpisodeFile {
+            id: episode_id,
+            episode_id,
+            path: path.to_string(),
+            size: 0,
+            quality: Quality::HDTV720p,
+            created_at: Utc::now(),
+            media_info: None,
+        }
+    }
+
+    fn config(consumers: Vec<MetadataConsumer>) -> Config {
+        Config {
+            metadata_consumers: consumers,
+            ..Config::default()
+        }
+    }
+
+    fn paths(files: &[(PathBuf, String)]) -> Vec<String> {
+        files
+            .iter()
+            .map(|&(ref path, _)| path.to_string_lossy().into_owned())
+            .collect()
+    }
+
+    #[test]
+    fn lays_out_files_for_kodi() {
+        let files = vec![
+            file(10, "/tv/Show Name/Season 01/Show Name - S01E01E02.mkv"),
+            file(11, "/tv/Show Name/Season 01/Show Name - S01E01E02.mkv"),
+        ];
+        let planned = plan_metadata(&show(), &files, &config(vec![MetadataConsumer::Kodi]));
+
+        assert_eq!(
+            paths(&planned.nfos),
+            vec![
+                "/tv/Show Name/tvshow.nfo",
+                "/tv/Show Name/Season 01/season.nfo",
+                "/tv/Show Name/Season 01/Show Name - S01E01E02.nfo",
+            ]
+        );
+        assert_eq!(planned.nfos[2].1.matches("<episodedetails>").count(), 2);
+        assert_eq!(
+            paths(&planned.artwork),
+            vec![
+                "/tv/Show Name/poster.jpg",
+                "/tv/Show Name/fanart.jpg",
+                "/tv/Show Name/season01-poster.jpg",
+                "/tv/Show Name/Season 01/Show Name - S01E01E02-thumb.png",
+            ]
+        );
+    }
+
+    #[test]
+    fn places_season_posters_for_jellyfin() {
+        let files = vec![file(12, "/tv/Show Name/Season 01/Show Name - S01E03.mkv")];
+        let planned = plan_metadata(&show(), &files, &config(vec![MetadataConsumer::Jellyfin]));
+
+        assert!(paths(&planned.artwork).contains(&"/tv/Show Name/Season 01/poster.jpg".to_string()));
+        assert!(!planned.nfos[0].1.contains("<thumb"));
+        assert!(plan_metadata(&show(), &files, &config(vec![])).nfos.is_empty());
+    }
+
+    #[test]
+    fn leaves_nfo_files_of_local_shows_alone() {
+        let files = vec![file(12, "/tv/Show Name/Season 01/Show Name - S01E03.mkv")];
+        let local = TVShow {
+            provider: TVMetadataProvider::Local,
+            ..show()
+        };
+        let planned = plan_metadata(&local, &files, &config(vec![MetadataConsumer::Jellyfin]));
+
+        assert!(planned.nfos.is_empty());
+        assert!(!planned.artwork.is_empty());
+    }
+}
@@ -0,0 +1,6 @@
+pub mod catalog;
+pub mod import;
+pub mod metadata;
+pub mod scanner;
+pub mod search;
+pub mod show;
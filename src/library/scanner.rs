@@ -0,0 +1,595 @@
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use config::Config;
+use db::episode_file::upsert_episode_file;
+use db::import_plan::*;
+use db::tv_show::{get_show_ids_by_provider, insert_show};
+use db::DBExecutor;
+use error::WebError;
+use library::import::{find_video_files, parse_video_file};
+use library::metadata::try_update_show_metadata;
+use library::show::{load_full_show, try_refresh_show_credits};
+use media;
+use nfo::{element_text, elements};
+use providers::metadata::{MetadataProvider, MetadataProviders};
+use resources::episode_file::EpisodeFile;
+use resources::import_plan::*;
+use resources::root_folder::RootFolder;
+use resources::tv_show::{ExternalId, ShowSearchResult, TVMetadataProvider, TVShow};
+
+use actix::prelude::*;
+use chrono::Utc;
+use futures::{future, Future};
+
+/// A folder inside a root folder that is assumed to hold a single show.
+#[derive(Clone, Debug)]
+pub struct CandidateShow {
+    pub folder: PathBuf,
+    pub title: String,
+    pub year: Option<i32>,
+    pub external_id: Option<ExternalId>,
+    pub files: Vec<PathBuf>,
+}
+
+/// A folder inside a root folder that couldn't be scanned.
+#[derive(Clone, Debug)]
+pub struct SkippedFolder {
+    pub folder: PathBuf,
+    pub reason: String,
+}
+
+/// Treats every folder directly inside `root` as a show. Folders that can't
+/// be scanned are returned apart, so that one of them doesn't stop the scan.
+pub fn scan_root_folder(root: &Path) -> io::Result<(Vec<CandidateShow>, Vec<SkippedFolder>)> {
+    let mut candidates = Vec::new();
+    let mut skipped = Vec::new();
+
+    for entry in fs::read_dir(root)? {
+        let path = entry?.path();
+        if !path.is_dir() {
+            continue;
+        }
+
+        let scanned = match path.file_name().and_then(|name| name.to_str()) {
+            Some(name) if name.starts_with('.') => continue,
+            Some(_) => scan_show_folder(&path).map_err(|e| e.to_string()),
+            None => Err("The folder's name isn't valid UTF-8".to_string()),
+        };
+        match scanned {
+            Ok(candidate) => candidates.push(candidate),
+            Err(reason) => skipped.push(SkippedFolder {
+                folder: path,
+                reason,
+            }),
+        }
+    }
+
+    candidates.sort_by(|a, b| a.folder.cmp(&b.folder));
+    skipped.sort_by(|a, b| a.folder.cmp(&b.folder));
+    Ok((candidates, skipped))
+}
+
+pub fn scan_show_folder(folder: &Path) -> io::Result<CandidateShow> {
+    let name = folder
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or("");
+    let (title, year) = clean_folder_name(name);
+    let external_id = find_external_id(name).or_else(|| read_nfo_external_id(folder));
+
+    Ok(CandidateShow {
+        folder: folder.to_path_buf(),
+        title,
+        year,
+        external_id,
+        files: find_video_files(folder)?,
+    })
+}
+
+/// Strips tags such as `[imdb-tt0944947]` or `{tvdb-121361}` from a folder
+/// name, and splits off a year written as `(2011)`.
+pub fn clean_folder_name(name: &str) -> (String, Option<i32>) {
+    let mut title = String::with_capacity(name.len());
+    let mut year = None;
+    let mut rest = name;
+
+    while let Some(start) = rest.find(|c: char| c == '[' || c == '{' || c == '(') {
+        title.push_str(&rest[..start]);
+
+        let close = match rest.as_bytes()[start] {
+            b'[' => ']',
+            b'{' => '}',
+            _ => ')',
+        };
+        let end = match rest[start..].find(close) {
+            Some(end) => start + end,
+            None => {
+                rest = &rest[start + 1..];
+                continue;
+            }
+        };
+
+        let inner = &rest[start + 1..end];
+        if close == ')' {
+            match inner.parse::<i32>() {
+                Ok(y) if inner.len() == 4 => year = Some(y),
+                _ => title.push_str(inner),
+            }
+        }
+
+        rest = &rest[end + 1..];
+    }
+    title.push_str(rest);
+
+    let title = title
+        .split(|c: char| c.is_whitespace() || c == '.' || c == '_')
+        .filter(|t| !t.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ");
+    (title, year)
+}
+
+/// Finds an IMDb id (`tt0944947`) or a TheTVDB id (`tvdb-121361`,
+/// `tvdbid=121361`) in a folder name.
+pub fn find_external_id(text: &str) -> Option<ExternalId> {
+    let lowered = text.to_lowercase();
+
+    for (idx, _) in lowered.match_indices("tt") {
+        let preceded_by_word = lowered[..idx]
+            .chars()
+            .last()
+            .map_or(false, |c| c.is_alphanumeric());
+        let digits = leading_digits(&lowered[idx + 2..]);
+
+        if !preceded_by_word && digits.len() >= 7 {
+            return Some(ExternalId::IMDB(format!("tt{}", digits)));
+        }
+    }
+
+    for (idx, _) in lowered.match_indices("tvdb") {
+        let mut rest = &lowered[idx + 4..];
+        if rest.starts_with("id") {
+            rest = &rest[2..];
+        }
+        let rest = rest.trim_left_matches(|c| c == '-' || c == '=' || c == ':' || c == ' ');
+        if let Ok(id) = leading_digits(rest).parse() {
+            return Some(ExternalId::TheTVDB(id));
+        }
+    }
+
+    None
+}
+
+fn leading_digits(s: &str) -> &str {
+    let end = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    &s[..end]
+}
+
+/// Reads an external id from the `tvshow.nfo` in `folder`, if there is one.
+fn read_nfo_external_id(folder: &Path) -> Option<ExternalId> {
+    let mut nfo = String::new();
+    File::open(folder.join("tvshow.nfo"))
+        .and_then(|mut f| f.read_to_string(&mut nfo))
+        .ok()?;
+
+    for id in elements(&nfo, "uniqueid") {
+        match id.attr("type") {
+            Some("imdb") => return Some(ExternalId::IMDB(id.text())),
+            Some("tvdb") => {
+                if let Ok(id) = id.text().parse() {
+                    return Some(ExternalId::TheTVDB(id));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    for tag in &["imdb_id", "imdbid"] {
+        if let Some(value) = element_text(&nfo, tag) {
+            return Some(ExternalId::IMDB(value));
+        }
+    }
+    if let Some(id) = element_text(&nfo, "tvdbid").and_then(|v| v.parse().ok()) {
+        return Some(ExternalId::TheTVDB(id));
+    }
+
+    // Some NFO files only contain a link to the show's IMDb page.
+    find_external_id(&nfo)
+}
+
+/// Maps each episode file to the episodes of `show` it contains, returning the
+/// matched files and the paths of the files that couldn't be matched.
+pub fn match_files(show: &TVShow, files: &[PathBuf]) -> (Vec<PlannedFile>, Vec<String>) {
+    let mut matched = Vec::new();
+    let mut unmatched = Vec::new();
+
+    for file in files {
+        let path = file.to_string_lossy().into_owned();
+        let parsed = match parse_video_file(file) {
+            Some(ref parsed) if !parsed.episodes.is_empty() => parsed.clone(),
+            _ => {
+                unmatched.push(path);
+                continue;
+            }
+        };
+
+        let all_known = parsed
+            .episodes
+            .iter()
+            .all(|num| find_episode_id(show, parsed.season, *num).is_some());
+
+        if all_known {
+            matched.push(PlannedFile {
+                path,
+                season: parsed.season,
+                episodes: parsed.episodes,
+                quality: parsed.quality,
+            });
+        } else {
+            unmatched.push(path);
+        }
+    }
+
+    (matched, unmatched)
+}
+
+fn find_episode_id(show: &TVShow, season: i32, num: i32) -> Option<i32> {
+    show.seasons
+        .iter()
+        .flat_map(|seasons| seasons.iter())
+        .flat_map(|s| s.episodes.iter().flat_map(|e| e.iter()))
+        .find(|e| e.season_num == season && e.num == Some(num))
+        .map(|e| e.id)
+}
+
+fn unmatched_show(candidate: CandidateShow) -> PlannedShow {
+    PlannedShow {
+        folder: candidate.folder.to_string_lossy().into_owned(),
+        matched_by: None,
+        show: None,
+        existing_show_id: None,
+        files: vec![],
+        unmatched_files: candidate
+            .files
+            .iter()
+            .map(|f| f.to_string_lossy().into_owned())
+            .collect(),
+        scan_error: None,
+    }
+}
+
+fn skipped_show(skipped: SkippedFolder) -> PlannedShow {
+    PlannedShow {
+        folder: skipped.folder.to_string_lossy().into_owned(),
+        matched_by: None,
+        show: None,
+        existing_show_id: None,
+        files: vec![],
+        unmatched_files: vec![],
+        scan_error: Some(skipped.reason),
+    }
+}
+
+/// The search result for a folder: the first one that premiered in the year
+/// the folder names, if it names one, and the first one otherwise. Shows that
+/// share a name, such as remakes, are told apart by their year.
+fn pick_search_result(results: Vec<ShowSearchResult>, year: Option<i32>) -> Option<TVShow> {
+    let result = match year {
+        Some(year) => results.into_iter().find(|r| r.year == Some(year)),
+        None => results.into_iter().next(),
+    };
+    result.map(|r| r.show)
+}
+
+/// Identifies the show in a candidate folder, preferring an external id over
+/// a search for the folder's name.
+fn identify_show(
+    candidate: &CandidateShow,
+    provider: Rc<MetadataProvider>,
+) -> Box<Future<Item = Option<(TVShow, MatchSource)>, Error = WebError>> {
+    let title = candidate.title.clone();
+    let year = candidate.year;
+    let search = move |provider: Rc<MetadataProvider>| {
+        provider.search(&title).map(move |results| {
+            pick_search_result(results, year).map(|show| (show, MatchSource::Search))
+        })
+    };
+
+    match candidate.external_id {
+        Some(ref id) => Box::new(
+            provider
+                .lookup_show(id)
+                .and_then(move |show| match show {
+                    Some(show) => Box::new(future::ok(Some((show, MatchSource::ExternalId))))
+                        as Box<Future<Item = _, Error = _>>,
+                    None => Box::new(search(provider)),
+                }),
+        ),
+        None => Box::new(search(provider)),
+    }
+}
+
+/// Identifies a candidate folder and matches its files to the show's
+/// episodes. A folder that can't be identified is kept in the plan, without a
+/// show, so it can be reviewed. If looking it up failed, the error is kept in
+/// its `scan_error`.
+pub fn plan_show(
+    candidate: CandidateShow,
+    provider: Rc<MetadataProvider>,
+) -> Box<Future<Item = PlannedShow, Error = WebError>> {
+    let fallback = candidate.clone();
+
+    Box::new(
+        identify_show(&candidate, provider.clone())
+            .and_then(move |found| match found {
+                Some((show, matched_by)) => Box::new(
+                    load_full_show(provider, &show.provider_id).map(move |show| {
+                        let (files, unmatched_files) = match_files(&show, &candidate.files);
+                        PlannedShow {
+                            folder: candidate.folder.to_string_lossy().into_owned(),
+                            matched_by: Some(matched_by),
+                            show: Some(show),
+                            existing_show_id: None,
+                            files,
+                            unmatched_files,
+                            scan_error: None,
+                        }
+                    }),
+                )
+                    as Box<Future<Item = PlannedShow, Error = WebError>>,
+                None => Box::new(future::ok(unmatched_show(candidate))),
+            })
+            .or_else(move |e| {
+                let mut planned = unmatched_show(fallback);
+                planned.scan_error = Some(format!("Unable to identify the show: {}", e));
+                Ok(planned)
+            }),
+    )
+}
+
+/// Scans a root folder and stores the resulting plan without adding anything
+/// to the library.
+pub fn create_import_plan(
+    root_folder: RootFolder,
+    provider: Rc<MetadataProvider>,
+    pool: Addr<Syn, DBExecutor>,
+) -> Box<Future<Item = ImportPlan, Error = WebError>> {
+    let (candidates, skipped) = match scan_root_folder(Path::new(&root_folder.path)) {
+        Ok(scan) => scan,
+        Err(e) => return Box::new(future::err(WebError::from(e))),
+    };
+
+    let mut fut: Box<Future<Item = Vec<PlannedShow>, Error = WebError>> =
+        Box::new(future::ok(vec![]));
+    for candidate in candidates {
+        let provider = provider.clone();
+        fut = Box::new(fut.and_then(move |mut shows| {
+            plan_show(candidate, provider).map(move |show| {
+                shows.push(show);
+                shows
+            })
+        }));
+    }
+
+    let existing = get_show_ids_by_provider(TVMetadataProvider::TVMaze, pool.clone());
+    Box::new(
+        fut.join(existing)
+            .and_then(move |(mut shows, existing)| {
+                for planned in shows.iter_mut() {
+                    planned.existing_show_id = planned
+                        .show
+                        .as_ref()
+                        .and_then(|show| existing.get(&show.provider_id).cloned());
+                }
+                shows.extend(skipped.into_iter().map(skipped_show));
+                shows.sort_by(|a, b| a.folder.cmp(&b.folder));
+                insert_import_plan(root_folder.id, &shows, pool)
+            }),
+    )
+}
+
+/// Builds the file records for a newly inserted show from its planned files.
+fn episode_files(show: &TVShow, files: &[PlannedFile]) -> Vec<EpisodeFile> {
+    files
+        .iter()
+        .flat_map(|file| {
+            let size = fs::metadata(&file.path).map(|m| m.len() as i64).unwrap_or(0);
+            let media_info = media::probe(Path::new(&file.path)).ok();
+            file.episodes
+                .iter()
+                .filter_map(|num| find_episode_id(show, file.season, *num))
+                .map(|episode_id| EpisodeFile {
+                    id: -1,
+                    episode_id,
+                    path: file.path.clone(),
+                    size,
+                    quality: file.quality,
+                    created_at: Utc::now(),
+                    media_info: media_info.clone(),
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+/// Adds the shows in `folders` from a plan to the library, recording their
+/// existing files in place. Shows that are already in the library are
+/// skipped.
+pub fn confirm_import_plan(
+    id: i32,
+    folders: Vec<String>,
+    providers: MetadataProviders,
+    config: Rc<Config>,
+    pool: Addr<Syn, DBExecutor>,
+) -> Box<Future<Item = Vec<TVShow>, Error = WebError>> {
+    Box::new(get_import_plan(id, pool.clone()).and_then(move |plan| {
+        if plan.confirmed_at.is_some() {
+            return Box::new(future::err(WebError::InvalidRequest(format!(
+                "Import plan {} was already confirmed",
+                id
+            )))) as Box<Future<Item = Vec<TVShow>, Error = WebError>>;
+        }
+
+        let root_folder_id = plan.root_folder_id;
+        let mut fut: Box<Future<Item = Vec<TVShow>, Error = WebError>> =
+            Box::new(future::ok(vec![]));
+
+        for planned in plan.shows {
+            let mut show = match planned.show {
+                Some(ref show) if planned.existing_show_id.is_none()
+                    && folders.contains(&planned.folder) =>
+                {
+                    show.clone()
+                }
+                _ => continue,
+            };
+            show.path = Some(planned.folder.clone());
+            show.root_folder_id = Some(root_folder_id);
+
+            let pool = pool.clone();
+            let config = config.clone();
+            let provider = providers.get(show.provider).ok();
+            let files = planned.files;
+            fut = Box::new(fut.and_then(move |mut shows| {
+                insert_show(&show, pool.clone()).and_then(move |show| {
+                    let show_id = show.id;
+                    let inserts: Vec<_> = episode_files(&show, &files)
+                        .iter()
+                        .map(|f| upsert_episode_file(f, pool.clone()))
+                        .collect();
+                    let credits: Box<Future<Item = (), Error = WebError>> = match provider {
+                        Some(provider) => {
+                            let id = &show.provider_id;
+                            try_refresh_show_credits(show_id, id, provider, pool.clone())
+                        }
+                        None => Box::new(future::ok(())),
+                    };
+                    future::join_all(inserts)
+                        .and_then(move |_| credits)
+                        .and_then(move |_| try_update_show_metadata(show_id, false, config, pool))
+                        .map(move |_| {
+                            shows.push(show);
+                            shows
+                        })
+                })
+            }));
+        }
+
+        Box::new(fut.and_then(move |shows| {
+            mark_import_plan_confirmed(id, pool).map(move |_| shows)
+        }))
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use super::*;
+    use resources::quality::DEFAULT_QUALITY_PROFILE_ID;
+    use resources::tv_show::{ExternalIds, MetadataSources};
+
+    use chrono::NaiveDate;
+    use tempfile::TempDir;
+
+    #[test]
+    fn cleans_folder_names() {
+        assert_eq!(
+            clean_folder_name("Doctor Who (2005) [imdb-tt0436992]"),
+            ("Doctor Who".to_string(), Some(2005))
+        );
+        assert_eq!(
+            clean_folder_name("The.Office.(US)"),
+            ("The Office US".to_string(), None)
+        );
+    }
+
+    #[test]
+    fn finds_external_ids_in_names() {
+        assert_eq!(
+            find_external_id("Doctor Who (2005) [imdb-tt0436992]"),
+            Some(ExternalId::IMDB("tt0436992".to_string()))
+        );
+        assert_eq!(
+            find_external_id("Firefly {tvdbid-78874}"),
+            Some(ExternalId::TheTVDB(78874))
+        );
+        assert_eq!(find_external_id("Scott Pilgrim"), None);
+    }
+
+    #[test]
+    fn scans_show_folders_and_nfo_files() {
+        let root = TempDir::new().unwrap();
+        let show = root.path().join("Firefly (2002)");
+        fs::create_dir_all(show.join("Season 01")).unwrap();
+        File::create(show.join("Season 01").join("Firefly - S01E01.mkv")).unwrap();
+        File::create(show.join("tvshow.nfo"))
+            .unwrap()
+            .write_all(b"<tvshow><uniqueid type=\"tvdb\" default=\"true\">78874</uniqueid></tvshow>")
+            .unwrap();
+        fs::create_dir_all(root.path().join(".trash")).unwrap();
+
+        let (candidates, skipped) = scan_root_folder(root.path()).unwrap();
+        assert!(skipped.is_empty());
+        assert_eq!(candidates.len(), 1);
+        assert_eq!(candidates[0].title, "Firefly");
+        assert_eq!(candidates[0].year, Some(2002));
+        assert_eq!(candidates[0].external_id, Some(ExternalId::TheTVDB(78874)));
+        assert_eq!(candidates[0].files.len(), 1);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn skips_folders_that_cannot_be_scanned() {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+
+        let root = TempDir::new().unwrap();
+        fs::create_dir_all(root.path().join("Firefly")).unwrap();
+        let invalid = root.path().join(OsStr::from_bytes(b"Caf\xe9"));
+        fs::create_dir_all(&invalid).unwrap();
+
+        let (candidates, skipped) = scan_root_folder(root.path()).unwrap();
+        assert_eq!(candidates.len(), 1);
+        assert_eq!(skipped.len(), 1);
+        assert_eq!(skipped[0].folder, invalid);
+    }
+
+    #[test]
+    fn picks_the_search_result_from_the_folders_year() {
+        let result = |provider_id: &str, year: i32| {
+            let show = TVShow {
+                id: -1,
+                name: "Doctor Who".to_string(),
+                summary: None,
+                cover_img: None,
+                fanart_img: None,
+                genres: vec![],
+                runtime: None,
+                premiered: Some(NaiveDate::from_ymd(year, 3, 26)),
+                external_ids: ExternalIds::default(),
+                provider: TVMetadataProvider::TVMaze,
+                provider_id: provider_id.to_string(),
+                provider_url: String::new(),
+                provider_updated: None,
+                monitored: true,
+                quality_profile_id: DEFAULT_QUALITY_PROFILE_ID,
+                root_folder_id: None,
+                path: None,
+                aliases: vec![],
+                metadata_sources: MetadataSources::new(),
+                seasons: None,
+            };
+            ShowSearchResult::new(show, None, None, None)
+        };
+        let results = vec![result("1963", 1963), result("2005", 2005)];
+        let picked = |year| pick_search_result(results.clone(), year).map(|s| s.provider_id);
+
+        assert_eq!(picked(None), Some("1963".to_string()));
+        assert_eq!(picked(Some(2005)), Some("2005".to_string()));
+        assert_eq!(picked(Some(2018)), None);
+    }
+}
@@ -0,0 +1,390 @@
+use std::rc::Rc;
+
+use db::episode::insert_grab;
+use db::DBExecutor;
+use error::WebError;
+use providers::download::DownloadProvider;
+use providers::indexer::{IndexerProvider, IndexerQuery, IndexerRelease};
+use release::{self, title_matches, ParsedRelease};
+use resources::grab::EpisodeGrab;
+use resources::wanted::WantedEpisode;
+
+use actix::prelude::*;
+use chrono::Utc;
+use futures::{future, Future};
+
+/// A release that matched a wanted episode, paired with its parsed name.
+#[derive(Clone, Debug, Serialize)]
+pub struct RankedRelease {
+    pub release: IndexerRelease,
+    pub parsed: ParsedRelease,
+}
+
+/// Searches the configured indexers for wanted episodes and sends the best
+/// matching release to the download provider.
+pub struct Searcher {
+    indexers: Vec<Rc<IndexerProvider>>,
+    downloader: Option<Rc<DownloadProvider>>,
+}
+
+impl Searcher {
+    pub fn new(
+        indexers: Vec<Rc<IndexerProvider>>,
+        downloader: Option<Rc<DownloadProvider>>,
+    ) -> Searcher {
+        Searcher {
+            indexers,
+            downloader,
+        }
+    }
+
+    /// Queries every indexer and returns the acceptable releases for
+    /// `episode`, best first.
+    ///
+    /// An indexer that fails is skipped so that one broken indexer doesn't
+    /// prevent grabs from the others.
+    pub fn search(
+        &self,
+        episode: &WantedEpisode,
+    ) -> Box<Future<Item = Vec<RankedRelease>, Error = WebError>> {
+        let query = IndexerQuery::from(episode);
+        let searches: Vec<_> = self
+            .indexers
+            .iter()
+            .map(|indexer| {
+                let name = indexer.name().to_string();
+                indexer.search(&query).or_else(move |e| {
+                    eprintln!("Indexer {} failed: {:?}", name, e);
+                    Ok::<_, WebError>(vec![])
+                })
+            })
+            .collect();
+
+        let episode = episode.clone();
+        Box::new(future::join_all(searches).map(move |results| {
+            rank_releases(&episode, results.into_iter().flatten().collect())
+        }))
+    }
+
+    /// Searches for `episode` and sends the best release to the download
+    /// provider. Resolves to `None` if nothing acceptable was found.
+    pub fn grab(
+        &self,
+        episode: &WantedEpisode,
+    ) -> Box<Future<Item = Option<EpisodeGrab>, Error = WebError>> {
+        let downloader = match self.downloader {
+            Some(ref downloader) => downloader.clone(),
+            None => return Box::new(future::err(WebError::NoDownloadProvider)),
+        };
+        let episode_id = episode.episode_id;
+
+        Box::new(self.search(episode).and_then(move |releases| {
+            let best = match releases.into_iter().next() {
+                Some(best) => best,
+                None => {
+                    return Box::new(future::ok(None))
+                        as Box<Future<Item = Option<EpisodeGrab>, Error = WebError>>
+                }
+            };
+
+            Box::new(downloader.add(&best.release).map(move |download_id| {
+                Some(EpisodeGrab {
+                    id: -1,
+                    episode_id,
+                    title: best.release.title,
+                    quality: best.parsed.quality,
+                    indexer: best.release.indexer,
+                    download_id,
+                    grabbed_at: Utc::now(),
+                })
+            }))
+        }))
+    }
+}
+
+/// Drops releases that don't belong to `episode` or aren't an upgrade under
+/// its quality profile, then sorts the rest by quality and seeders.
+pub fn rank_releases(
+    episode: &WantedEpisode,
+    releases: Vec<IndexerRelease>,
+) -> Vec<RankedRelease> {
+    let num = match episode.num {
+        Some(num) => num,
+        None => return vec![],
+    };
+
+    let mut ranked: Vec<RankedRelease> = releases
+        .into_iter()
+        .filter_map(|r| {
+            release::parse(&r.title).map(|parsed| RankedRelease { release: r, parsed })
+        })
+        .filter(|r| {
+            title_matches(&r.parsed.title, &episode.show_name, &episode.show_aliases)
+                && r.parsed.season == episode.season_num
+                && r.parsed.episodes.contains(&num)
+                && episode.profile.is_upgrade(episode.current_quality(), r.parsed.quality)
+        })
+        .collect();
+
+    ranked.sort_by(|a, b| {
+        b.parsed
+            .quality
+            .cmp(&a.parsed.quality)
+            .then(b.release.seeders.cmp(&a.release.seeders))
+    });
+    ranked
+}
+
+/// Grabs each of `episodes` in turn and records the successful grabs.
+pub fn grab_episodes(
+    searcher: Rc<Searcher>,
+    episodes: Vec<WantedEpisode>,
+    pool: Addr<Syn, DBExecutor>,
+) -> Box<Future<Item = Vec<EpisodeGrab>, Error = WebError>> {
+    record_grabs(searcher, episodes, move |grab| insert_grab(&grab, pool.clone()))
+}
+
+/// Grabs each of `episodes` in turn and records the successful grabs with
+/// `record`. An episode whose search, grab or record fails is logged and
+/// skipped, so that it doesn't keep the others from being grabbed.
+fn record_grabs<R>(
+    searcher: Rc<Searcher>,
+    episodes: Vec<WantedEpisode>,
+    record: R,
+) -> Box<Future<Item = Vec<EpisodeGrab>, Error = WebError>>
+where
+    R: Fn(EpisodeGrab) -> Box<Future<Item = EpisodeGrab, Error = WebError>> + 'static,
+{
+    let record = Rc::new(record);
+    let mut fut: Box<Future<Item = Vec<EpisodeGrab>, Error = WebError>> =
+        Box::new(future::ok(vec![]));
+
+    for episode in episodes.into_iter().filter(|e| e.is_wanted()) {
+        let searcher = searcher.clone();
+        let record = record.clone();
+
+        fut = Box::new(fut.and_then(move |mut grabs| {
+            let episode_id = episode.episode_id;
+            searcher
+                .grab(&episode)
+                .and_then(move |grab| match grab {
+                    Some(grab) => Box::new((*record)(grab).map(Some))
+                        as Box<Future<Item = Option<EpisodeGrab>, Error = WebError>>,
+                    None => Box::new(future::ok(None)),
+                })
+                .then(move |res| -> Result<Vec<EpisodeGrab>, WebError> {
+                    match res {
+                        Ok(Some(grab)) => grabs.push(grab),
+                        Ok(None) => {}
+                        Err(e) => eprintln!("Unable to grab episode {}: {:?}", episode_id, e),
+                    }
+                    Ok(grabs)
+                })
+        }));
+    }
+
+    fut
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+
+    use super::*;
+    use providers::download::CompletedDownload;
+    use resources::quality::{Quality, QualityProfile};
+
+    type GrabFuture = Box<Future<Item = EpisodeGrab, Error = WebError>>;
+
+    struct FakeIndexer {
+        releases: Vec<(&'static str, u32)>,
+    }
+
+    impl IndexerProvider for FakeIndexer {
+        fn name(&self) -> &str {
+            "fake"
+        }
+
+        fn search(
+            &self,
+            _query: &IndexerQuery,
+        ) -> Box<Future<Item = Vec<IndexerRelease>, Error = WebError>> {
+            Box::new(future::ok(
+                self.releases
+                    .iter()
+                    .map(|&(title, seeders)| IndexerRelease {
+                        title: title.to_string(),
+                        indexer: "fake".to_string(),
+                        download_url: format!("magnet:?dn={}", title),
+                        size: None,
+                        seeders: Some(seeders),
+                    })
+                    .collect(),
+            ))
+        }
+    }
+
+    struct BrokenIndexer;
+
+    impl IndexerProvider for BrokenIndexer {
+        fn name(&self) -> &str {
+            "broken"
+        }
+
+        fn search(
+            &self,
+            _query: &IndexerQuery,
+        ) -> Box<Future<Item = Vec<IndexerRelease>, Error = WebError>> {
+            Box::new(future::err(WebError::IndexerError("offline".to_string())))
+        }
+    }
+
+    #[derive(Default)]
+    struct FakeDownloader {
+        added: RefCell<Vec<String>>,
+    }
+
+    impl DownloadProvider for FakeDownloader {
+        fn name(&self) -> &str {
+            "fake"
+        }
+
+        fn add(&self, release: &IndexerRelease) -> Box<Future<Item = String, Error = WebError>> {
+            self.added.borrow_mut().push(release.title.clone());
+            Box::new(future::ok("download-1".to_string()))
+        }
+
+        fn completed(&self) -> Box<Future<Item = Vec<CompletedDownload>, Error = WebError>> {
+            Box::new(future::ok(vec![]))
+        }
+    }
+
+    fn wanted(file_quality: Option<Quality>) -> WantedEpisode {
+        WantedEpisode {
+            episode_id: 7,
+            show_id: 1,
+            season_id: 2,
+            show_name: "The Expanse".to_string(),
+            show_aliases: vec![],
+            season_num: 3,
+            num: Some(5),
+            name: "Triple Point".to_string(),
+            airdate: None,
+            profile: QualityProfile {
+                id: 1,
+                name: "HD".to_string(),
+                allowed: vec![Quality::HDTV720p, Quality::WEBDL720p, Quality::WEBDL1080p],
+                cutoff: Quality::WEBDL1080p,
+            },
+            file_quality,
+            grab_quality: None,
+        }
+    }
+
+    fn searcher(downloader: Rc<FakeDownloader>) -> Searcher {
+        let indexer = FakeIndexer {
+            releases: vec![
+                ("The.Expanse.S03E05.720p.HDTV.x264-AAA", 500),
+                ("The.Expanse.S03E05.720p.WEB-DL.x264-BBB", 10),
+                ("The.Expanse.S03E05.720p.WEB-DL.x264-CCC", 50),
+                ("The.Expanse.S03E05.2160p.WEB-DL.x265-DDD", 900),
+                ("The.Expanse.S03E06.1080p.WEB-DL.x264-EEE", 900),
+                ("The.Expanse.Recap.720p.HDTV-FFF", 900),
+                ("Expanse.Rising.S03E05.1080p.WEB-DL-GGG", 900),
+            ],
+        };
+
+        Searcher::new(
+            vec![Rc::new(BrokenIndexer) as Rc<IndexerProvider>, Rc::new(indexer)],
+            Some(downloader as Rc<DownloadProvider>),
+        )
+    }
+
+    #[test]
+    fn grabs_best_allowed_release() {
+        let downloader = Rc::new(FakeDownloader::default());
+        let grab = searcher(downloader.clone())
+            .grab(&wanted(None))
+            .wait()
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(grab.episode_id, 7);
+        assert_eq!(grab.quality, Quality::WEBDL720p);
+        assert_eq!(grab.title, "The.Expanse.S03E05.720p.WEB-DL.x264-CCC");
+        assert_eq!(grab.download_id, "download-1");
+        assert_eq!(*downloader.added.borrow(), vec![grab.title.clone()]);
+    }
+
+    #[test]
+    fn skips_releases_that_are_not_upgrades() {
+        let downloader = Rc::new(FakeDownloader::default());
+        let grab = searcher(downloader.clone())
+            .grab(&wanted(Some(Quality::WEBDL720p)))
+            .wait()
+            .unwrap();
+
+        assert!(grab.is_none());
+        assert!(downloader.added.borrow().is_empty());
+    }
+
+    #[test]
+    fn fails_without_download_provider() {
+        let searcher = Searcher::new(vec![], None);
+        match searcher.grab(&wanted(None)).wait() {
+            Err(WebError::NoDownloadProvider) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn matches_releases_named_after_an_alias() {
+        let downloader = Rc::new(FakeDownloader::default());
+        let mut episode = wanted(None);
+        episode.show_aliases = vec!["Expanse Rising".to_string()];
+        let grab = searcher(downloader)
+            .grab(&episode)
+            .wait()
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(grab.title, "Expanse.Rising.S03E05.1080p.WEB-DL-GGG");
+    }
+
+    #[test]
+    fn records_grabs_and_skips_episodes_that_fail() {
+        let downloader = Rc::new(FakeDownloader::default());
+        let episodes: Vec<WantedEpisode> = vec![7, 8, 9, 10]
+            .into_iter()
+            .map(|id| {
+                let mut episode = wanted(None);
+                episode.episode_id = id;
+                if id == 10 {
+                    episode.file_quality = Some(Quality::WEBDL1080p);
+                }
+                episode
+            })
+            .collect();
+
+        // Stands in for `insert_grab`, failing for one of the episodes.
+        let recorded = Rc::new(RefCell::new(vec![]));
+        let record_into = recorded.clone();
+        let record = move |mut grab: EpisodeGrab| -> GrabFuture {
+            if grab.episode_id == 8 {
+                return Box::new(future::err(WebError::DownloadError("full".to_string())));
+            }
+            grab.id = grab.episode_id * 10;
+            record_into.borrow_mut().push(grab.episode_id);
+            Box::new(future::ok(grab))
+        };
+
+        let grabs = record_grabs(Rc::new(searcher(downloader.clone())), episodes, record)
+            .wait()
+            .unwrap();
+
+        assert_eq!(grabs.iter().map(|g| g.id).collect::<Vec<_>>(), vec![70, 90]);
+        assert_eq!(*recorded.borrow(), vec![7, 9]);
+        // The episode that already has its cutoff quality isn't searched.
+        assert_eq!(downloader.added.borrow().len(), 3);
+    }
+}
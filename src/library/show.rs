@@ -0,0 +1,213 @@
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use config::Config;
+use db::person::replace_show_credits;
+use db::tv_show::{get_entire_show, get_show_updates_by_provider, insert_seasons, update_show};
+use db::DBExecutor;
+use error::WebError;
+use library::metadata::try_update_show_metadata;
+use providers::metadata::{MetadataProvider, UpdatePeriod};
+use resources::tv_show::*;
+
+use actix::prelude::*;
+use futures::{future, Future};
+
+/// Loads a show from `provider` along with all of its seasons and episodes,
+/// at once if the provider can.
+pub fn load_full_show(
+    provider: Rc<MetadataProvider>,
+    id: &str,
+) -> Box<Future<Item = TVShow, Error = WebError>> {
+    let season_provider = provider.clone();
+    let id_clone = id.to_string();
+
+    Box::new(provider.get_full_show(id).and_then(move |show| match show {
+        Some(show) => Box::new(future::ok(show)) as Box<Future<Item = _, Error = _>>,
+        None => load_show_by_season(season_provider, &id_clone),
+    }))
+}
+
+/// Episodes are requested one season at a time so that the provider isn't
+/// hit with a burst of concurrent requests.
+fn load_show_by_season(
+    provider: Rc<MetadataProvider>,
+    id: &str,
+) -> Box<Future<Item = TVShow, Error = WebError>> {
+    let season_provider = provider.clone();
+    let load_seasons = provider.get_show(id).and_then(move |show| {
+        season_provider
+            .get_show_seasons(&show.provider_id)
+            .map(|seasons| (show, seasons))
+    });
+
+    Box::new(load_seasons.and_then(move |(mut show, seasons)| {
+        let mut fut: Box<Future<Item = Vec<TVShowSeason>, Error = WebError>> =
+            Box::new(future::ok(vec![]));
+
+        for mut season in seasons.into_iter() {
+            let provider = provider.clone();
+            let show_id = show.provider_id.clone();
+
+            fut = Box::new(fut.and_then(move |mut seasons| {
+                provider
+                    .get_season_episodes(&show_id, &season.provider_id)
+                    .map(move |episodes| {
+                        season.episodes = Some(episodes);
+                        seasons.push(season);
+                        seasons
+                    })
+            }));
+        }
+
+        fut.map(move |seasons| {
+            show.seasons = Some(seasons);
+            show
+        })
+    }))
+}
+
+/// Reloads a show in the library from `provider`, updating its details,
+/// adding or updating its seasons and episodes and replacing its credits.
+pub fn refresh_show(
+    show: TVShow,
+    provider: Rc<MetadataProvider>,
+    pool: Addr<Syn, DBExecutor>,
+) -> Box<Future<Item = TVShow, Error = WebError>> {
+    let show_id = show.id;
+    let pool_clone = pool.clone();
+    let provider_id = show.provider_id.clone();
+    let credits_provider = provider.clone();
+    let credits_pool = pool.clone();
+
+    Box::new(
+        load_full_show(provider, &show.provider_id)
+            .and_then(move |mut fresh| {
+                fresh.id = show_id;
+                let seasons = fresh
+                    .seasons
+                    .take()
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(|mut s| {
+                        s.show_id = show_id;
+                        s
+                    })
+                    .collect();
+
+                update_show(&fresh, pool.clone()).join(insert_seasons(seasons, pool))
+            })
+            .and_then(move |_| {
+                try_refresh_show_credits(show_id, &provider_id, credits_provider, credits_pool)
+            })
+            .and_then(move |_| get_entire_show(show_id, pool_clone).and_then(|show| show)),
+    )
+}
+
+/// Replaces a show's cast and crew with the ones listed by `provider`.
+/// Failures are only logged, since the show is usable without them.
+pub fn try_refresh_show_credits(
+    show_id: i32,
+    provider_id: &str,
+    provider: Rc<MetadataProvider>,
+    pool: Addr<Syn, DBExecutor>,
+) -> Box<Future<Item = (), Error = WebError>> {
+    Box::new(
+        provider
+            .get_show_credits(provider_id)
+            .and_then(move |credits| replace_show_credits(show_id, credits, pool))
+            .or_else(move |e| {
+                eprintln!("Unable to refresh the credits of show {}: {:?}", show_id, e);
+                Ok(())
+            }),
+    )
+}
+
+/// Refreshes the library's shows from `kind` that changed at the provider
+/// within `since`, one at a time, and resolves to how many were refreshed.
+/// Shows that fail to refresh are logged and skipped.
+pub fn refresh_updated_shows(
+    kind: TVMetadataProvider,
+    provider: Rc<MetadataProvider>,
+    since: UpdatePeriod,
+    config: Rc<Config>,
+    pool: Addr<Syn, DBExecutor>,
+) -> Box<Future<Item = usize, Error = WebError>> {
+    let stored_pool = pool.clone();
+    let load_changed = provider.get_show_updates(since).and_then(move |updates| {
+        get_show_updates_by_provider(kind, stored_pool)
+            .map(move |stored| changed_shows(&updates, &stored))
+    });
+
+    Box::new(load_changed.and_then(move |show_ids| {
+        let mut fut: Box<Future<Item = usize, Error = WebError>> = Box::new(future::ok(0));
+
+        for show_id in show_ids {
+            let provider = provider.clone();
+            let config = config.clone();
+            let pool = pool.clone();
+
+            fut = Box::new(fut.and_then(move |refreshed| {
+                let refresh_pool = pool.clone();
+                get_entire_show(show_id, pool.clone())
+                    .and_then(|show| show)
+                    .and_then(move |show| refresh_show(show, provider, refresh_pool))
+                    .and_then(move |_| try_update_show_metadata(show_id, false, config, pool))
+                    .then(move |res| -> Result<usize, WebError> {
+                        match res {
+                            Ok(_) => Ok(refreshed + 1),
+                            Err(e) => {
+                                eprintln!("Unable to refresh show {}: {:?}", show_id, e);
+                                Ok(refreshed)
+                            }
+                        }
+                    })
+            }));
+        }
+
+        fut
+    }))
+}
+
+/// The ids of the stored shows whose last update at their provider is newer
+/// than the one stored with them. Shows stored without one are refreshed
+/// whenever they changed.
+fn changed_shows(
+    updates: &HashMap<String, i64>,
+    stored: &HashMap<String, (i32, Option<i64>)>,
+) -> Vec<i32> {
+    let mut ids: Vec<i32> = stored
+        .iter()
+        .filter_map(|(provider_id, &(id, updated))| match updates.get(provider_id) {
+            Some(&latest) if updated.map_or(true, |u| latest > u) => Some(id),
+            _ => None,
+        })
+        .collect();
+    ids.sort();
+    ids
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_refreshes_shows_that_changed() {
+        let updates: HashMap<String, i64> = vec![
+            ("1".to_string(), 1530000000),
+            ("2".to_string(), 1530000000),
+            ("3".to_string(), 1530000000),
+            ("9".to_string(), 1530000000),
+        ].into_iter()
+            .collect();
+        let stored: HashMap<String, (i32, Option<i64>)> = vec![
+            ("1".to_string(), (11, Some(1530000000))),
+            ("2".to_string(), (12, Some(1520000000))),
+            ("3".to_string(), (13, None)),
+            ("4".to_string(), (14, Some(1520000000))),
+        ].into_iter()
+            .collect();
+
+        assert_eq!(changed_shows(&updates, &stored), vec![12, 13]);
+    }
+}
@@ -2,6 +2,8 @@
 
 extern crate actix;
 extern crate actix_web;
+extern crate chrono;
+extern crate fs2;
 extern crate futures;
 #[macro_use]
 extern crate postgres;
@@ -13,19 +15,36 @@ extern crate serde;
 #[macro_use]
 extern crate serde_derive;
 extern crate serde_json;
+#[cfg(test)]
+extern crate tempfile;
+extern crate thetvdb;
 extern crate tvmaze;
 
+mod config;
 #[macro_use]
 mod db;
 mod error;
+mod jobs;
+mod library;
+mod media;
+mod naming;
+mod nfo;
 mod providers;
+mod release;
 mod resources;
 mod routes;
 
 use std::env;
+use std::rc::Rc;
 
+use config::Config;
 use db::DBExecutor;
-use providers::metadata::TVMazeProvider;
+use jobs::{CatalogSyncJob, EpisodeSearchJob, ImportJob, ShowUpdateJob};
+use library::search::Searcher;
+use providers::download::DownloadProvider;
+use providers::indexer::IndexerProvider;
+use providers::metadata::{LocalProvider, MetadataProviders, TVMazeProvider, TheTVDBProvider};
+use resources::tv_show::TVMetadataProvider;
 use routes::*;
 
 use actix::prelude::*;
@@ -34,22 +53,98 @@ use r2d2::Pool as DBPool;
 use r2d2_postgres::{PostgresConnectionManager, TlsMode};
 
 pub struct AppState {
-    provider: TVMazeProvider,
+    config: Config,
+    metadata_providers: MetadataProviders,
+    searcher: Rc<Searcher>,
     db_pool: Addr<Syn, DBExecutor>,
 }
 
+/// Shows are filled in from the configured fallbacks, if any.
+///
+/// Every worker gets its own providers, but they share `tvmaze`, so that all
+/// of them stay within TVmaze's rate limit together, and know of the show
+/// updates that `ShowUpdateJob` loads, which outdate the cached shows.
+fn create_metadata_providers(config: &Config, tvmaze: &TVMazeProvider) -> MetadataProviders {
+    create_primary_metadata_providers(config, tvmaze).with_fallbacks(&config.metadata_fallbacks)
+}
+
+/// The providers on their own, without filling their shows in from others.
+/// TheTVDB is only available when an API key is configured, and is left out
+/// if its client can't be created.
+fn create_primary_metadata_providers(
+    config: &Config,
+    tvmaze: &TVMazeProvider,
+) -> MetadataProviders {
+    let mut providers = MetadataProviders::new();
+    providers.register(TVMetadataProvider::TVMaze, Rc::new(tvmaze.clone()));
+    providers.register(TVMetadataProvider::Local, Rc::new(LocalProvider::new()));
+
+    if let Some(ref apikey) = config.thetvdb_api_key {
+        let pin = config.thetvdb_pin.as_ref().map(|pin| pin.as_str());
+        match TheTVDBProvider::new(apikey, pin) {
+            Ok(thetvdb) => providers.register(TVMetadataProvider::TheTVDB, Rc::new(thetvdb)),
+            Err(e) => eprintln!("Unable to create the TheTVDB client: {:?}", e),
+        }
+    }
+    providers
+}
+
+/// No indexer implementations exist yet, so searches come back empty until
+/// they are registered here.
+fn create_indexers() -> Vec<Rc<IndexerProvider>> {
+    vec![]
+}
+
+/// No download provider implementations exist yet, so episodes aren't
+/// searched for automatically and nothing is imported until one is registered
+/// here.
+fn create_download_provider() -> Option<Rc<DownloadProvider>> {
+    None
+}
+
+fn create_searcher() -> Searcher {
+    Searcher::new(create_indexers(), create_download_provider())
+}
+
 fn main() {
     let pg_conn = env::var("PG_URL").expect("Unable to get PG_URL environment variable.");
+    let config = Config::from_env();
     let sys = System::new("Unify");
     let manager = PostgresConnectionManager::new(pg_conn, TlsMode::None)
         .expect("Unable to connect to PostgreSQL server.");
     let db_pool = DBPool::new(manager).expect("Unable to create database pool.");
     let addr = SyncArbiter::start(4, move || DBExecutor::new(db_pool.clone()));
+    let tvmaze = TVMazeProvider::new(&config).expect("Unable to create the TVmaze client.");
+
+    let _: Addr<Unsync, _> = ShowUpdateJob::new(
+        create_metadata_providers(&config, &tvmaze),
+        Rc::new(config.clone()),
+        addr.clone(),
+    ).start();
+
+    // The catalog only holds what the providers list themselves.
+    if !config.catalog_providers.is_empty() {
+        let _: Addr<Unsync, _> = CatalogSyncJob::new(
+            create_primary_metadata_providers(&config, &tvmaze),
+            config.catalog_providers.clone(),
+            addr.clone(),
+        ).start();
+    }
+
+    // Without a download provider every grab would fail.
+    if let Some(downloader) = create_download_provider() {
+        let _: Addr<Unsync, _> =
+            EpisodeSearchJob::new(Rc::new(create_searcher()), addr.clone()).start();
+        let _: Addr<Unsync, _> =
+            ImportJob::new(downloader, Rc::new(config.clone()), addr.clone()).start();
+    }
 
     server::new(move || {
         let state = AppState {
+            config: config.clone(),
             db_pool: addr.clone(),
-            provider: TVMazeProvider::new(),
+            metadata_providers: create_metadata_providers(&config, &tvmaze),
+            searcher: Rc::new(create_searcher()),
         };
 
         App::with_state(state).scope("/api", |api_scope| {
@@ -60,9 +155,62 @@ fn main() {
                             r.get().with(shows_all);
                             r.post().with(shows_add);
                         })
+                        .resource("/lookup", |r| r.get().with(shows_lookup))
                         .resource("/{id}", |r| {
                             r.get().with(shows_get);
                         })
+                        .resource("/{id}/refresh", |r| r.post().with(shows_refresh))
+                        .resource("/{id}/cast", |r| r.get().with(shows_cast))
+                        .resource("/{id}/crew", |r| r.get().with(shows_crew))
+                        .resource("/{id}/aliases", |r| {
+                            r.get().with(shows_aliases);
+                            r.post().with(shows_aliases_add);
+                        })
+                        .resource("/{id}/aliases/{alias_id}", |r| {
+                            r.delete().with(shows_aliases_delete)
+                        })
+                })
+                .nested("/people", |scope| {
+                    scope
+                        .resource("/{id}", |r| r.get().with(people_get))
+                        .resource("/{id}/shows", |r| r.get().with(people_shows))
+                })
+                .nested("/episodes", |scope| {
+                    scope.resource("/{id}/search", |r| {
+                        r.post().with(episodes_search);
+                    })
+                })
+                .nested("/seasons", |scope| {
+                    scope.resource("/{id}/search", |r| {
+                        r.post().with(seasons_search);
+                    })
+                })
+                .nested("/rootfolders", |scope| {
+                    scope
+                        .resource("", |r| {
+                            r.get().with(root_folders_all);
+                            r.post().with(root_folders_add);
+                        })
+                        .resource("/{id}", |r| {
+                            r.get().with(root_folders_get);
+                            r.put().with(root_folders_update);
+                            r.delete().with(root_folders_delete);
+                        })
+                        .resource("/{id}/scan", |r| r.post().with(root_folders_scan))
+                })
+                .nested("/importplans", |scope| {
+                    scope
+                        .resource("/{id}", |r| r.get().with(import_plans_get))
+                        .resource("/{id}/confirm", |r| r.post().with(import_plans_confirm))
+                })
+                .nested("/wanted", |scope| {
+                    scope
+                        .resource("/missing", |r| {
+                            r.get().with(wanted_missing);
+                        })
+                        .resource("/cutoff", |r| {
+                            r.get().with(wanted_cutoff);
+                        })
                 })
                 .route("/search", http::Method::GET, search)
         })
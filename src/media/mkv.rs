@@ -0,0 +1,383 @@
+//! Matroska (and WebM) files are a tree of EBML elements. Only the `Info` and
+//! `Tracks` elements of the segment are read; clusters of media data are
+//! skipped over.
+
+use std::io::{Read, Seek, SeekFrom};
+
+use super::*;
+
+pub const EBML_MAGIC: [u8; 4] = [0x1A, 0x45, 0xDF, 0xA3];
+
+const EBML_HEADER: u64 = 0x1A45_DFA3;
+const SEGMENT: u64 = 0x1853_8067;
+const INFO: u64 = 0x1549_A966;
+const TRACKS: u64 = 0x1654_AE6B;
+const TIMECODE_SCALE: u64 = 0x2A_D7B1;
+const DURATION: u64 = 0x4489;
+const TRACK_ENTRY: u64 = 0xAE;
+const TRACK_TYPE: u64 = 0x83;
+const CODEC_ID: u64 = 0x86;
+const LANGUAGE: u64 = 0x22_B59C;
+const VIDEO: u64 = 0xE0;
+const PIXEL_WIDTH: u64 = 0xB0;
+const PIXEL_HEIGHT: u64 = 0xBA;
+
+const TRACK_TYPE_VIDEO: u64 = 1;
+const TRACK_TYPE_AUDIO: u64 = 2;
+const TRACK_TYPE_SUBTITLE: u64 = 0x11;
+
+/// Timestamps are in milliseconds unless the file says otherwise.
+const DEFAULT_TIMECODE_SCALE: u64 = 1_000_000;
+
+/// Tracks without a `Language` element are English according to the spec.
+const DEFAULT_LANGUAGE: &'static str = "eng";
+
+pub fn probe<R: Read + Seek>(reader: &mut R) -> Result<MediaInfo, ProbeError> {
+    match read_header(reader)? {
+        Some((EBML_HEADER, Some(size))) => skip(reader, size)?,
+        _ => return Err(ProbeError::Malformed("missing EBML header")),
+    }
+
+    loop {
+        match read_header(reader)? {
+            Some((SEGMENT, _)) => break,
+            Some((_, Some(size))) => skip(reader, size)?,
+            _ => return Err(ProbeError::Malformed("missing segment")),
+        }
+    }
+
+    let mut info = MediaInfo::new(Container::Matroska);
+    let (mut seen_info, mut seen_tracks) = (false, false);
+
+    while !(seen_info && seen_tracks) {
+        match read_header(reader)? {
+            Some((INFO, Some(size))) => {
+                parse_info(&read_body(reader, size)?, &mut info)?;
+                seen_info = true;
+            }
+            Some((TRACKS, Some(size))) => {
+                parse_tracks(&read_body(reader, size)?, &mut info)?;
+                seen_tracks = true;
+            }
+            Some((_, Some(size))) => skip(reader, size)?,
+            // An element of unknown size (usually a live-streamed cluster)
+            // can't be skipped, so stop with what has been found so far.
+            Some((_, None)) | None => break,
+        }
+    }
+
+    if !seen_tracks {
+        return Err(ProbeError::Malformed("missing tracks"));
+    }
+    Ok(info)
+}
+
+fn parse_info(data: &[u8], info: &mut MediaInfo) -> Result<(), ProbeError> {
+    let mut scale = DEFAULT_TIMECODE_SCALE;
+    let mut duration = None;
+
+    for (id, body) in elements(data)? {
+        match id {
+            TIMECODE_SCALE => scale = read_uint(body),
+            DURATION => duration = read_float(body),
+            _ => {}
+        }
+    }
+
+    info.duration = duration.map(|d| d * scale as f64 / 1_000_000_000.0);
+    Ok(())
+}
+
+fn parse_tracks(data: &[u8], info: &mut MediaInfo) -> Result<(), ProbeError> {
+    for (id, entry) in elements(data)? {
+        if id != TRACK_ENTRY {
+            continue;
+        }
+
+        let mut track_type = 0;
+        let mut codec = String::new();
+        let mut language = DEFAULT_LANGUAGE.to_string();
+        let mut size = (None, None);
+
+        for (id, body) in elements(entry)? {
+            match id {
+                TRACK_TYPE => track_type = read_uint(body),
+                CODEC_ID => codec = read_string(body),
+                LANGUAGE => language = read_string(body),
+                VIDEO => {
+                    for (id, body) in elements(body)? {
+                        match id {
+                            PIXEL_WIDTH => size.0 = Some(read_uint(body) as u32),
+                            PIXEL_HEIGHT => size.1 = Some(read_uint(body) as u32),
+                            _ => {}
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let language = if language == "und" { None } else { Some(language) };
+        match track_type {
+            TRACK_TYPE_VIDEO if info.video_codec.is_none() => {
+                info.video_codec = Some(codec_name(&codec));
+                info.width = size.0;
+                info.height = size.1;
+            }
+            TRACK_TYPE_AUDIO => info.audio_tracks.push(Track {
+                codec: codec_name(&codec),
+                language,
+            }),
+            TRACK_TYPE_SUBTITLE => info.subtitle_tracks.push(Track {
+                codec: codec_name(&codec),
+                language,
+            }),
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
+
+/// Maps a Matroska codec id to a short, container-independent name.
+fn codec_name(id: &str) -> String {
+    let name = match id {
+        "V_MPEG4/ISO/AVC" => "h264",
+        "V_MPEGH/ISO/HEVC" => "hevc",
+        "V_MPEG4/ISO/ASP" => "mpeg4",
+        "V_MPEG2" => "mpeg2",
+        "V_VP8" => "vp8",
+        "V_VP9" => "vp9",
+        "V_AV1" => "av1",
+        "A_AC3" => "ac3",
+        "A_EAC3" => "eac3",
+        "A_DTS" => "dts",
+        "A_TRUEHD" => "truehd",
+        "A_FLAC" => "flac",
+        "A_OPUS" => "opus",
+        "A_VORBIS" => "vorbis",
+        "A_MPEG/L3" => "mp3",
+        "S_TEXT/UTF8" => "srt",
+        "S_TEXT/ASS" | "S_TEXT/SSA" => "ass",
+        "S_TEXT/WEBVTT" => "webvtt",
+        "S_HDMV/PGS" => "pgs",
+        "S_VOBSUB" => "vobsub",
+        _ if id.starts_with("A_AAC") => "aac",
+        _ => return id.to_lowercase(),
+    };
+    name.to_string()
+}
+
+/// Reads an element's id and size from a stream, returning `None` at the end
+/// of the stream. The size is `None` if the element's size is unknown.
+fn read_header<R: Read>(reader: &mut R) -> Result<Option<(u64, Option<u64>)>, ProbeError> {
+    let mut first = [0; 1];
+    if reader.read(&mut first)? == 0 {
+        return Ok(None);
+    }
+
+    let id = read_stream_vint(reader, first[0], true)?;
+    reader.read_exact(&mut first)?;
+    let size = read_stream_vint(reader, first[0], false)?;
+
+    Ok(Some((id.0, if is_unknown_size(size) { None } else { Some(size.0) })))
+}
+
+fn read_stream_vint<R: Read>(
+    reader: &mut R,
+    first: u8,
+    keep_marker: bool,
+) -> Result<(u64, usize), ProbeError> {
+    let len = vint_len(first)?;
+    let mut bytes = [0; 8];
+    bytes[0] = first;
+    reader.read_exact(&mut bytes[1..len])?;
+    vint(&bytes[..len], keep_marker)
+}
+
+fn skip<R: Seek>(reader: &mut R, size: u64) -> Result<(), ProbeError> {
+    reader.seek(SeekFrom::Current(size as i64))?;
+    Ok(())
+}
+
+/// Splits the body of a master element into its children.
+fn elements(mut data: &[u8]) -> Result<Vec<(u64, &[u8])>, ProbeError> {
+    let mut children = Vec::new();
+
+    while !data.is_empty() {
+        let (id, id_len) = vint(data, true)?;
+        let size = vint(&data[id_len..], false)?;
+        let start = id_len + size.1;
+        // An unknown size inside a header element runs to the end of it.
+        let end = if is_unknown_size(size) {
+            data.len()
+        } else {
+            start + size.0 as usize
+        };
+
+        if end > data.len() || start > end {
+            return Err(ProbeError::Malformed("element overruns its parent"));
+        }
+        children.push((id, &data[start..end]));
+        data = &data[end..];
+    }
+
+    Ok(children)
+}
+
+fn vint_len(first: u8) -> Result<usize, ProbeError> {
+    if first == 0 {
+        Err(ProbeError::Malformed("invalid variable-length integer"))
+    } else {
+        Ok(first.leading_zeros() as usize + 1)
+    }
+}
+
+/// Decodes a variable-length integer, returning its value and length in
+/// bytes. Element ids keep their length marker, sizes don't.
+fn vint(data: &[u8], keep_marker: bool) -> Result<(u64, usize), ProbeError> {
+    let len = vint_len(*data.first().ok_or(ProbeError::Malformed("truncated element"))?)?;
+    if data.len() < len {
+        return Err(ProbeError::Malformed("truncated element"));
+    }
+
+    let mut value = read_uint(&data[..len]);
+    if !keep_marker {
+        value &= (1 << (7 * len)) - 1;
+    }
+    Ok((value, len))
+}
+
+/// Whether a decoded size has all of its bits set, which marks an unknown
+/// size.
+fn is_unknown_size(size: (u64, usize)) -> bool {
+    size.0 == (1 << (7 * size.1)) - 1
+}
+
+fn read_float(data: &[u8]) -> Option<f64> {
+    match data.len() {
+        4 => Some(f32::from_bits(read_uint(data) as u32) as f64),
+        8 => Some(f64::from_bits(read_uint(data))),
+        _ => None,
+    }
+}
+
+fn read_string(data: &[u8]) -> String {
+    String::from_utf8_lossy(data)
+        .trim_right_matches('\0')
+        .to_string()
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    fn be_bytes(value: u64) -> Vec<u8> {
+        (0..8).rev().map(|i| (value >> (i * 8)) as u8).collect()
+    }
+
+    /// Encodes an element with a one byte size, or an eight byte one for
+    /// larger bodies.
+    fn element(id: u64, body: &[u8]) -> Vec<u8> {
+        let mut out: Vec<u8> = be_bytes(id).into_iter().skip_while(|b| *b == 0).collect();
+        if body.len() < 0x7F {
+            out.push(0x80 | body.len() as u8);
+        } else {
+            out.push(0x01);
+            out.extend_from_slice(&be_bytes(body.len() as u64)[1..]);
+        }
+        out.extend_from_slice(body);
+        out
+    }
+
+    fn uint(id: u64, value: u64) -> Vec<u8> {
+        element(id, &be_bytes(value))
+    }
+
+    /// Builds a Matroska file with a 1080p H.264 track, an English and a
+    /// Japanese AAC track and an English subtitle track.
+    pub fn sample(duration_secs: f64) -> Vec<u8> {
+        let info = [
+            uint(TIMECODE_SCALE, DEFAULT_TIMECODE_SCALE),
+            element(DURATION, &be_bytes((duration_secs * 1000.0).to_bits())),
+        ].concat();
+
+        let video = [
+            uint(TRACK_TYPE, TRACK_TYPE_VIDEO),
+            element(CODEC_ID, b"V_MPEG4/ISO/AVC"),
+            element(
+                VIDEO,
+                &[uint(PIXEL_WIDTH, 1920), uint(PIXEL_HEIGHT, 1080)].concat(),
+            ),
+        ].concat();
+        let english = [
+            uint(TRACK_TYPE, TRACK_TYPE_AUDIO),
+            element(CODEC_ID, b"A_AAC"),
+        ].concat();
+        let japanese = [
+            uint(TRACK_TYPE, TRACK_TYPE_AUDIO),
+            element(CODEC_ID, b"A_AC3"),
+            element(LANGUAGE, b"jpn"),
+        ].concat();
+        let subtitles = [
+            uint(TRACK_TYPE, TRACK_TYPE_SUBTITLE),
+            element(CODEC_ID, b"S_TEXT/UTF8"),
+            element(LANGUAGE, b"eng"),
+        ].concat();
+        let tracks = [
+            element(TRACK_ENTRY, &video),
+            element(TRACK_ENTRY, &english),
+            element(TRACK_ENTRY, &japanese),
+            element(TRACK_ENTRY, &subtitles),
+        ].concat();
+
+        let segment = [
+            element(INFO, &info),
+            element(TRACKS, &tracks),
+            element(0x1F43_B675, &[0; 256]),
+        ].concat();
+
+        [
+            element(EBML_HEADER, &element(0x4282, b"matroska")),
+            element(SEGMENT, &segment),
+        ].concat()
+    }
+
+    #[test]
+    fn probes_tracks_and_duration() {
+        let info = probe(&mut Cursor::new(sample(2580.0))).unwrap();
+
+        assert_eq!(info.container, Container::Matroska);
+        assert_eq!(info.duration, Some(2580.0));
+        assert_eq!((info.width, info.height), (Some(1920), Some(1080)));
+        assert_eq!(info.video_codec, Some("h264".to_string()));
+        assert_eq!(info.audio_languages(), vec!["eng", "jpn"]);
+        assert_eq!(info.audio_tracks[1].codec, "ac3");
+        assert_eq!(
+            info.subtitle_tracks,
+            vec![Track {
+                codec: "srt".to_string(),
+                language: Some("eng".to_string()),
+            }]
+        );
+    }
+
+    #[test]
+    fn handles_unknown_segment_size() {
+        let mut data = element(EBML_HEADER, &[]);
+        data.extend_from_slice(&[0x18, 0x53, 0x80, 0x67, 0xFF]);
+        data.extend(element(
+            TRACKS,
+            &element(
+                TRACK_ENTRY,
+                &[uint(TRACK_TYPE, TRACK_TYPE_VIDEO), element(CODEC_ID, b"V_VP9")].concat(),
+            ),
+        ));
+
+        let info = probe(&mut Cursor::new(data)).unwrap();
+        assert_eq!(info.video_codec, Some("vp9".to_string()));
+        assert_eq!(info.duration, None);
+    }
+}
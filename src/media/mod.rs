@@ -0,0 +1,137 @@
+//! Reads the headers of Matroska and MP4 containers to find out what a video
+//! file actually holds, independent of what its name claims.
+
+use std::fs::File;
+use std::io::{self, BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+
+mod mkv;
+mod mp4;
+
+#[cfg(test)]
+pub use self::mkv::tests::sample as sample_mkv;
+
+/// The largest header element that is read into memory. Anything larger is
+/// assumed to be a corrupt size rather than a real header.
+const MAX_HEADER_SIZE: u64 = 16 * 1024 * 1024;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Container {
+    Matroska,
+    MP4,
+}
+
+/// An audio or subtitle track.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Track {
+    pub codec: String,
+    /// An ISO 639-2 code such as `eng`, if the container specifies one.
+    pub language: Option<String>,
+}
+
+/// What was found in a video file's container headers.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MediaInfo {
+    pub container: Container,
+    /// The duration in seconds.
+    pub duration: Option<f64>,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub video_codec: Option<String>,
+    pub audio_tracks: Vec<Track>,
+    pub subtitle_tracks: Vec<Track>,
+}
+
+impl MediaInfo {
+    fn new(container: Container) -> MediaInfo {
+        MediaInfo {
+            container,
+            duration: None,
+            width: None,
+            height: None,
+            video_codec: None,
+            audio_tracks: vec![],
+            subtitle_tracks: vec![],
+        }
+    }
+
+    pub fn audio_languages(&self) -> Vec<&str> {
+        self.audio_tracks
+            .iter()
+            .filter_map(|t| t.language.as_ref().map(|l| l.as_str()))
+            .collect()
+    }
+}
+
+#[derive(Debug)]
+pub enum ProbeError {
+    IOError(io::Error),
+    /// The file isn't a Matroska or MP4 container.
+    UnknownContainer,
+    Malformed(&'static str),
+}
+
+impl From<io::Error> for ProbeError {
+    fn from(e: io::Error) -> Self {
+        ProbeError::IOError(e)
+    }
+}
+
+pub fn probe(path: &Path) -> Result<MediaInfo, ProbeError> {
+    let mut reader = BufReader::new(File::open(path)?);
+    probe_reader(&mut reader)
+}
+
+pub fn probe_reader<R: Read + Seek>(reader: &mut R) -> Result<MediaInfo, ProbeError> {
+    let mut magic = [0; 8];
+    reader.read_exact(&mut magic).map_err(|e| match e.kind() {
+        io::ErrorKind::UnexpectedEof => ProbeError::UnknownContainer,
+        _ => ProbeError::IOError(e),
+    })?;
+    reader.seek(SeekFrom::Start(0))?;
+
+    if magic[..4] == mkv::EBML_MAGIC {
+        mkv::probe(reader)
+    } else if &magic[4..] == b"ftyp" {
+        mp4::probe(reader)
+    } else {
+        Err(ProbeError::UnknownContainer)
+    }
+}
+
+/// Reads the `len` bytes of an element's body.
+fn read_body<R: Read>(reader: &mut R, len: u64) -> Result<Vec<u8>, ProbeError> {
+    if len > MAX_HEADER_SIZE {
+        return Err(ProbeError::Malformed("header element is too large"));
+    }
+
+    let mut body = vec![0; len as usize];
+    reader.read_exact(&mut body)?;
+    Ok(body)
+}
+
+/// Reads a big-endian unsigned integer of up to 8 bytes.
+fn read_uint(data: &[u8]) -> u64 {
+    data.iter().take(8).fold(0, |n, b| (n << 8) | *b as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    #[test]
+    fn rejects_unknown_containers() {
+        match probe_reader(&mut Cursor::new(b"definitely not a video".to_vec())) {
+            Err(ProbeError::UnknownContainer) => {}
+            other => panic!("Expected UnknownContainer, got {:?}", other),
+        }
+
+        match probe_reader(&mut Cursor::new(b"tiny".to_vec())) {
+            Err(ProbeError::UnknownContainer) => {}
+            other => panic!("Expected UnknownContainer, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,324 @@
+//! MP4 files are a tree of boxes, each starting with its size and a four
+//! character type. Everything of interest is inside the `moov` box, which may
+//! come before or after the media data.
+
+use std::io::{Read, Seek, SeekFrom};
+
+use super::*;
+
+/// Boxes that only contain other boxes and are descended into.
+const CONTAINER_BOXES: &'static [&'static [u8; 4]] = &[b"mdia", b"minf", b"stbl"];
+
+/// Handler types used by subtitle tracks.
+const SUBTITLE_HANDLERS: &'static [&'static [u8; 4]] = &[b"sbtl", b"subt", b"text"];
+
+pub fn probe<R: Read + Seek>(reader: &mut R) -> Result<MediaInfo, ProbeError> {
+    let mut header = [0; 8];
+    let start = reader.seek(SeekFrom::Current(0))?;
+    let end = reader.seek(SeekFrom::End(0))?;
+    reader.seek(SeekFrom::Start(start))?;
+
+    loop {
+        match reader.read_exact(&mut header) {
+            Ok(()) => {}
+            Err(ref e) if e.kind() == ::std::io::ErrorKind::UnexpectedEof => {
+                return Err(ProbeError::Malformed("missing moov box"))
+            }
+            Err(e) => return Err(ProbeError::from(e)),
+        }
+
+        let mut size = read_uint(&header[..4]);
+        let mut header_len = 8;
+        if size == 1 {
+            let mut large = [0; 8];
+            reader.read_exact(&mut large)?;
+            size = read_uint(&large);
+            header_len = 16;
+        }
+        let here = reader.seek(SeekFrom::Current(0))?;
+        if size == 0 {
+            // The last box may run to the end of the file.
+            size = end.saturating_sub(here) + header_len;
+        }
+
+        if size < header_len {
+            return Err(ProbeError::Malformed("box is smaller than its header"));
+        }
+        // Also keeps the seek below within the file for bogus sizes.
+        let body_len = size - header_len;
+        if body_len > end.saturating_sub(here) {
+            return Err(ProbeError::Malformed("box runs past the end of the file"));
+        }
+
+        if &header[4..] == b"moov" {
+            return parse_moov(&read_body(reader, body_len)?);
+        }
+        reader.seek(SeekFrom::Start(here + body_len))?;
+    }
+}
+
+fn parse_moov(data: &[u8]) -> Result<MediaInfo, ProbeError> {
+    let mut info = MediaInfo::new(Container::MP4);
+
+    for (kind, body) in boxes(data)? {
+        match &kind {
+            b"mvhd" => info.duration = parse_mvhd(body),
+            b"trak" => parse_trak(body, &mut info)?,
+            _ => {}
+        }
+    }
+
+    Ok(info)
+}
+
+/// Reads the movie's duration from its `mvhd` box.
+fn parse_mvhd(data: &[u8]) -> Option<f64> {
+    let (timescale, duration) = match *data.first()? {
+        0 => (data.get(12..16)?, data.get(16..20)?),
+        _ => (data.get(20..24)?, data.get(24..32)?),
+    };
+    let timescale = read_uint(timescale);
+
+    if timescale == 0 {
+        None
+    } else {
+        Some(read_uint(duration) as f64 / timescale as f64)
+    }
+}
+
+struct TrakInfo {
+    handler: Option<[u8; 4]>,
+    codec: Option<[u8; 4]>,
+    language: Option<String>,
+    width: Option<u32>,
+    height: Option<u32>,
+}
+
+fn parse_trak(data: &[u8], info: &mut MediaInfo) -> Result<(), ProbeError> {
+    let mut trak = TrakInfo {
+        handler: None,
+        codec: None,
+        language: None,
+        width: None,
+        height: None,
+    };
+    collect_trak(data, &mut trak)?;
+
+    let codec = trak.codec.map_or_else(String::new, |c| codec_name(&c));
+    match trak.handler.as_ref() {
+        Some(b"vide") if info.video_codec.is_none() => {
+            info.video_codec = Some(codec);
+            info.width = trak.width;
+            info.height = trak.height;
+        }
+        Some(b"soun") => info.audio_tracks.push(Track {
+            codec,
+            language: trak.language,
+        }),
+        Some(handler) if SUBTITLE_HANDLERS.contains(&handler) => {
+            info.subtitle_tracks.push(Track {
+                codec,
+                language: trak.language,
+            })
+        }
+        _ => {}
+    }
+
+    Ok(())
+}
+
+fn collect_trak(data: &[u8], trak: &mut TrakInfo) -> Result<(), ProbeError> {
+    for (kind, body) in boxes(data)? {
+        match &kind {
+            b"tkhd" => {
+                // The width and height are 16.16 fixed-point numbers at the
+                // end of the box, whose offset depends on its version.
+                let offset = if body.first() == Some(&0) { 76 } else { 88 };
+                if let Some(size) = body.get(offset..offset + 8) {
+                    trak.width = Some((read_uint(&size[..4]) >> 16) as u32);
+                    trak.height = Some((read_uint(&size[4..]) >> 16) as u32);
+                }
+            }
+            b"mdhd" => {
+                let offset = if body.first() == Some(&0) { 20 } else { 32 };
+                trak.language = body.get(offset..offset + 2).and_then(parse_language);
+            }
+            b"hdlr" => trak.handler = body.get(8..12).map(to_kind),
+            // The first sample entry's type is the track's codec.
+            b"stsd" => trak.codec = body.get(12..16).map(to_kind),
+            kind if CONTAINER_BOXES.contains(&kind) => collect_trak(body, trak)?,
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
+
+/// Decodes a packed ISO 639-2 language code, which stores each letter in five
+/// bits as an offset from 0x60.
+fn parse_language(data: &[u8]) -> Option<String> {
+    let packed = read_uint(data) as u16;
+    let language: String = [10, 5, 0]
+        .iter()
+        .map(|shift| (((packed >> *shift) & 0x1F) as u8 + 0x60) as char)
+        .collect();
+
+    if packed == 0 || language == "und" {
+        None
+    } else {
+        Some(language)
+    }
+}
+
+/// Maps an MP4 sample entry type to a short, container-independent name.
+fn codec_name(kind: &[u8; 4]) -> String {
+    let name = match kind {
+        b"avc1" | b"avc3" => "h264",
+        b"hvc1" | b"hev1" => "hevc",
+        b"mp4v" => "mpeg4",
+        b"av01" => "av1",
+        b"vp09" => "vp9",
+        b"mp4a" => "aac",
+        b"ac-3" => "ac3",
+        b"ec-3" => "eac3",
+        b"Opus" => "opus",
+        b"fLaC" => "flac",
+        b"tx3g" => "tx3g",
+        b"wvtt" => "webvtt",
+        _ => return String::from_utf8_lossy(&kind[..]).trim().to_lowercase(),
+    };
+    name.to_string()
+}
+
+/// Splits the body of a box into its children.
+fn boxes(mut data: &[u8]) -> Result<Vec<([u8; 4], &[u8])>, ProbeError> {
+    let mut children = Vec::new();
+
+    while data.len() >= 8 {
+        let mut size = read_uint(&data[..4]) as usize;
+        let mut header_len = 8;
+        if size == 1 {
+            size = read_uint(data.get(8..16).unwrap_or(&[])) as usize;
+            header_len = 16;
+        } else if size == 0 {
+            size = data.len();
+        }
+
+        if size < header_len || size > data.len() {
+            return Err(ProbeError::Malformed("box overruns its parent"));
+        }
+
+        children.push((to_kind(&data[4..8]), &data[header_len..size]));
+        data = &data[size..];
+    }
+
+    Ok(children)
+}
+
+fn to_kind(data: &[u8]) -> [u8; 4] {
+    let mut kind = [0; 4];
+    kind.copy_from_slice(data);
+    kind
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    fn u32_bytes(value: u32) -> Vec<u8> {
+        (0..4).rev().map(|i| (value >> (i * 8)) as u8).collect()
+    }
+
+    fn mp4_box(kind: &[u8; 4], body: &[u8]) -> Vec<u8> {
+        [u32_bytes(body.len() as u32 + 8), kind.to_vec(), body.to_vec()].concat()
+    }
+
+    fn trak(handler: &[u8; 4], codec: &[u8; 4], language: &[u8; 3], size: (u32, u32)) -> Vec<u8> {
+        let mut tkhd = vec![0; 76];
+        tkhd.extend(u32_bytes(size.0 << 16));
+        tkhd.extend(u32_bytes(size.1 << 16));
+
+        let packed = language
+            .iter()
+            .fold(0u32, |packed, c| (packed << 5) | (*c - 0x60) as u32);
+        let mut mdhd = vec![0; 20];
+        mdhd.extend_from_slice(&u32_bytes(packed)[2..]);
+        mdhd.extend_from_slice(&[0, 0]);
+
+        let hdlr = [vec![0; 8], handler.to_vec(), vec![0; 12]].concat();
+        let stsd = [vec![0, 0, 0, 0], u32_bytes(1), mp4_box(codec, &[0; 8])].concat();
+
+        let stbl = mp4_box(b"stbl", &mp4_box(b"stsd", &stsd));
+        let mdia = [
+            mp4_box(b"mdhd", &mdhd),
+            mp4_box(b"hdlr", &hdlr),
+            mp4_box(b"minf", &stbl),
+        ].concat();
+
+        mp4_box(b"trak", &[mp4_box(b"tkhd", &tkhd), mp4_box(b"mdia", &mdia)].concat())
+    }
+
+    #[test]
+    fn probes_moov_after_media_data() {
+        let mut mvhd = vec![0; 12];
+        mvhd.extend(u32_bytes(1000));
+        mvhd.extend(u32_bytes(1_290_500));
+        mvhd.extend(vec![0; 80]);
+
+        let moov = [
+            mp4_box(b"mvhd", &mvhd),
+            trak(b"vide", b"hvc1", b"und", (1280, 720)),
+            trak(b"soun", b"mp4a", b"eng", (0, 0)),
+            trak(b"sbtl", b"tx3g", b"spa", (0, 0)),
+        ].concat();
+        let file = [
+            mp4_box(b"ftyp", b"isom\0\0\x02\0"),
+            mp4_box(b"mdat", &[0; 512]),
+            mp4_box(b"moov", &moov),
+        ].concat();
+
+        let info = probe(&mut Cursor::new(file)).unwrap();
+        assert_eq!(info.container, Container::MP4);
+        assert_eq!(info.duration, Some(1290.5));
+        assert_eq!((info.width, info.height), (Some(1280), Some(720)));
+        assert_eq!(info.video_codec, Some("hevc".to_string()));
+        assert_eq!(info.audio_languages(), vec!["eng"]);
+        assert_eq!(info.audio_tracks[0].codec, "aac");
+        assert_eq!(
+            info.subtitle_tracks,
+            vec![Track {
+                codec: "tx3g".to_string(),
+                language: Some("spa".to_string()),
+            }]
+        );
+    }
+
+    #[test]
+    fn requires_moov_box() {
+        let file = [mp4_box(b"ftyp", b"isom\0\0\x02\0"), mp4_box(b"mdat", &[0; 16])].concat();
+
+        match probe(&mut Cursor::new(file)) {
+            Err(ProbeError::Malformed(_)) => {}
+            other => panic!("Expected a malformed file, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn rejects_boxes_larger_than_the_file() {
+        let oversized = [
+            u32_bytes(1),
+            b"mdat".to_vec(),
+            u32_bytes(0x8000_0000),
+            u32_bytes(0),
+            vec![0; 16],
+        ].concat();
+        let file = [mp4_box(b"ftyp", b"isom\0\0\x02\0"), oversized].concat();
+
+        match probe(&mut Cursor::new(file)) {
+            Err(ProbeError::Malformed("box runs past the end of the file")) => {}
+            other => panic!("Expected a malformed file, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,152 @@
+//! Renders folder and file names from user-configurable templates such as
+//! `{Show Name}` or `{Show Name} - S{season:00}E{episode:00}`.
+
+use resources::tv_show::{TVShow, TVShowEpisode};
+
+/// Characters that are not allowed in file names on at least one of the
+/// platforms a library might be shared with.
+const ILLEGAL_CHARS: &'static [char] = &['/', '\\', ':', '*', '?', '"', '<', '>', '|'];
+
+/// A value that can be substituted into a template.
+pub enum Token<'a> {
+    Text(&'a str),
+    Number(Option<i32>),
+}
+
+/// Renders `template`, replacing each `{Name}` or `{Name:00}` with the value
+/// `lookup` returns for `Name`. Numbers are zero-padded to the width of the
+/// format after the colon. Unknown tokens are left as they are.
+pub fn render<'a, F>(template: &str, lookup: F) -> String
+where
+    F: Fn(&str) -> Option<Token<'a>>,
+{
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(start) = rest.find('{') {
+        out.push_str(&rest[..start]);
+
+        let end = match rest[start..].find('}') {
+            Some(end) => start + end,
+            None => break,
+        };
+
+        let token = &rest[start + 1..end];
+        let (name, format) = match token.find(':') {
+            Some(idx) => (&token[..idx], Some(&token[idx + 1..])),
+            None => (token, None),
+        };
+
+        match lookup(name) {
+            Some(Token::Text(text)) => out.push_str(&sanitize(text)),
+            Some(Token::Number(Some(num))) => {
+                let width = format.map_or(0, |f| f.len());
+                out.push_str(&format!("{:0width$}", num, width = width));
+            }
+            Some(Token::Number(None)) => {}
+            None => out.push_str(&rest[start..end + 1]),
+        }
+
+        rest = &rest[end + 1..];
+    }
+
+    out.push_str(rest);
+    out.trim().to_string()
+}
+
+/// Replaces characters that can't appear in a path component.
+pub fn sanitize(name: &str) -> String {
+    name.chars()
+        .map(|c| if ILLEGAL_CHARS.contains(&c) { ' ' } else { c })
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Whether `name` can't be used as a path component, since it is empty or
+/// would point at the folder it is in or its parent. Names of only dots are
+/// all rejected, since Windows drops trailing dots.
+fn is_reserved(name: &str) -> bool {
+    name.chars().all(|c| c == '.')
+}
+
+/// `name`, or `fallback` if `name` can't be used as a path component.
+fn or_fallback<F>(name: String, fallback: F) -> String
+where
+    F: FnOnce() -> String,
+{
+    if is_reserved(&name) {
+        fallback()
+    } else {
+        name
+    }
+}
+
+/// Renders the name of a show's folder inside its root folder, which is
+/// named after the show's provider id if its name can't be used.
+pub fn show_folder_name(template: &str, show: &TVShow) -> String {
+    let name = render(template, |token| match token {
+        "Show Name" => Some(Token::Text(&show.name)),
+        _ => None,
+    });
+    or_fallback(name, || sanitize(&format!("{:?} {}", show.provider, show.provider_id)))
+}
+
+/// Renders the name of a season's folder inside its show's folder.
+pub fn season_folder_name(template: &str, season_num: i32) -> String {
+    let name = render(template, |token| match token {
+        "season" => Some(Token::Number(Some(season_num))),
+        _ => None,
+    });
+    or_fallback(name, || format!("Season {:02}", season_num))
+}
+
+/// Renders the name of an episode's file, without its extension.
+pub fn episode_file_name(template: &str, show: &TVShow, episode: &TVShowEpisode) -> String {
+    let name = render(template, |token| match token {
+        "Show Name" => Some(Token::Text(&show.name)),
+        "Episode Title" => Some(Token::Text(&episode.name)),
+        "season" => Some(Token::Number(Some(episode.season_num))),
+        "episode" => Some(Token::Number(episode.num)),
+        _ => None,
+    });
+    or_fallback(name, || {
+        format!("S{:02}E{:02}", episode.season_num, episode.num.unwrap_or(0))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_padded_numbers() {
+        let name = render("{Show Name} - S{season:00}E{episode:000}", |token| match token {
+            "Show Name" => Some(Token::Text("Star Trek: Discovery")),
+            "season" => Some(Token::Number(Some(1))),
+            "episode" => Some(Token::Number(Some(7))),
+            _ => None,
+        });
+
+        assert_eq!(name, "Star Trek Discovery - S01E007");
+    }
+
+    #[test]
+    fn keeps_unknown_tokens() {
+        let name = render("{Show Name} {Unknown}", |_| None);
+        assert_eq!(name, "{Show Name} {Unknown}");
+    }
+
+    #[test]
+    fn never_names_folders_after_themselves_or_their_parent() {
+        for name in &["", ".", "..", "...", " .. ", "/"] {
+            let folder = render("{Show Name}", |_| Some(Token::Text(name)));
+            assert!(is_reserved(&folder), "{:?} rendered as {:?}", name, folder);
+            assert_eq!(or_fallback(folder, || "TVMaze 169".to_string()), "TVMaze 169");
+        }
+
+        assert_eq!(season_folder_name("..", 2), "Season 02");
+        assert_eq!(or_fallback("..hack".to_string(), String::new), "..hack");
+    }
+}
@@ -0,0 +1,390 @@
+//! Renders the NFO files that Kodi and Jellyfin read a library's metadata
+//! from: `tvshow.nfo` in a show's folder, `season.nfo` in each season's folder
+//! and an NFO next to each episode file. Shows that aren't in any online
+//! database are read back from the same files.
+
+use resources::tv_show::{TVMetadataProvider, TVShow, TVShowEpisode, TVShowSeason};
+
+use chrono::Datelike;
+
+const XML_HEADER: &'static str = "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\" ?>\n";
+
+/// Builds an XML document one element per line.
+struct Document {
+    out: String,
+    depth: usize,
+}
+
+impl Document {
+    fn new() -> Document {
+        Document {
+            out: XML_HEADER.to_string(),
+            depth: 0,
+        }
+    }
+
+    fn open(&mut self, name: &str) {
+        self.indent();
+        self.out.push_str(&format!("<{}>\n", name));
+        self.depth += 1;
+    }
+
+    fn close(&mut self, name: &str) {
+        self.depth -= 1;
+        self.indent();
+        self.out.push_str(&format!("</{}>\n", name));
+    }
+
+    fn element(&mut self, name: &str, attrs: &[(&str, &str)], text: &str) {
+        self.indent();
+        self.out.push('<');
+        self.out.push_str(name);
+        for &(key, value) in attrs {
+            self.out.push_str(&format!(" {}=\"{}\"", key, escape(value)));
+        }
+        self.out.push_str(&format!(">{}</{}>\n", escape(text), name));
+    }
+
+    fn text(&mut self, name: &str, text: &str) {
+        self.element(name, &[], text);
+    }
+
+    fn optional<T: ToString>(&mut self, name: &str, value: Option<T>) {
+        if let Some(value) = value {
+            self.text(name, &value.to_string());
+        }
+    }
+
+    fn indent(&mut self) {
+        for _ in 0..self.depth {
+            self.out.push_str("  ");
+        }
+    }
+}
+
+/// Renders `tvshow.nfo`. With `art_urls` set, the remote poster and fanart are
+/// included so a media center can fetch them itself.
+pub fn show_nfo(show: &TVShow, art_urls: bool) -> String {
+    let mut doc = Document::new();
+    doc.open("tvshow");
+    doc.text("title", &show.name);
+    doc.optional("plot", show.summary.as_ref().map(|s| plain_text(s)));
+    doc.optional("runtime", show.runtime);
+    doc.optional("premiered", show.premiered);
+    doc.optional("year", show.premiered.map(|d| d.year()));
+    for genre in show.genres.iter() {
+        doc.text("genre", genre);
+    }
+
+    let id_type = provider_id_type(show.provider);
+    if let Some(kind) = id_type {
+        doc.element("uniqueid", &[("type", kind), ("default", "true")], &show.provider_id);
+    }
+    if let Some(ref imdb) = show.external_ids.imdb {
+        doc.element("uniqueid", &[("type", "imdb")], imdb);
+    }
+    if let (Some(thetvdb), false) = (show.external_ids.thetvdb, id_type == Some("tvdb")) {
+        doc.element("uniqueid", &[("type", "tvdb")], &thetvdb.to_string());
+    }
+    if let Some(tvrage) = show.external_ids.tvrage {
+        doc.element("uniqueid", &[("type", "tvrage")], &tvrage.to_string());
+    }
+
+    if art_urls {
+        if let Some(ref poster) = show.cover_img {
+            doc.element("thumb", &[("aspect", "poster")], poster);
+        }
+        if let Some(ref fanart) = show.fanart_img {
+            doc.open("fanart");
+            doc.text("thumb", fanart);
+            doc.close("fanart");
+        }
+    }
+
+    doc.close("tvshow");
+    doc.out
+}
+
+/// Renders `season.nfo`.
+pub fn season_nfo(season: &TVShowSeason, art_urls: bool) -> String {
+    let mut doc = Document::new();
+    doc.open("season");
+    if season.name.is_empty() {
+        doc.text("title", &format!("Season {}", season.num));
+    } else {
+        doc.text("title", &season.name);
+    }
+    doc.text("seasonnumber", &season.num.to_string());
+    doc.optional("plot", season.summary.as_ref().map(|s| plain_text(s)));
+    if art_urls {
+        if let Some(ref poster) = season.cover_img {
+            doc.element("thumb", &[("aspect", "poster")], poster);
+        }
+    }
+    doc.close("season");
+    doc.out
+}
+
+/// Renders the NFO for an episode file. A file holding several episodes gets
+/// one `episodedetails` element per episode.
+pub fn episode_nfo(show: &TVShow, episodes: &[&TVShowEpisode], art_urls: bool) -> String {
+    let mut doc = Document::new();
+
+    for episode in episodes {
+        doc.open("episodedetails");
+        doc.text("title", &episode.name);
+        doc.text("showtitle", &show.name);
+        doc.text("season", &episode.season_num.to_string());
+        doc.optional("episode", episode.num);
+        doc.optional("plot", episode.summary.as_ref().map(|s| plain_text(s)));
+        doc.optional("runtime", episode.runtime);
+        doc.optional("aired", episode.airdate);
+        if let Some(kind) = provider_id_type(show.provider) {
+            doc.element(
+                "uniqueid",
+                &[("type", kind), ("default", "true")],
+                &episode.provider_id,
+            );
+        }
+        if art_urls {
+            doc.optional("thumb", episode.cover_img.as_ref());
+        }
+        doc.close("episodedetails");
+    }
+
+    doc.out
+}
+
+/// The `uniqueid` type media centers know a provider's ids by.
+fn provider_id_type(provider: TVMetadataProvider) -> Option<&'static str> {
+    match provider {
+        TVMetadataProvider::TVMaze => Some("tvmaze"),
+        TVMetadataProvider::TheTVDB => Some("tvdb"),
+        // The id of a local show is only the folder it was read from.
+        TVMetadataProvider::Local => None,
+    }
+}
+
+/// Reduces the HTML summaries TVmaze returns to plain text.
+pub fn plain_text(html: &str) -> String {
+    let mut text = String::with_capacity(html.len());
+    let mut in_tag = false;
+
+    for c in html.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' if in_tag => in_tag = false,
+            c if !in_tag => text.push(c),
+            _ => {}
+        }
+    }
+
+    text.replace("&nbsp;", " ")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&")
+        .trim()
+        .to_string()
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// An element found in an NFO file.
+#[derive(Clone, Copy, Debug)]
+pub struct Element<'a> {
+    attrs: &'a str,
+    body: &'a str,
+}
+
+impl<'a> Element<'a> {
+    /// The value of the attribute `name`, if it's set.
+    pub fn attr(&self, name: &str) -> Option<&'a str> {
+        let pattern = format!("{}=\"", name);
+        let mut rest = self.attrs;
+
+        while let Some(idx) = rest.find(&pattern) {
+            let preceded_by_space = rest[..idx].ends_with(char::is_whitespace);
+            rest = &rest[idx + pattern.len()..];
+            if preceded_by_space {
+                return rest.find('"').map(|end| &rest[..end]);
+            }
+        }
+
+        None
+    }
+
+    /// The raw contents of the element, including any child elements.
+    pub fn body(&self) -> &'a str {
+        self.body
+    }
+
+    /// The contents of the element as plain text.
+    pub fn text(&self) -> String {
+        plain_text(self.body)
+    }
+}
+
+/// Finds every `tag` element in `xml`. These files are small and loosely
+/// written by hand as often as not, so this only looks for matching opening
+/// and closing tags rather than parsing the document properly.
+pub fn elements<'a>(xml: &'a str, tag: &str) -> Vec<Element<'a>> {
+    let open = format!("<{}", tag);
+    let close = format!("</{}>", tag);
+    let mut found = Vec::new();
+    let mut rest = xml;
+
+    while let Some(start) = rest.find(&open) {
+        rest = &rest[start + open.len()..];
+        let attrs_end = match rest.find('>') {
+            Some(attrs_end) => attrs_end,
+            None => break,
+        };
+
+        // `<tag` might only be the start of a longer name.
+        if !rest.starts_with(|c: char| c == '>' || c == '/' || c.is_whitespace()) {
+            continue;
+        }
+
+        if rest[..attrs_end].ends_with('/') {
+            found.push(Element {
+                attrs: &rest[..attrs_end - 1],
+                body: "",
+            });
+            rest = &rest[attrs_end + 1..];
+            continue;
+        }
+
+        let body = &rest[attrs_end + 1..];
+        let end = match body.find(&close) {
+            Some(end) => end,
+            None => break,
+        };
+        found.push(Element {
+            attrs: &rest[..attrs_end],
+            body: &body[..end],
+        });
+        rest = &body[end + close.len()..];
+    }
+
+    found
+}
+
+/// The text of the first `tag` element in `xml`, if it isn't empty.
+pub fn element_text(xml: &str, tag: &str) -> Option<String> {
+    elements(xml, tag)
+        .first()
+        .map(|e| e.text())
+        .filter(|text| !text.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use resources::quality::DEFAULT_QUALITY_PROFILE_ID;
+    use resources::tv_show::{ExternalIds, MetadataSources};
+
+    use chrono::NaiveDate;
+
+    fn show() -> TVShow {
+        TVShow {
+            id: 1,
+            name: "Law & Order".to_string(),
+            summary: Some("<p><b>Law &amp; Order</b> follows detectives.</p>".to_string()),
+            cover_img: Some("http://static.tvmaze.com/poster.jpg".to_string()),
+            fanart_img: None,
+            genres: vec!["Crime".to_string(), "Drama".to_string()],
+            runtime: Some(60),
+            premiered: NaiveDate::from_ymd_opt(1990, 9, 13),
+            external_ids: ExternalIds {
+                imdb: Some("tt0098844".to_string()),
+                thetvdb: Some(72368),
+                tvrage: None,
+            },
+            provider: TVMetadataProvider::TVMaze,
+            provider_id: "3".to_string(),
+            provider_url: String::new(),
+            provider_updated: None,
+            monitored: true,
+            quality_profile_id: DEFAULT_QUALITY_PROFILE_ID,
+            root_folder_id: None,
+            path: None,
+            aliases: vec![],
+            metadata_sources: MetadataSources::new(),
+            seasons: None,
+        }
+    }
+
+    fn episode(num: i32, name: &str) -> TVShowEpisode {
+        TVShowEpisode {
+            id: num,
+            show_id: 1,
+            season_id: 1,
+            num: Some(num),
+            season_num: 1,
+            name: name.to_string(),
+            summary: None,
+            cover_img: None,
+            runtime: Some(60),
+            airdate: NaiveDate::from_ymd_opt(1990, 9, 13),
+            monitored: true,
+            provider_id: (100 + num).to_string(),
+            provider_url: String::new(),
+            metadata_sources: MetadataSources::new(),
+        }
+    }
+
+    #[test]
+    fn renders_show_details_and_ids() {
+        let nfo = show_nfo(&show(), true);
+
+        assert!(nfo.starts_with(XML_HEADER));
+        assert!(nfo.contains("<title>Law &amp; Order</title>"));
+        assert!(nfo.contains("<plot>Law &amp; Order follows detectives.</plot>"));
+        assert!(nfo.contains("<year>1990</year>"));
+        assert!(nfo.contains("<genre>Crime</genre>\n  <genre>Drama</genre>"));
+        assert!(nfo.contains("<uniqueid type=\"tvmaze\" default=\"true\">3</uniqueid>"));
+        assert!(nfo.contains("<uniqueid type=\"imdb\">tt0098844</uniqueid>"));
+        assert!(nfo.contains("<uniqueid type=\"tvdb\">72368</uniqueid>"));
+        assert!(nfo.contains("<thumb aspect=\"poster\">"));
+        assert!(!show_nfo(&show(), false).contains("<thumb"));
+    }
+
+    #[test]
+    fn renders_multi_episode_files() {
+        let first = episode(1, "Prescription for Death");
+        let second = episode(2, "Subterranean Homeboy Blues");
+        let nfo = episode_nfo(&show(), &[&first, &second], false);
+
+        assert_eq!(nfo.matches("<episodedetails>").count(), 2);
+        assert!(nfo.contains("<aired>1990-09-13</aired>"));
+        assert!(nfo.contains("<uniqueid type=\"tvmaze\" default=\"true\">102</uniqueid>"));
+    }
+
+    #[test]
+    fn reads_elements_back() {
+        let xml = "<tvshow><title>Law &amp; Order</title><titles/>\
+                   <uniqueid type=\"tvdb\" default=\"true\">72368</uniqueid>\
+                   <uniqueid type=\"imdb\">tt0098844</uniqueid><thumb/></tvshow>";
+
+        assert_eq!(element_text(xml, "title"), Some("Law & Order".to_string()));
+        assert_eq!(element_text(xml, "thumb"), None);
+        let ids: Vec<_> = elements(xml, "uniqueid")
+            .iter()
+            .map(|e| (e.attr("type"), e.attr("default"), e.text()))
+            .collect();
+        assert_eq!(
+            ids,
+            vec![
+                (Some("tvdb"), Some("true"), "72368".to_string()),
+                (Some("imdb"), None, "tt0098844".to_string()),
+            ]
+        );
+    }
+}
@@ -0,0 +1,25 @@
+use error::WebError;
+use providers::indexer::IndexerRelease;
+
+use futures::Future;
+
+/// A download that the download client has finished.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CompletedDownload {
+    pub id: String,
+    pub name: String,
+    /// The file or folder the download was saved to.
+    pub path: String,
+}
+
+/// A client that releases can be sent to, such as Deluge.
+pub trait DownloadProvider {
+    fn name(&self) -> &str;
+
+    /// Queues a release for download, resolving to the id the download
+    /// client assigned to it.
+    fn add(&self, release: &IndexerRelease) -> Box<Future<Item = String, Error = WebError>>;
+
+    /// Lists the downloads that have finished and are ready to be imported.
+    fn completed(&self) -> Box<Future<Item = Vec<CompletedDownload>, Error = WebError>>;
+}
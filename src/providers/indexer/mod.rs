@@ -0,0 +1,42 @@
+use error::WebError;
+use resources::wanted::WantedEpisode;
+
+use futures::Future;
+
+/// A release as reported by an indexer, before its name has been parsed.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct IndexerRelease {
+    pub title: String,
+    pub indexer: String,
+    pub download_url: String,
+    pub size: Option<u64>,
+    pub seeders: Option<u32>,
+}
+
+#[derive(Clone, Debug)]
+pub struct IndexerQuery {
+    pub show_name: String,
+    pub season: i32,
+    pub episode: Option<i32>,
+}
+
+impl<'a> From<&'a WantedEpisode> for IndexerQuery {
+    fn from(episode: &'a WantedEpisode) -> Self {
+        IndexerQuery {
+            show_name: episode.show_name.clone(),
+            season: episode.season_num,
+            episode: episode.num,
+        }
+    }
+}
+
+/// A source of releases, such as a Torznab or Newznab endpoint. Any number
+/// of indexers can be configured at the same time.
+pub trait IndexerProvider {
+    fn name(&self) -> &str;
+
+    fn search(
+        &self,
+        query: &IndexerQuery,
+    ) -> Box<Future<Item = Vec<IndexerRelease>, Error = WebError>>;
+}
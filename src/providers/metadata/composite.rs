@@ -0,0 +1,674 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use super::{MetadataProvider, UpdatePeriod};
+use error::WebError;
+use resources::person::ShowCredits;
+use resources::tv_show::*;
+
+use futures::{future, Future, Stream};
+
+/// Loads shows from a primary provider and fills the fields it leaves empty
+/// from other providers, which are tried in order. The filled fields are
+/// recorded in the records' `metadata_sources`.
+///
+/// The other providers' shows are matched by the external ids of the primary
+/// show, their seasons and episodes by their numbers. A fallback that fails
+/// or doesn't know the show leaves the gaps as they are, rather than failing
+/// the whole load.
+pub struct CompositeProvider {
+    primary: Rc<MetadataProvider>,
+    fallbacks: Vec<Fallback>,
+}
+
+#[derive(Clone)]
+struct Fallback {
+    kind: TVMetadataProvider,
+    provider: Rc<MetadataProvider>,
+    /// The id of the primary's last loaded show and the id of this
+    /// provider's version of it, or `None` if it has no matching show. Kept so
+    /// that the seasons and episodes of the same load don't have to be matched
+    /// again, and replaced by the next show so that no match goes stale.
+    last_match: Rc<RefCell<Option<(String, Option<String>)>>>,
+}
+
+impl CompositeProvider {
+    pub fn new(
+        primary: Rc<MetadataProvider>,
+        fallbacks: Vec<(TVMetadataProvider, Rc<MetadataProvider>)>,
+    ) -> CompositeProvider {
+        CompositeProvider {
+            primary,
+            fallbacks: fallbacks
+                .into_iter()
+                .map(|(kind, provider)| Fallback {
+                    kind,
+                    provider,
+                    last_match: Rc::new(RefCell::new(None)),
+                })
+                .collect(),
+        }
+    }
+}
+
+impl MetadataProvider for CompositeProvider {
+    fn get_show(&self, id: &str) -> Box<Future<Item = TVShow, Error = WebError>> {
+        let fallbacks = self.fallbacks.clone();
+        Box::new(
+            self.primary
+                .get_show(id)
+                .and_then(move |show| fill_show(fallbacks, show)),
+        )
+    }
+
+    fn get_show_seasons(
+        &self,
+        id: &str,
+    ) -> Box<Future<Item = Vec<TVShowSeason>, Error = WebError>> {
+        let mut fut = self.primary.get_show_seasons(id);
+
+        for fallback in self.fallbacks.iter().cloned() {
+            let primary = self.primary.clone();
+            let id = id.to_string();
+
+            fut = Box::new(fut.and_then(move |mut seasons| {
+                fallback.get_seasons(primary, &id).map(move |others| {
+                    for season in seasons.iter_mut() {
+                        let num = season.num;
+                        if let Some(other) = others.iter().find(|o| o.num == num) {
+                            merge_season(season, other, fallback.kind);
+                        }
+                    }
+                    seasons
+                })
+            }));
+        }
+
+        fut
+    }
+
+    fn get_season_episodes(
+        &self,
+        show_id: &str,
+        season_id: &str,
+    ) -> Box<Future<Item = Vec<TVShowEpisode>, Error = WebError>> {
+        let mut fut = self.primary.get_season_episodes(show_id, season_id);
+
+        for fallback in self.fallbacks.iter().cloned() {
+            let primary = self.primary.clone();
+            let show_id = show_id.to_string();
+
+            fut = Box::new(fut.and_then(move |mut episodes| {
+                let season_num = episodes.first().map(|e| e.season_num);
+                let season_num = match season_num {
+                    Some(num) => num,
+                    None => {
+                        return Box::new(future::ok(episodes)) as Box<Future<Item = _, Error = _>>
+                    }
+                };
+
+                Box::new(fallback.get_episodes(primary, &show_id, season_num).map(
+                    move |others| {
+                        for episode in episodes.iter_mut() {
+                            let num = episode.num;
+                            let other = others
+                                .iter()
+                                .find(|o| num.is_some() && o.num == num);
+                            if let Some(other) = other {
+                                merge_episode(episode, other, fallback.kind);
+                            }
+                        }
+                        episodes
+                    },
+                ))
+            }));
+        }
+
+        fut
+    }
+
+    /// Only the primary loads the show at once. The fallbacks still fill in
+    /// its seasons and episodes one season at a time.
+    fn get_full_show(&self, id: &str) -> Box<Future<Item = Option<TVShow>, Error = WebError>> {
+        let fallbacks = self.fallbacks.clone();
+        let primary = self.primary.clone();
+
+        Box::new(self.primary.get_full_show(id).and_then(move |show| match show {
+            Some(show) => Box::new(
+                fill_show(fallbacks.clone(), show)
+                    .and_then(move |show| fill_seasons(fallbacks, primary, show))
+                    .map(Some),
+            ) as Box<Future<Item = _, Error = _>>,
+            None => Box::new(future::ok(None)),
+        }))
+    }
+
+    /// Search results are left as they are, since they're only used to pick a
+    /// show that is loaded in full afterwards.
+    fn search(&self, q: &str) -> Box<Future<Item = Vec<ShowSearchResult>, Error = WebError>> {
+        self.primary.search(q)
+    }
+
+    fn lookup_show(
+        &self,
+        id: &ExternalId,
+    ) -> Box<Future<Item = Option<TVShow>, Error = WebError>> {
+        let fallbacks = self.fallbacks.clone();
+        Box::new(self.primary.lookup_show(id).and_then(move |show| match show {
+            Some(show) => {
+                Box::new(fill_show(fallbacks, show).map(Some)) as Box<Future<Item = _, Error = _>>
+            }
+            None => Box::new(future::ok(None)),
+        }))
+    }
+
+    /// Credits aren't merged, the first provider that lists anyone is used.
+    fn get_show_credits(&self, id: &str) -> Box<Future<Item = ShowCredits, Error = WebError>> {
+        let mut fut = self.primary.get_show_credits(id);
+
+        for fallback in self.fallbacks.iter().cloned() {
+            let primary = self.primary.clone();
+            let id = id.to_string();
+
+            fut = Box::new(fut.and_then(move |credits| {
+                if credits.is_empty() {
+                    fallback.get_credits(primary, &id)
+                } else {
+                    Box::new(future::ok(credits))
+                }
+            }));
+        }
+
+        fut
+    }
+
+    /// Shows are refreshed from all providers at once, so only the primary's
+    /// changes are followed.
+    fn get_show_updates(
+        &self,
+        since: UpdatePeriod,
+    ) -> Box<Future<Item = HashMap<String, i64>, Error = WebError>> {
+        self.primary.get_show_updates(since)
+    }
+
+    /// The catalog only lists the primary's shows, as searches do.
+    fn get_show_index(
+        &self,
+        first_page: u64,
+    ) -> Box<Stream<Item = (u64, Vec<ShowSearchResult>), Error = WebError>> {
+        self.primary.get_show_index(first_page)
+    }
+
+    fn get_show_index_page(
+        &self,
+        page: u64,
+    ) -> Box<Future<Item = Option<Vec<ShowSearchResult>>, Error = WebError>> {
+        self.primary.get_show_index_page(page)
+    }
+}
+
+impl Fallback {
+    /// Finds this provider's version of a show of the primary provider by its
+    /// TheTVDB id, then by its IMDb id.
+    fn find_show(&self, show: &TVShow) -> Box<Future<Item = Option<TVShow>, Error = WebError>> {
+        let mut ids = vec![];
+        if let Some(id) = show.external_ids.thetvdb {
+            ids.push(ExternalId::TheTVDB(id as u64));
+        }
+        if let Some(ref id) = show.external_ids.imdb {
+            ids.push(ExternalId::IMDB(id.clone()));
+        }
+
+        let mut fut: Box<Future<Item = Option<TVShow>, Error = WebError>> =
+            Box::new(future::ok(None));
+        for id in ids.into_iter() {
+            let provider = self.provider.clone();
+            fut = Box::new(fut.and_then(move |found| match found {
+                Some(found) => {
+                    Box::new(future::ok(Some(found))) as Box<Future<Item = _, Error = _>>
+                }
+                None => Box::new(provider.lookup_show(&id).or_else(|_| Ok(None))),
+            }));
+        }
+
+        let last_match = self.last_match.clone();
+        let show_id = show.provider_id.clone();
+        Box::new(fut.map(move |found| {
+            let found_id = found.as_ref().map(|s| s.provider_id.clone());
+            *last_match.borrow_mut() = Some((show_id, found_id));
+            found
+        }))
+    }
+
+    /// The id of this provider's version of the primary's show `id`, using
+    /// the match made when the show was loaded if it was the last one.
+    fn get_show_id(
+        &self,
+        primary: Rc<MetadataProvider>,
+        id: &str,
+    ) -> Box<Future<Item = Option<String>, Error = WebError>> {
+        if let Some((ref show_id, ref found)) = *self.last_match.borrow() {
+            if show_id == id {
+                return Box::new(future::ok(found.clone()));
+            }
+        }
+
+        let fallback = self.clone();
+        Box::new(
+            primary
+                .get_show(id)
+                .and_then(move |show| fallback.find_show(&show))
+                .map(|found| found.map(|s| s.provider_id)),
+        )
+    }
+
+    fn get_seasons(
+        &self,
+        primary: Rc<MetadataProvider>,
+        id: &str,
+    ) -> Box<Future<Item = Vec<TVShowSeason>, Error = WebError>> {
+        let provider = self.provider.clone();
+
+        Box::new(self.get_show_id(primary, id).and_then(move |found| match found {
+            Some(id) => Box::new(provider.get_show_seasons(&id).or_else(|_| Ok(vec![])))
+                as Box<Future<Item = _, Error = _>>,
+            None => Box::new(future::ok(vec![])),
+        }))
+    }
+
+    fn get_credits(
+        &self,
+        primary: Rc<MetadataProvider>,
+        id: &str,
+    ) -> Box<Future<Item = ShowCredits, Error = WebError>> {
+        let provider = self.provider.clone();
+
+        Box::new(self.get_show_id(primary, id).and_then(move |found| match found {
+            Some(id) => Box::new(
+                provider
+                    .get_show_credits(&id)
+                    .or_else(|_| Ok(ShowCredits::default())),
+            ) as Box<Future<Item = _, Error = _>>,
+            None => Box::new(future::ok(ShowCredits::default())),
+        }))
+    }
+
+    fn get_episodes(
+        &self,
+        primary: Rc<MetadataProvider>,
+        show_id: &str,
+        season_num: i32,
+    ) -> Box<Future<Item = Vec<TVShowEpisode>, Error = WebError>> {
+        let provider = self.provider.clone();
+
+        Box::new(self.get_show_id(primary, show_id).and_then(move |found| {
+            let show_id = match found {
+                Some(id) => id,
+                None => return Box::new(future::ok(vec![])) as Box<Future<Item = _, Error = _>>,
+            };
+
+            Box::new(
+                provider
+                    .get_show_seasons(&show_id)
+                    .and_then(move |seasons| {
+                        match seasons.into_iter().find(|s| s.num == season_num) {
+                            Some(season) => {
+                                provider.get_season_episodes(&show_id, &season.provider_id)
+                            }
+                            None => Box::new(future::ok(vec![])),
+                        }
+                    })
+                    .or_else(|_| Ok(vec![])),
+            )
+        }))
+    }
+}
+
+fn fill_show(
+    fallbacks: Vec<Fallback>,
+    show: TVShow,
+) -> Box<Future<Item = TVShow, Error = WebError>> {
+    let mut fut: Box<Future<Item = TVShow, Error = WebError>> = Box::new(future::ok(show));
+
+    for fallback in fallbacks.into_iter() {
+        fut = Box::new(fut.and_then(move |mut show| {
+            fallback.find_show(&show).map(move |other| {
+                if let Some(other) = other {
+                    merge_show(&mut show, &other, fallback.kind);
+                }
+                show
+            })
+        }));
+    }
+
+    fut
+}
+
+/// Fills the seasons and episodes of a show that was loaded in full, the same
+/// way as `get_show_seasons` and `get_season_episodes` do.
+fn fill_seasons(
+    fallbacks: Vec<Fallback>,
+    primary: Rc<MetadataProvider>,
+    show: TVShow,
+) -> Box<Future<Item = TVShow, Error = WebError>> {
+    let mut fut: Box<Future<Item = TVShow, Error = WebError>> = Box::new(future::ok(show));
+
+    for fallback in fallbacks.into_iter() {
+        let primary = primary.clone();
+
+        fut = Box::new(fut.and_then(move |mut show| {
+            let id = show.provider_id.clone();
+            fallback
+                .get_seasons(primary.clone(), &id)
+                .and_then(move |others| {
+                    let mut matched = vec![];
+                    for season in show.seasons.iter_mut().flat_map(|s| s.iter_mut()) {
+                        let num = season.num;
+                        if let Some(other) = others.iter().find(|o| o.num == num) {
+                            merge_season(season, other, fallback.kind);
+                            matched.push(num);
+                        }
+                    }
+
+                    let mut fut: Box<Future<Item = TVShow, Error = WebError>> =
+                        Box::new(future::ok(show));
+                    for num in matched.into_iter() {
+                        let fallback = fallback.clone();
+                        let primary = primary.clone();
+                        let id = id.clone();
+
+                        fut = Box::new(fut.and_then(move |mut show| {
+                            fallback.get_episodes(primary, &id, num).map(move |others| {
+                                merge_season_episodes(&mut show, num, &others, fallback.kind);
+                                show
+                            })
+                        }));
+                    }
+                    fut
+                })
+        }));
+    }
+
+    fut
+}
+
+fn merge_season_episodes(
+    show: &mut TVShow,
+    season_num: i32,
+    others: &[TVShowEpisode],
+    kind: TVMetadataProvider,
+) {
+    let episodes = show
+        .seasons
+        .iter_mut()
+        .flat_map(|s| s.iter_mut())
+        .find(|s| s.num == season_num)
+        .and_then(|s| s.episodes.as_mut());
+
+    for episode in episodes.into_iter().flat_map(|e| e.iter_mut()) {
+        let num = episode.num;
+        if let Some(other) = others.iter().find(|o| num.is_some() && o.num == num) {
+            merge_episode(episode, other, kind);
+        }
+    }
+}
+
+/// Sets an empty field to the other provider's value and records where it
+/// came from.
+fn fill<T: Clone>(
+    field: &mut Option<T>,
+    other: &Option<T>,
+    name: &str,
+    kind: TVMetadataProvider,
+    sources: &mut MetadataSources,
+) {
+    if field.is_none() && other.is_some() {
+        *field = other.clone();
+        sources.insert(name.to_string(), kind);
+    }
+}
+
+fn merge_show(show: &mut TVShow, other: &TVShow, kind: TVMetadataProvider) {
+    // Providers know the show under different titles, so aliases are pooled.
+    show.add_aliases(other.aliases.iter().cloned());
+
+    let sources = &mut show.metadata_sources;
+    fill(&mut show.summary, &other.summary, "summary", kind, sources);
+    fill(&mut show.cover_img, &other.cover_img, "cover_img", kind, sources);
+    fill(&mut show.fanart_img, &other.fanart_img, "fanart_img", kind, sources);
+    fill(&mut show.runtime, &other.runtime, "runtime", kind, sources);
+    fill(&mut show.premiered, &other.premiered, "premiered", kind, sources);
+}
+
+fn merge_season(season: &mut TVShowSeason, other: &TVShowSeason, kind: TVMetadataProvider) {
+    let sources = &mut season.metadata_sources;
+    fill(&mut season.summary, &other.summary, "summary", kind, sources);
+    fill(&mut season.cover_img, &other.cover_img, "cover_img", kind, sources);
+}
+
+fn merge_episode(episode: &mut TVShowEpisode, other: &TVShowEpisode, kind: TVMetadataProvider) {
+    let sources = &mut episode.metadata_sources;
+    fill(&mut episode.summary, &other.summary, "summary", kind, sources);
+    fill(&mut episode.cover_img, &other.cover_img, "cover_img", kind, sources);
+    fill(&mut episode.runtime, &other.runtime, "runtime", kind, sources);
+    fill(&mut episode.airdate, &other.airdate, "airdate", kind, sources);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::cell::Cell;
+
+    use resources::quality::DEFAULT_QUALITY_PROFILE_ID;
+
+    use chrono::NaiveDate;
+
+    /// Serves a single show, found by any id or by its TheTVDB id, and
+    /// counts the lookups by external id.
+    struct StubProvider {
+        show: TVShow,
+        lookups: Rc<Cell<usize>>,
+    }
+
+    impl StubProvider {
+        fn new(show: TVShow) -> StubProvider {
+            StubProvider {
+                show,
+                lookups: Rc::new(Cell::new(0)),
+            }
+        }
+    }
+
+    impl MetadataProvider for StubProvider {
+        fn get_show(&self, id: &str) -> Box<Future<Item = TVShow, Error = WebError>> {
+            Box::new(future::ok(TVShow {
+                provider_id: id.to_string(),
+                seasons: None,
+                ..self.show.clone()
+            }))
+        }
+
+        fn get_show_seasons(
+            &self,
+            _id: &str,
+        ) -> Box<Future<Item = Vec<TVShowSeason>, Error = WebError>> {
+            let seasons = self.show.seasons.clone().unwrap_or_default();
+            Box::new(future::ok(
+                seasons
+                    .into_iter()
+                    .map(|s| TVShowSeason { episodes: None, ..s })
+                    .collect(),
+            ))
+        }
+
+        fn get_season_episodes(
+            &self,
+            _show_id: &str,
+            season_id: &str,
+        ) -> Box<Future<Item = Vec<TVShowEpisode>, Error = WebError>> {
+            let seasons = self.show.seasons.clone().unwrap_or_default();
+            Box::new(future::ok(
+                seasons
+                    .into_iter()
+                    .find(|s| s.provider_id == season_id)
+                    .and_then(|s| s.episodes)
+                    .unwrap_or_default(),
+            ))
+        }
+
+        fn search(
+            &self,
+            _q: &str,
+        ) -> Box<Future<Item = Vec<ShowSearchResult>, Error = WebError>> {
+            Box::new(future::ok(vec![]))
+        }
+
+        fn lookup_show(
+            &self,
+            id: &ExternalId,
+        ) -> Box<Future<Item = Option<TVShow>, Error = WebError>> {
+            self.lookups.set(self.lookups.get() + 1);
+            let found = match *id {
+                ExternalId::TheTVDB(id) => self.show.external_ids.thetvdb == Some(id as i64),
+                _ => false,
+            };
+            Box::new(future::ok(if found { Some(self.show.clone()) } else { None }))
+        }
+    }
+
+    fn episode(num: i32, summary: Option<&str>, airdate: Option<NaiveDate>) -> TVShowEpisode {
+        TVShowEpisode {
+            id: -1,
+            show_id: -1,
+            season_id: -1,
+            num: Some(num),
+            season_num: 1,
+            name: format!("Episode {}", num),
+            summary: summary.map(|s| s.to_string()),
+            cover_img: None,
+            runtime: None,
+            airdate,
+            monitored: true,
+            provider_id: num.to_string(),
+            provider_url: String::new(),
+            metadata_sources: MetadataSources::new(),
+        }
+    }
+
+    fn show(
+        provider: TVMetadataProvider,
+        summary: Option<&str>,
+        cover_img: Option<&str>,
+        aliases: Vec<&str>,
+        episodes: Vec<TVShowEpisode>,
+    ) -> TVShow {
+        TVShow {
+            id: -1,
+            name: "Show Name".to_string(),
+            summary: summary.map(|s| s.to_string()),
+            cover_img: cover_img.map(|s| s.to_string()),
+            fanart_img: None,
+            genres: vec![],
+            runtime: None,
+            premiered: None,
+            external_ids: ExternalIds {
+                imdb: None,
+                thetvdb: Some(81189),
+                tvrage: None,
+            },
+            provider,
+            provider_id: "169".to_string(),
+            provider_url: String::new(),
+            provider_updated: None,
+            monitored: true,
+            quality_profile_id: DEFAULT_QUALITY_PROFILE_ID,
+            root_folder_id: None,
+            path: None,
+            aliases: aliases.into_iter().map(|a| a.to_string()).collect(),
+            metadata_sources: MetadataSources::new(),
+            seasons: Some(vec![TVShowSeason {
+                id: -1,
+                show_id: -1,
+                num: 1,
+                name: "Season 1".to_string(),
+                summary: None,
+                cover_img: cover_img.map(|s| s.to_string()),
+                provider_id: "s1".to_string(),
+                provider_url: String::new(),
+                metadata_sources: MetadataSources::new(),
+                episodes: Some(episodes),
+            }]),
+        }
+    }
+
+    #[test]
+    fn fills_gaps_from_fallbacks_and_records_their_sources() {
+        let aired = NaiveDate::from_ymd_opt(2008, 1, 20);
+        let primary = show(
+            TVMetadataProvider::TVMaze,
+            Some("Primary summary"),
+            None,
+            vec!["Nom de la série"],
+            vec![episode(1, None, None), episode(2, Some("Primary"), aired)],
+        );
+        let fallback = show(
+            TVMetadataProvider::TheTVDB,
+            Some("Fallback summary"),
+            Some("http://img/poster.jpg"),
+            vec!["Show Name", "Nom de la série", "Serienname"],
+            vec![episode(1, Some("Fallback"), aired), episode(2, Some("Fallback"), None)],
+        );
+        let provider = CompositeProvider::new(
+            Rc::new(StubProvider::new(primary)),
+            vec![(
+                TVMetadataProvider::TheTVDB,
+                Rc::new(StubProvider::new(fallback)),
+            )],
+        );
+
+        let show = provider.get_show("169").wait().unwrap();
+        assert_eq!(show.summary, Some("Primary summary".to_string()));
+        assert_eq!(show.cover_img, Some("http://img/poster.jpg".to_string()));
+        assert_eq!(
+            show.metadata_sources.get("cover_img"),
+            Some(&TVMetadataProvider::TheTVDB)
+        );
+        assert!(show.metadata_sources.get("summary").is_none());
+        assert_eq!(show.aliases, vec!["Nom de la série", "Serienname"]);
+
+        let seasons = provider.get_show_seasons("169").wait().unwrap();
+        assert_eq!(seasons[0].cover_img, Some("http://img/poster.jpg".to_string()));
+
+        let episodes = provider.get_season_episodes("169", "s1").wait().unwrap();
+        assert_eq!(episodes[0].summary, Some("Fallback".to_string()));
+        assert_eq!(episodes[0].airdate, aired);
+        assert_eq!(episodes[0].metadata_sources.len(), 2);
+        assert_eq!(episodes[1].summary, Some("Primary".to_string()));
+        assert!(episodes[1].metadata_sources.is_empty());
+    }
+
+    #[test]
+    fn only_keeps_the_match_of_the_last_loaded_show() {
+        let primary = show(TVMetadataProvider::TVMaze, None, None, vec![], vec![]);
+        let mut fallback = show(TVMetadataProvider::TheTVDB, None, None, vec![], vec![]);
+        fallback.external_ids.thetvdb = Some(1);
+        let fallback = StubProvider::new(fallback);
+        let lookups = fallback.lookups.clone();
+        let provider = CompositeProvider::new(
+            Rc::new(StubProvider::new(primary)),
+            vec![(TVMetadataProvider::TheTVDB, Rc::new(fallback))],
+        );
+
+        // The show has no match, which holds for the rest of its load.
+        provider.get_show("169").wait().unwrap();
+        provider.get_show_seasons("169").wait().unwrap();
+        assert_eq!(lookups.get(), 1);
+
+        // Loading another show drops it, so the first is matched again.
+        provider.get_show("170").wait().unwrap();
+        provider.get_show_seasons("169").wait().unwrap();
+        assert_eq!(lookups.get(), 3);
+    }
+}
@@ -0,0 +1,384 @@
+//! Reads shows that aren't in any online database from the NFO files in their
+//! folders. A local show's provider id is the path of its folder, which holds
+//! `tvshow.nfo` and the episode NFO files, either directly or in season
+//! folders.
+
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use super::{MetadataProvider, ToUnify};
+use error::WebError;
+use nfo::{element_text, elements};
+use resources::quality::DEFAULT_QUALITY_PROFILE_ID;
+use resources::tv_show::*;
+
+use chrono::NaiveDate;
+use futures::{future, Future};
+
+/// A show's folder and the contents of its `tvshow.nfo`.
+pub struct LocalShow {
+    folder: PathBuf,
+    nfo: String,
+}
+
+/// A season with episode NFO files, along with its `season.nfo` if it has one.
+pub struct LocalSeason {
+    num: i32,
+    nfo: Option<String>,
+}
+
+/// An `episodedetails` element from an episode's NFO file.
+pub struct LocalEpisode {
+    season_num: i32,
+    num: i32,
+    details: String,
+}
+
+#[derive(Clone)]
+pub struct LocalProvider;
+
+impl LocalProvider {
+    pub fn new() -> LocalProvider {
+        LocalProvider
+    }
+
+    fn read_show(&self, folder: &str) -> Result<LocalShow, WebError> {
+        let folder = PathBuf::from(folder);
+        let nfo = fs::read_to_string(folder.join("tvshow.nfo")).map_err(|e| match e.kind() {
+            io::ErrorKind::NotFound => {
+                WebError::InvalidRequest(format!("No tvshow.nfo found in {}", folder.display()))
+            }
+            _ => WebError::from(e),
+        })?;
+
+        Ok(LocalShow { folder, nfo })
+    }
+
+    fn read_seasons(&self, folder: &str) -> Result<Vec<LocalSeason>, WebError> {
+        let mut nums: Vec<i32> = self.read_episodes(folder)?
+            .iter()
+            .map(|e| e.season_num)
+            .collect();
+        nums.sort();
+        nums.dedup();
+
+        let mut season_nfos = Vec::new();
+        for path in nfo_files(Path::new(folder))? {
+            if path.file_name().map_or(false, |name| name == "season.nfo") {
+                let nfo = fs::read_to_string(&path)?;
+                let num = element_text(&nfo, "seasonnumber").and_then(|n| n.parse::<i32>().ok());
+                if let Some(num) = num {
+                    season_nfos.push((num, nfo));
+                }
+            }
+        }
+
+        Ok(nums
+            .into_iter()
+            .map(|num| LocalSeason {
+                num,
+                nfo: season_nfos
+                    .iter()
+                    .find(|&&(n, _)| n == num)
+                    .map(|&(_, ref nfo)| nfo.clone()),
+            })
+            .collect())
+    }
+
+    /// Reads the episodes from every NFO file in the show's folder. An episode
+    /// described by several files is only read from the first of them.
+    fn read_episodes(&self, folder: &str) -> Result<Vec<LocalEpisode>, WebError> {
+        let mut seen = HashSet::new();
+        let mut episodes = Vec::new();
+
+        for path in nfo_files(Path::new(folder))? {
+            let name = path.file_name().and_then(|name| name.to_str()).unwrap_or("");
+            if name == "tvshow.nfo" || name == "season.nfo" {
+                continue;
+            }
+
+            let nfo = fs::read_to_string(&path)?;
+            for details in elements(&nfo, "episodedetails") {
+                let body = details.body();
+                let season_num = element_text(body, "season").and_then(|n| n.parse().ok());
+                let num = element_text(body, "episode").and_then(|n| n.parse().ok());
+
+                if let (Some(season_num), Some(num)) = (season_num, num) {
+                    if seen.insert((season_num, num)) {
+                        episodes.push(LocalEpisode {
+                            season_num,
+                            num,
+                            details: body.to_string(),
+                        });
+                    }
+                }
+            }
+        }
+
+        episodes.sort_by_key(|e| (e.season_num, e.num));
+        Ok(episodes)
+    }
+}
+
+impl MetadataProvider for LocalProvider {
+    fn get_show(&self, id: &str) -> Box<Future<Item = TVShow, Error = WebError>> {
+        Box::new(future::result(
+            self.read_show(id).map(|show| Self::to_unify_show(&show)),
+        ))
+    }
+
+    fn get_show_seasons(
+        &self,
+        id: &str,
+    ) -> Box<Future<Item = Vec<TVShowSeason>, Error = WebError>> {
+        Box::new(future::result(self.read_seasons(id).map(|seasons| {
+            seasons.iter().map(|s| Self::to_unify_season(s)).collect()
+        })))
+    }
+
+    fn get_season_episodes(
+        &self,
+        show_id: &str,
+        season_id: &str,
+    ) -> Box<Future<Item = Vec<TVShowEpisode>, Error = WebError>> {
+        Box::new(future::result(self.read_episodes(show_id).map(|episodes| {
+            episodes
+                .iter()
+                .filter(|e| e.season_num.to_string() == season_id)
+                .map(|e| Self::to_unify_episode(e))
+                .collect()
+        })))
+    }
+
+    /// Local shows are added by their folder, so there is nothing to search.
+    fn search(&self, _q: &str) -> Box<Future<Item = Vec<ShowSearchResult>, Error = WebError>> {
+        Box::new(future::ok(vec![]))
+    }
+}
+
+impl ToUnify for LocalProvider {
+    type ProviderShow = LocalShow;
+    type ProviderSeason = LocalSeason;
+    type ProviderEpisode = LocalEpisode;
+
+    fn to_unify_show(p: &Self::ProviderShow) -> TVShow {
+        let nfo = p.nfo.as_str();
+        let folder = p.folder.to_string_lossy().into_owned();
+        let name = element_text(nfo, "title").unwrap_or_else(|| {
+            p.folder
+                .file_name()
+                .map_or_else(|| folder.clone(), |name| name.to_string_lossy().into_owned())
+        });
+
+        TVShow {
+            id: -1,
+            name,
+            summary: element_text(nfo, "plot"),
+            cover_img: elements(nfo, "thumb")
+                .into_iter()
+                .find(|e| e.attr("aspect") == Some("poster"))
+                .map(|e| e.text())
+                .filter(|url| !url.is_empty()),
+            fanart_img: elements(nfo, "fanart")
+                .first()
+                .and_then(|fanart| element_text(fanart.body(), "thumb")),
+            genres: elements(nfo, "genre")
+                .iter()
+                .map(|e| e.text())
+                .filter(|genre| !genre.is_empty())
+                .collect(),
+            runtime: element_text(nfo, "runtime").and_then(|r| r.parse().ok()),
+            premiered: element_text(nfo, "premiered").and_then(|d| parse_date(&d)),
+            external_ids: read_external_ids(nfo),
+            provider: TVMetadataProvider::Local,
+            provider_id: folder.clone(),
+            provider_url: String::new(),
+            provider_updated: None,
+            monitored: true,
+            quality_profile_id: DEFAULT_QUALITY_PROFILE_ID,
+            root_folder_id: None,
+            path: Some(folder),
+            aliases: vec![],
+            metadata_sources: MetadataSources::new(),
+            seasons: None,
+        }
+    }
+
+    fn to_unify_season(p: &Self::ProviderSeason) -> TVShowSeason {
+        let nfo = p.nfo.as_ref().map_or("", |nfo| nfo.as_str());
+
+        TVShowSeason {
+            id: -1,
+            show_id: -1,
+            num: p.num,
+            name: element_text(nfo, "title").unwrap_or_default(),
+            summary: element_text(nfo, "plot"),
+            cover_img: element_text(nfo, "thumb"),
+            provider_id: p.num.to_string(),
+            provider_url: String::new(),
+            metadata_sources: MetadataSources::new(),
+            episodes: None,
+        }
+    }
+
+    fn to_unify_episode(p: &Self::ProviderEpisode) -> TVShowEpisode {
+        let details = p.details.as_str();
+
+        TVShowEpisode {
+            id: -1,
+            show_id: -1,
+            season_id: -1,
+            num: Some(p.num),
+            season_num: p.season_num,
+            name: element_text(details, "title").unwrap_or_else(|| format!("Episode {}", p.num)),
+            summary: element_text(details, "plot"),
+            cover_img: element_text(details, "thumb"),
+            runtime: element_text(details, "runtime").and_then(|r| r.parse().ok()),
+            airdate: element_text(details, "aired").and_then(|d| parse_date(&d)),
+            monitored: true,
+            provider_id: format!("S{:02}E{:02}", p.season_num, p.num),
+            provider_url: String::new(),
+            metadata_sources: MetadataSources::new(),
+        }
+    }
+}
+
+/// Lists the NFO files in a show's folder and its season folders.
+fn nfo_files(folder: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+
+    for entry in fs::read_dir(folder)? {
+        let path = entry?.path();
+        let hidden = path.file_name()
+            .and_then(|name| name.to_str())
+            .map_or(true, |name| name.starts_with('.'));
+
+        if hidden {
+            continue;
+        } else if path.is_dir() {
+            for entry in fs::read_dir(&path)? {
+                let path = entry?.path();
+                if is_nfo(&path) {
+                    files.push(path);
+                }
+            }
+        } else if is_nfo(&path) {
+            files.push(path);
+        }
+    }
+
+    files.sort();
+    Ok(files)
+}
+
+fn is_nfo(path: &Path) -> bool {
+    path.is_file() && path.extension().map_or(false, |ext| ext == "nfo")
+}
+
+fn parse_date(date: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(date, "%Y-%m-%d").ok()
+}
+
+fn read_external_ids(nfo: &str) -> ExternalIds {
+    let mut ids = ExternalIds::default();
+
+    for id in elements(nfo, "uniqueid") {
+        let value = id.text();
+        match id.attr("type") {
+            Some("imdb") => ids.imdb = Some(value),
+            Some("tvdb") => ids.thetvdb = value.parse().ok(),
+            Some("tvrage") => ids.tvrage = value.parse().ok(),
+            _ => {}
+        }
+    }
+
+    // Older files have dedicated elements instead.
+    if ids.imdb.is_none() {
+        ids.imdb = element_text(nfo, "imdb_id");
+    }
+    if ids.thetvdb.is_none() {
+        ids.thetvdb = element_text(nfo, "tvdbid").and_then(|id| id.parse().ok());
+    }
+
+    ids
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::File;
+    use std::io::Write;
+
+    use super::*;
+
+    use tempfile::TempDir;
+
+    fn write(path: &Path, contents: &str) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        File::create(path)
+            .unwrap()
+            .write_all(contents.as_bytes())
+            .unwrap();
+    }
+
+    #[test]
+    fn reads_shows_from_nfo_files() {
+        let dir = TempDir::new().unwrap();
+        let folder = dir.path().join("Home Videos");
+        write(
+            &folder.join("tvshow.nfo"),
+            "<tvshow><title>Home Videos</title><plot>Summers &amp; holidays</plot>\
+             <genre>Family</genre><premiered>2001-06-01</premiered>\
+             <uniqueid type=\"imdb\">tt0000001</uniqueid></tvshow>",
+        );
+        write(
+            &folder.join("Season 01").join("season.nfo"),
+            "<season><seasonnumber>1</seasonnumber><title>Summer 2001</title></season>",
+        );
+        write(
+            &folder.join("Season 01").join("Home Videos - S01E01E02.nfo"),
+            "<episodedetails><title>Beach</title><season>1</season><episode>1</episode>\
+             <aired>2001-07-14</aired></episodedetails>\
+             <episodedetails><title>Camping</title><season>1</season><episode>2</episode>\
+             </episodedetails>",
+        );
+        write(
+            &folder.join("Specials").join("Blooper Reel.nfo"),
+            "<episodedetails><season>0</season><episode>1</episode></episodedetails>",
+        );
+        write(&folder.join("notes.nfo"), "<movie><title>Not an episode</title></movie>");
+
+        let provider = LocalProvider::new();
+        let id = folder.to_str().unwrap();
+        let show = provider.get_show(id).wait().unwrap();
+        assert_eq!(show.name, "Home Videos");
+        assert_eq!(show.summary, Some("Summers & holidays".to_string()));
+        assert_eq!(show.genres, vec!["Family"]);
+        assert_eq!(show.premiered, NaiveDate::from_ymd_opt(2001, 6, 1));
+        assert_eq!(show.external_ids.imdb, Some("tt0000001".to_string()));
+        assert_eq!(show.provider_id, id);
+        assert_eq!(show.path, Some(id.to_string()));
+
+        let seasons = provider.get_show_seasons(id).wait().unwrap();
+        let seasons: Vec<_> = seasons.iter().map(|s| (s.num, s.name.as_str())).collect();
+        assert_eq!(seasons, vec![(0, ""), (1, "Summer 2001")]);
+
+        let episodes = provider.get_season_episodes(id, "1").wait().unwrap();
+        let names: Vec<_> = episodes.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, vec!["Beach", "Camping"]);
+        assert_eq!(episodes[0].provider_id, "S01E01");
+        assert_eq!(episodes[0].airdate, NaiveDate::from_ymd_opt(2001, 7, 14));
+    }
+
+    #[test]
+    fn requires_a_show_nfo() {
+        let dir = TempDir::new().unwrap();
+
+        match LocalProvider::new().get_show(dir.path().to_str().unwrap()).wait() {
+            Err(WebError::InvalidRequest(_)) => {}
+            Err(e) => panic!("Expected an invalid request, got {:?}", e),
+            Ok(show) => panic!("Expected an invalid request, got {:?}", show),
+        }
+    }
+}
@@ -1,43 +1,185 @@
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use error::WebError;
+use resources::person::ShowCredits;
 use resources::tv_show::*;
 
-use futures::Future;
+use futures::{future, stream, Future, Stream};
 
+pub mod composite;
+pub mod local;
+pub mod thetvdb;
 pub mod tvmaze;
 
+pub use self::composite::CompositeProvider;
+pub use self::local::LocalProvider;
+pub use self::thetvdb::TheTVDBProvider;
 pub use self::tvmaze::TVMazeProvider;
 
-pub trait MetadataProvider: Clone {
-    type ProviderShow;
-    type ProviderSeason;
-    type ProviderEpisode;
-    type ProviderError;
+/// How far back `MetadataProvider::get_show_updates` goes.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum UpdatePeriod {
+    Day,
+    Week,
+    Month,
+}
 
-    fn get_show(
-        &self,
-        id: &str,
-    ) -> Result<Box<Future<Item = TVShow, Error = Self::ProviderError>>, Self::ProviderError>;
+/// A source of show metadata. Used as a trait object so that shows from any
+/// number of providers can live in the same library, see `MetadataProviders`.
+pub trait MetadataProvider {
+    fn get_show(&self, id: &str) -> Box<Future<Item = TVShow, Error = WebError>>;
     fn get_show_seasons(
         &self,
         id: &str,
-    ) -> Result<
-        Box<Future<Item = Vec<TVShowSeason>, Error = Self::ProviderError>>,
-        Self::ProviderError,
-    >;
+    ) -> Box<Future<Item = Vec<TVShowSeason>, Error = WebError>>;
     fn get_season_episodes(
         &self,
         show_id: &str,
         season_id: &str,
-    ) -> Result<
-        Box<Future<Item = Vec<TVShowEpisode>, Error = Self::ProviderError>>,
-        Self::ProviderError,
-    >;
+    ) -> Box<Future<Item = Vec<TVShowEpisode>, Error = WebError>>;
+
+    /// Loads a show with all of its seasons and their episodes in a few
+    /// requests. Resolves to `None` if the provider can only load episodes
+    /// one season at a time, see `library::show::load_full_show`.
+    fn get_full_show(&self, _id: &str) -> Box<Future<Item = Option<TVShow>, Error = WebError>> {
+        Box::new(future::ok(None))
+    }
+
+    fn search(&self, q: &str) -> Box<Future<Item = Vec<ShowSearchResult>, Error = WebError>>;
 
-    fn search(
+    /// The other titles a show is known by. Loads the whole show unless the
+    /// provider can load its aliases on their own.
+    fn get_show_aliases(&self, id: &str) -> Box<Future<Item = Vec<String>, Error = WebError>> {
+        Box::new(self.get_show(id).map(|show| show.aliases))
+    }
+
+    /// Finds a show by its id in another database. Resolves to `None` if the
+    /// id is unknown or the provider can't look shows up that way.
+    fn lookup_show(
         &self,
-        q: &str,
-    ) -> Result<Box<Future<Item = Vec<TVShow>, Error = Self::ProviderError>>, Self::ProviderError>;
+        _id: &ExternalId,
+    ) -> Box<Future<Item = Option<TVShow>, Error = WebError>> {
+        Box::new(future::ok(None))
+    }
+
+    /// Loads a show's cast and crew. Resolves to no one if the provider
+    /// doesn't list them.
+    fn get_show_credits(&self, _id: &str) -> Box<Future<Item = ShowCredits, Error = WebError>> {
+        Box::new(future::ok(ShowCredits::default()))
+    }
+
+    /// The shows that changed within `since`, mapped by their ids to the time
+    /// of their last update as a unix timestamp, which shows also have as
+    /// their `provider_updated`. Resolves to no shows if the provider doesn't
+    /// keep track of changes.
+    fn get_show_updates(
+        &self,
+        _since: UpdatePeriod,
+    ) -> Box<Future<Item = HashMap<String, i64>, Error = WebError>> {
+        Box::new(future::ok(HashMap::new()))
+    }
+
+    /// Every show the provider lists, a page at a time along with the page's
+    /// number, from `first_page` to the last page. Shows are listed as search
+    /// results without a score, since they come with the same details. Used
+    /// to fill the catalog, see `library::catalog`. Resolves to no pages if
+    /// the provider can't list its shows.
+    fn get_show_index(
+        &self,
+        _first_page: u64,
+    ) -> Box<Stream<Item = (u64, Vec<ShowSearchResult>), Error = WebError>> {
+        Box::new(stream::empty())
+    }
+
+    /// The shows on a single page of the provider's index, or `None` past its
+    /// last page.
+    fn get_show_index_page(
+        &self,
+        _page: u64,
+    ) -> Box<Future<Item = Option<Vec<ShowSearchResult>>, Error = WebError>> {
+        Box::new(future::ok(None))
+    }
+}
+
+/// Converts a provider's own records into Unify's. Kept apart from
+/// `MetadataProvider` so that it can still be used as a trait object.
+pub trait ToUnify {
+    type ProviderShow;
+    type ProviderSeason;
+    type ProviderEpisode;
 
     fn to_unify_show(p: &Self::ProviderShow) -> TVShow;
     fn to_unify_season(p: &Self::ProviderSeason) -> TVShowSeason;
     fn to_unify_episode(p: &Self::ProviderEpisode) -> TVShowEpisode;
 }
+
+/// The metadata providers shows can be loaded from, keyed by the `provider`
+/// stored with every show.
+#[derive(Clone, Default)]
+pub struct MetadataProviders {
+    providers: HashMap<TVMetadataProvider, Rc<MetadataProvider>>,
+}
+
+impl MetadataProviders {
+    pub fn new() -> MetadataProviders {
+        MetadataProviders::default()
+    }
+
+    pub fn register(&mut self, kind: TVMetadataProvider, provider: Rc<MetadataProvider>) {
+        self.providers.insert(kind, provider);
+    }
+
+    pub fn kinds(&self) -> Vec<TVMetadataProvider> {
+        self.providers.keys().cloned().collect()
+    }
+
+    pub fn get(&self, kind: TVMetadataProvider) -> Result<Rc<MetadataProvider>, WebError> {
+        self.providers
+            .get(&kind)
+            .cloned()
+            .ok_or(WebError::NoMetadataProvider(kind))
+    }
+
+    /// Puts every provider behind a `CompositeProvider` that fills its gaps
+    /// from the registered providers among `fallbacks`, in that order.
+    pub fn with_fallbacks(&self, fallbacks: &[TVMetadataProvider]) -> MetadataProviders {
+        let providers = self.providers
+            .iter()
+            .map(|(&kind, primary)| {
+                let others: Vec<_> = fallbacks
+                    .iter()
+                    .filter(|&&other| other != kind)
+                    .filter_map(|&other| self.providers.get(&other).map(|p| (other, p.clone())))
+                    .collect();
+
+                let provider: Rc<MetadataProvider> = if others.is_empty() {
+                    primary.clone()
+                } else {
+                    Rc::new(CompositeProvider::new(primary.clone(), others))
+                };
+                (kind, provider)
+            })
+            .collect();
+
+        MetadataProviders { providers }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dispatches_by_stored_provider() {
+        let mut providers = MetadataProviders::new();
+        providers.register(TVMetadataProvider::Local, Rc::new(LocalProvider::new()));
+
+        assert!(providers.get(TVMetadataProvider::Local).is_ok());
+        match providers.get(TVMetadataProvider::TVMaze) {
+            Err(WebError::NoMetadataProvider(TVMetadataProvider::TVMaze)) => {}
+            Err(e) => panic!("Expected a missing provider, got {:?}", e),
+            Ok(_) => panic!("Expected a missing provider"),
+        }
+    }
+}
@@ -0,0 +1,325 @@
+use super::{MetadataProvider, ToUnify};
+use error::WebError;
+use resources::quality::DEFAULT_QUALITY_PROFILE_ID;
+use resources::tv_show::*;
+
+use thetvdb::api::{SearchResult, SeasonType, ARTWORK_SERIES_BACKGROUND, REMOTE_ID_IMDB};
+use thetvdb::client::Client;
+use thetvdb::error::Error as TheTVDBError;
+use thetvdb::resources::{Episode, RemoteId, Season, SeriesExtended};
+
+use chrono::NaiveDate;
+use futures::{future, Future};
+
+const THETVDB_SITE_URL: &'static str = "https://thetvdb.com/series/";
+
+#[derive(Clone)]
+pub struct TheTVDBProvider {
+    client: Client,
+}
+
+impl TheTVDBProvider {
+    pub fn new(apikey: &str, pin: Option<&str>) -> Result<TheTVDBProvider, TheTVDBError> {
+        Ok(TheTVDBProvider {
+            client: Client::new(apikey, pin)?,
+        })
+    }
+}
+
+impl MetadataProvider for TheTVDBProvider {
+    fn get_show(&self, id: &str) -> Box<Future<Item = TVShow, Error = WebError>> {
+        Box::new(
+            future::result(self.client.series_extended(id))
+                .flatten()
+                .map(|series| Self::to_unify_show(&series))
+                .map_err(WebError::from),
+        )
+    }
+
+    /// Only the seasons of the aired order are used, since that's what
+    /// releases are numbered by.
+    fn get_show_seasons(
+        &self,
+        id: &str,
+    ) -> Box<Future<Item = Vec<TVShowSeason>, Error = WebError>> {
+        Box::new(
+            future::result(self.client.series_extended(id))
+                .flatten()
+                .map(|series| {
+                    series
+                        .seasons
+                        .iter()
+                        .filter(|s| s._type._type == "official")
+                        .map(|s| Self::to_unify_season(s))
+                        .collect()
+                })
+                .map_err(WebError::from),
+        )
+    }
+
+    fn get_season_episodes(
+        &self,
+        _show_id: &str,
+        season_id: &str,
+    ) -> Box<Future<Item = Vec<TVShowEpisode>, Error = WebError>> {
+        Box::new(
+            future::result(self.client.season_extended(season_id))
+                .flatten()
+                .map(|season| {
+                    season
+                        .episodes
+                        .iter()
+                        .map(|e| Self::to_unify_episode(e))
+                        .collect()
+                })
+                .map_err(WebError::from),
+        )
+    }
+
+    /// Episodes are loaded for the whole series at once, a page at a time,
+    /// and grouped into the seasons of the aired order.
+    fn get_full_show(&self, id: &str) -> Box<Future<Item = Option<TVShow>, Error = WebError>> {
+        let series = future::result(self.client.series_extended(id)).flatten();
+        let episodes =
+            future::result(self.client.series_episodes(id, SeasonType::Official)).flatten();
+
+        Box::new(
+            series
+                .join(episodes)
+                .map(|(series, episodes)| {
+                    let mut show = Self::to_unify_show(&series);
+                    show.seasons = Some(
+                        series
+                            .seasons
+                            .iter()
+                            .filter(|s| s._type._type == "official")
+                            .map(|s| {
+                                let mut season = Self::to_unify_season(s);
+                                season.episodes = Some(
+                                    episodes
+                                        .iter()
+                                        .filter(|e| e.season_number == s.number)
+                                        .map(Self::to_unify_episode)
+                                        .collect(),
+                                );
+                                season
+                            })
+                            .collect(),
+                    );
+                    Some(show)
+                })
+                .map_err(WebError::from),
+        )
+    }
+
+    /// TheTVDB orders results by relevance without scoring them.
+    fn search(&self, q: &str) -> Box<Future<Item = Vec<ShowSearchResult>, Error = WebError>> {
+        Box::new(
+            future::result(self.client.search_series(q))
+                .flatten()
+                .map(|results| {
+                    results
+                        .iter()
+                        .map(|r| {
+                            let show = search_result_to_show(r);
+                            ShowSearchResult::new(show, None, r.network.clone(), r.status.clone())
+                        })
+                        .collect()
+                })
+                .map_err(WebError::from),
+        )
+    }
+
+    fn lookup_show(
+        &self,
+        id: &ExternalId,
+    ) -> Box<Future<Item = Option<TVShow>, Error = WebError>> {
+        let series_id = match *id {
+            ExternalId::TheTVDB(id) => Box::new(future::ok(Some(id.to_string())))
+                as Box<Future<Item = _, Error = _>>,
+            ExternalId::IMDB(ref imdb) => Box::new(
+                future::result(self.client.search_remote_id(imdb))
+                    .flatten()
+                    .map(|results| {
+                        results
+                            .into_iter()
+                            .filter_map(|r| r.series)
+                            .next()
+                            .map(|series| series.id.to_string())
+                    }),
+            ),
+            ExternalId::TVRage(_) => return Box::new(future::ok(None)),
+        };
+
+        let client = self.client.clone();
+        Box::new(
+            series_id
+                .and_then(move |id| match id {
+                    Some(id) => Box::new(future::result(client.series_extended(&id)).flatten())
+                        as Box<Future<Item = _, Error = _>>,
+                    None => Box::new(future::err(TheTVDBError::NotFound)),
+                })
+                .then(|res| match res {
+                    Ok(series) => Ok(Some(Self::to_unify_show(&series))),
+                    Err(TheTVDBError::NotFound) => Ok(None),
+                    Err(e) => Err(WebError::from(e)),
+                }),
+        )
+    }
+}
+
+impl ToUnify for TheTVDBProvider {
+    type ProviderShow = SeriesExtended;
+    type ProviderSeason = Season;
+    type ProviderEpisode = Episode;
+
+    fn to_unify_show(p: &Self::ProviderShow) -> TVShow {
+        let fanart = p
+            .artworks
+            .iter()
+            .filter(|a| a._type == ARTWORK_SERIES_BACKGROUND)
+            .max_by(|a, b| {
+                let (a, b) = (a.score.unwrap_or(0.0), b.score.unwrap_or(0.0));
+                a.partial_cmp(&b).unwrap_or(::std::cmp::Ordering::Equal)
+            });
+
+        let mut show = TVShow {
+            id: -1,
+            name: p.name.clone(),
+            summary: p.overview.clone(),
+            cover_img: p.image.clone(),
+            fanart_img: fanart.map(|a| a.image.clone()),
+            genres: p.genres.iter().map(|g| g.name.clone()).collect(),
+            runtime: p.average_runtime.map(|x| x as i32),
+            premiered: p.first_aired.as_ref().and_then(|d| parse_date(d)),
+            external_ids: ExternalIds {
+                imdb: find_imdb_id(&p.remote_ids),
+                thetvdb: Some(p.id as i64),
+                tvrage: None,
+            },
+            provider: TVMetadataProvider::TheTVDB,
+            provider_id: p.id.to_string(),
+            provider_url: [THETVDB_SITE_URL, &p.slug].join(""),
+            provider_updated: None,
+            monitored: true,
+            quality_profile_id: DEFAULT_QUALITY_PROFILE_ID,
+            root_folder_id: None,
+            path: None,
+            aliases: vec![],
+            metadata_sources: MetadataSources::new(),
+            seasons: None,
+        };
+        show.add_aliases(p.aliases.iter().map(|a| a.name.clone()));
+        show
+    }
+
+    fn to_unify_season(p: &Self::ProviderSeason) -> TVShowSeason {
+        TVShowSeason {
+            id: -1,
+            show_id: -1,
+            num: p.number as i32,
+            name: p.name.clone().unwrap_or_default(),
+            summary: None,
+            cover_img: p.image.clone(),
+            provider_id: p.id.to_string(),
+            provider_url: String::new(),
+            metadata_sources: MetadataSources::new(),
+            episodes: None,
+        }
+    }
+
+    fn to_unify_episode(p: &Self::ProviderEpisode) -> TVShowEpisode {
+        TVShowEpisode {
+            id: -1,
+            show_id: -1,
+            season_id: -1,
+            num: Some(p.number as i32),
+            season_num: p.season_number as i32,
+            name: p
+                .name
+                .clone()
+                .unwrap_or_else(|| format!("Episode {}", p.number)),
+            summary: p.overview.clone(),
+            cover_img: p.image.clone(),
+            runtime: p.runtime.map(|x| x as i32),
+            airdate: p.aired.as_ref().and_then(|d| parse_date(d)),
+            monitored: true,
+            provider_id: p.id.to_string(),
+            provider_url: String::new(),
+            metadata_sources: MetadataSources::new(),
+        }
+    }
+}
+
+/// Search results are a flattened version of a series, with fewer details.
+fn search_result_to_show(r: &SearchResult) -> TVShow {
+    TVShow {
+        id: -1,
+        name: r.name.clone(),
+        summary: r.overview.clone(),
+        cover_img: r.image_url.clone(),
+        fanart_img: None,
+        genres: r.genres.clone(),
+        runtime: None,
+        premiered: r.first_air_time.as_ref().and_then(|d| parse_date(d)),
+        external_ids: ExternalIds {
+            imdb: find_imdb_id(&r.remote_ids),
+            thetvdb: r.tvdb_id.parse().ok(),
+            tvrage: None,
+        },
+        provider: TVMetadataProvider::TheTVDB,
+        provider_id: r.tvdb_id.clone(),
+        provider_url: r.slug
+            .as_ref()
+            .map_or_else(String::new, |slug| [THETVDB_SITE_URL, slug].join("")),
+        provider_updated: None,
+        monitored: true,
+        quality_profile_id: DEFAULT_QUALITY_PROFILE_ID,
+        root_folder_id: None,
+        path: None,
+        aliases: vec![],
+        metadata_sources: MetadataSources::new(),
+        seasons: None,
+    }
+}
+
+fn find_imdb_id(remote_ids: &[RemoteId]) -> Option<String> {
+    remote_ids
+        .iter()
+        .find(|id| id._type == REMOTE_ID_IMDB)
+        .map(|id| id.id.clone())
+}
+
+fn parse_date(date: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(date, "%Y-%m-%d").ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use thetvdb::api::Response;
+
+    use serde_json;
+
+    #[test]
+    fn converts_extended_series() {
+        let fixture = include_str!(concat!(
+            "../../../lib/providers/metadata/thetvdb/",
+            "tests/fixtures/series_extended.json"
+        ));
+        let series: Response<SeriesExtended> = serde_json::from_str(fixture).unwrap();
+        let show = TheTVDBProvider::to_unify_show(&series.data);
+
+        assert_eq!(show.provider_id, "121361");
+        assert_eq!(show.provider_url, "https://thetvdb.com/series/game-of-thrones");
+        assert_eq!(show.genres, vec!["Adventure", "Drama"]);
+        assert_eq!(show.premiered, NaiveDate::from_ymd_opt(2011, 4, 17));
+        assert_eq!(show.external_ids.imdb, Some("tt0944947".to_string()));
+        assert_eq!(show.aliases, vec!["Das Lied von Eis und Feuer", "Juego de tronos"]);
+        assert_eq!(
+            show.fanart_img,
+            Some("https://artworks.thetvdb.com/banners/fanart/original/121361-15.jpg".to_string())
+        );
+    }
+}
@@ -1,13 +1,28 @@
-use super::MetadataProvider;
+use std::collections::HashMap;
+
+use super::{MetadataProvider, ToUnify, UpdatePeriod};
+use config::Config;
+use error::WebError;
+use resources::person::{CastMember, CrewMember, Person, ShowCredits};
+use resources::quality::DEFAULT_QUALITY_PROFILE_ID;
 use resources::tv_show::*;
 
+use tvmaze::api::{LinkEmbed, ShowLookup, UpdatePeriod as TVMazeUpdatePeriod};
+use tvmaze::cache::{DiskCache, MemoryCache};
 use tvmaze::client::Client;
 use tvmaze::error::Error as TVMazeError;
 use tvmaze::resources::{
-    Episode as TVMazeEpisode, TVShow as TVMazeShow, TVShowSeason as TVMazeSeason,
+    CastPerson, CrewPerson, Episode as TVMazeEpisode, Person as TVMazePerson, ShowImage,
+    TVShow as TVMazeShow, TVShowSeason as TVMazeSeason,
 };
 
-use futures::Future;
+use futures::{future, Future, Stream};
+
+/// How many responses are kept in memory without a cache directory.
+const CACHED_RESPONSES: usize = 1000;
+
+/// How many pages of the show index are requested at once.
+const INDEX_CONCURRENCY: usize = 2;
 
 #[derive(Clone)]
 pub struct TVMazeProvider {
@@ -15,75 +30,257 @@ pub struct TVMazeProvider {
 }
 
 impl TVMazeProvider {
-    pub fn new() -> TVMazeProvider {
-        TVMazeProvider {
-            // This should never panic...
-            client: Client::new().unwrap(),
+    pub fn new(config: &Config) -> Result<TVMazeProvider, TVMazeError> {
+        let mut builder = Client::builder();
+        if let Some(ref base_url) = config.tvmaze_base_url {
+            builder = builder.base_url(base_url);
+        }
+        if let Some(ref proxy) = config.tvmaze_proxy {
+            builder = builder.proxy(proxy);
         }
+        builder = match config.tvmaze_cache_dir {
+            Some(ref dir) => builder.cache(DiskCache::new(dir).map_err(TVMazeError::CacheError)?),
+            None => builder.cache(MemoryCache::new(CACHED_RESPONSES)),
+        };
+
+        Ok(TVMazeProvider {
+            client: builder.build()?,
+        })
     }
 }
 
 impl MetadataProvider for TVMazeProvider {
-    type ProviderShow = TVMazeShow;
-    type ProviderSeason = TVMazeSeason;
-    type ProviderEpisode = TVMazeEpisode;
-    type ProviderError = TVMazeError;
+    fn get_show(&self, id: &str) -> Box<Future<Item = TVShow, Error = WebError>> {
+        let embed = Some(LinkEmbed::Single("akas"));
+        let show = future::result(self.client.show_main_info(id, embed)).flatten();
+        let images = future::result(self.client.show_images(id)).flatten();
 
-    fn get_show(
-        &self,
-        id: &str,
-    ) -> Result<Box<Future<Item = TVShow, Error = Self::ProviderError>>, Self::ProviderError> {
-        Ok(Box::new(
-            self.client
-                .show_main_info(id)?
-                .map(|r| Self::to_unify_show(&r)),
-        ))
+        Box::new(
+            show.join(images)
+                .map(|(show, images)| to_unify_show_with_extras(&show, &images))
+                .map_err(WebError::from),
+        )
+    }
+
+    /// Embeds the seasons in the show and loads all episodes at once,
+    /// including specials, which TVmaze leaves out of embedded episodes.
+    fn get_full_show(&self, id: &str) -> Box<Future<Item = Option<TVShow>, Error = WebError>> {
+        let embed = Some(LinkEmbed::Multiple(vec!["seasons", "akas"]));
+        let show = future::result(self.client.show_main_info(id, embed)).flatten();
+        let episodes = future::result(self.client.show_episode_list(id, true, None)).flatten();
+        let images = future::result(self.client.show_images(id)).flatten();
+
+        Box::new(
+            show.join3(episodes, images)
+                .map(|(show, episodes, images)| {
+                    let mut unify_show = to_unify_show_with_extras(&show, &images);
+                    let seasons = show.embeds.as_ref().and_then(|e| e.seasons.as_ref());
+                    unify_show.seasons = seasons.map(|seasons| {
+                        seasons
+                            .iter()
+                            .map(|s| {
+                                let mut season = Self::to_unify_season(s);
+                                season.episodes = Some(
+                                    episodes
+                                        .iter()
+                                        .filter(|e| e.season == s.number)
+                                        .map(Self::to_unify_episode)
+                                        .collect(),
+                                );
+                                season
+                            })
+                            .collect()
+                    });
+                    if unify_show.seasons.is_some() {
+                        Some(unify_show)
+                    } else {
+                        None
+                    }
+                })
+                .map_err(WebError::from),
+        )
     }
 
     fn get_show_seasons(
         &self,
         id: &str,
-    ) -> Result<
-        Box<Future<Item = Vec<TVShowSeason>, Error = Self::ProviderError>>,
-        Self::ProviderError,
-    > {
-        Ok(Box::new(self.client.show_seasons(id)?.map(|s| {
-            s.iter().map(|s| Self::to_unify_season(&s)).collect()
-        })))
+    ) -> Box<Future<Item = Vec<TVShowSeason>, Error = WebError>> {
+        Box::new(
+            future::result(self.client.show_seasons(id))
+                .flatten()
+                .map(|s| s.iter().map(|s| Self::to_unify_season(&s)).collect())
+                .map_err(WebError::from),
+        )
     }
 
     fn get_season_episodes(
         &self,
         _show_id: &str,
         season_id: &str,
-    ) -> Result<
-        Box<Future<Item = Vec<TVShowEpisode>, Error = Self::ProviderError>>,
-        Self::ProviderError,
-    > {
-        Ok(Box::new(self.client.season_episodes(season_id)?.map(|e| {
-            e.iter().map(|e| Self::to_unify_episode(&e)).collect()
-        })))
+    ) -> Box<Future<Item = Vec<TVShowEpisode>, Error = WebError>> {
+        Box::new(
+            future::result(self.client.season_episodes(season_id))
+                .flatten()
+                .map(|e| e.iter().map(|e| Self::to_unify_episode(&e)).collect())
+                .map_err(WebError::from),
+        )
     }
 
-    fn search(
+    fn search(&self, q: &str) -> Box<Future<Item = Vec<ShowSearchResult>, Error = WebError>> {
+        Box::new(
+            future::result(self.client.search_shows(q))
+                .flatten()
+                .map(|r| r.iter().map(|r| to_search_result(&r.show, Some(r.score))).collect())
+                .map_err(WebError::from),
+        )
+    }
+
+    fn get_show_aliases(&self, id: &str) -> Box<Future<Item = Vec<String>, Error = WebError>> {
+        Box::new(
+            future::result(self.client.show_akas(id))
+                .flatten()
+                .map(|akas| {
+                    let mut aliases: Vec<String> = vec![];
+                    for aka in akas {
+                        if !aliases.contains(&aka.name) {
+                            aliases.push(aka.name);
+                        }
+                    }
+                    aliases
+                })
+                .map_err(WebError::from),
+        )
+    }
+
+    fn lookup_show(
+        &self,
+        id: &ExternalId,
+    ) -> Box<Future<Item = Option<TVShow>, Error = WebError>> {
+        let thetvdb;
+        let tvrage;
+        let lookup = match *id {
+            ExternalId::IMDB(ref imdb) => ShowLookup::IMDB(imdb),
+            ExternalId::TheTVDB(id) => {
+                thetvdb = id.to_string();
+                ShowLookup::TheTVDB(&thetvdb)
+            }
+            ExternalId::TVRage(id) => {
+                tvrage = id.to_string();
+                ShowLookup::TVRage(&tvrage)
+            }
+        };
+
+        Box::new(
+            future::result(self.client.lookup_show(lookup))
+                .flatten()
+                .then(|res| match res {
+                    Ok(show) => Ok(show.map(|s| Self::to_unify_show(&s))),
+                    Err(TVMazeError::ShowNotFound) => Ok(None),
+                    Err(e) => Err(WebError::from(e)),
+                }),
+        )
+    }
+
+    fn get_show_credits(&self, id: &str) -> Box<Future<Item = ShowCredits, Error = WebError>> {
+        let cast = future::result(self.client.show_cast(id)).flatten();
+        let crew = future::result(self.client.show_crew(id)).flatten();
+
+        Box::new(
+            cast.join(crew)
+                .map(|(cast, crew)| ShowCredits {
+                    cast: cast.iter().map(to_unify_cast_member).collect(),
+                    crew: crew.iter().map(to_unify_crew_member).collect(),
+                })
+                .map_err(WebError::from),
+        )
+    }
+
+    fn get_show_updates(
         &self,
-        q: &str,
-    ) -> Result<Box<Future<Item = Vec<TVShow>, Error = Self::ProviderError>>, Self::ProviderError>
-    {
-        Ok(Box::new(self.client.search_shows(q)?.map(|r| {
-            r.iter().map(|r| Self::to_unify_show(&r.show)).collect()
-        })))
+        since: UpdatePeriod,
+    ) -> Box<Future<Item = HashMap<String, i64>, Error = WebError>> {
+        let since = match since {
+            UpdatePeriod::Day => TVMazeUpdatePeriod::Day,
+            UpdatePeriod::Week => TVMazeUpdatePeriod::Week,
+            UpdatePeriod::Month => TVMazeUpdatePeriod::Month,
+        };
+
+        Box::new(
+            future::result(self.client.show_updates(since))
+                .flatten()
+                .map(|updates| {
+                    updates
+                        .into_iter()
+                        .map(|(id, updated)| (id.to_string(), updated as i64))
+                        .collect()
+                })
+                .map_err(WebError::from),
+        )
     }
 
+    /// TVmaze numbers the pages of its index from 0, with 250 shows each.
+    fn get_show_index(
+        &self,
+        first_page: u64,
+    ) -> Box<Stream<Item = (u64, Vec<ShowSearchResult>), Error = WebError>> {
+        Box::new(
+            self.client
+                .show_index_pages(first_page, INDEX_CONCURRENCY)
+                .map(|(page, shows)| {
+                    (page, shows.iter().map(|s| to_search_result(s, None)).collect())
+                })
+                .map_err(WebError::from),
+        )
+    }
+
+    fn get_show_index_page(
+        &self,
+        page: u64,
+    ) -> Box<Future<Item = Option<Vec<ShowSearchResult>>, Error = WebError>> {
+        Box::new(
+            future::result(self.client.show_index(Some(page)))
+                .flatten()
+                .then(|res| match res {
+                    Ok(shows) => Ok(Some(
+                        shows.iter().map(|s| to_search_result(s, None)).collect(),
+                    )),
+                    Err(TVMazeError::NotFound(_)) => Ok(None),
+                    Err(e) => Err(WebError::from(e)),
+                }),
+        )
+    }
+}
+
+impl ToUnify for TVMazeProvider {
+    type ProviderShow = TVMazeShow;
+    type ProviderSeason = TVMazeSeason;
+    type ProviderEpisode = TVMazeEpisode;
+
     fn to_unify_show(p: &Self::ProviderShow) -> TVShow {
         TVShow {
             id: -1,
             name: p.name.clone(),
             summary: p.summary.clone(),
             cover_img: p.image.clone().map(|i| i.original),
+            fanart_img: None,
+            genres: p.genres.clone(),
+            runtime: p.runtime.map(|x| x as i32),
+            premiered: p.premiered,
+            external_ids: ExternalIds {
+                imdb: p.externals.imdb.clone(),
+                thetvdb: p.externals.thetvdb.map(|x| x as i64),
+                tvrage: p.externals.tvrage.map(|x| x as i64),
+            },
             provider: TVMetadataProvider::TVMaze,
             provider_id: p.id.to_string(),
             provider_url: p.url.clone(),
+            provider_updated: Some(p.updated as i64),
+            monitored: true,
+            quality_profile_id: DEFAULT_QUALITY_PROFILE_ID,
+            root_folder_id: None,
+            path: None,
+            aliases: vec![],
+            metadata_sources: MetadataSources::new(),
             seasons: None,
         }
     }
@@ -98,6 +295,7 @@ impl MetadataProvider for TVMazeProvider {
             cover_img: p.image.clone().map(|i| i.original),
             provider_id: p.id.to_string(),
             provider_url: p.url.clone(),
+            metadata_sources: MetadataSources::new(),
             episodes: None,
         }
     }
@@ -113,8 +311,77 @@ impl MetadataProvider for TVMazeProvider {
             summary: p.summary.clone(),
             cover_img: p.image.clone().map(|i| i.original),
             runtime: p.runtime.map(|x| x as i32),
+            airdate: p.airdate,
+            monitored: true,
             provider_id: p.id.to_string(),
             provider_url: p.url.clone(),
+            metadata_sources: MetadataSources::new(),
         }
     }
 }
+
+/// A show with its fanart picked from `images` and the aliases it was
+/// loaded with.
+fn to_unify_show_with_extras(p: &TVMazeShow, images: &[ShowImage]) -> TVShow {
+    let mut show = TVMazeProvider::to_unify_show(p);
+    show.fanart_img = find_fanart(images);
+    if let Some(akas) = p.embeds.as_ref().and_then(|e| e.akas.as_ref()) {
+        show.add_aliases(akas.iter().map(|a| a.name.clone()));
+    }
+    show
+}
+
+/// A show with the network or web channel it airs on and its status.
+fn to_search_result(p: &TVMazeShow, score: Option<f64>) -> ShowSearchResult {
+    let network = p.network
+        .as_ref()
+        .map(|n| n.name.clone())
+        .or_else(|| p.web_channel.as_ref().map(|w| w.name.clone()));
+    let status = Some(p.status.as_str().to_string());
+
+    ShowSearchResult::new(TVMazeProvider::to_unify_show(p), score, network, status)
+}
+
+fn to_unify_person(p: &TVMazePerson) -> Person {
+    Person {
+        id: -1,
+        name: p.name.clone(),
+        image: p.image.clone().map(|i| i.original),
+        birthday: p.birthday,
+        deathday: p.deathday,
+        gender: p.gender.clone(),
+        country: p.country.as_ref().map(|c| c.name.clone()),
+        provider: TVMetadataProvider::TVMaze,
+        provider_id: p.id.to_string(),
+        provider_url: p.url.clone(),
+    }
+}
+
+fn to_unify_cast_member(p: &CastPerson) -> CastMember {
+    CastMember {
+        person: to_unify_person(&p.person),
+        character: p.character.name.clone(),
+        character_img: p.character.image.clone().map(|i| i.original),
+    }
+}
+
+fn to_unify_crew_member(p: &CrewPerson) -> CrewMember {
+    CrewMember {
+        person: to_unify_person(&p.person),
+        job: p._type.clone(),
+    }
+}
+
+/// Picks the show's background image, preferring the one TVmaze marks as its
+/// main image.
+fn find_fanart(images: &[ShowImage]) -> Option<String> {
+    let mut backgrounds: Vec<&ShowImage> = images
+        .iter()
+        .filter(|i| i._type.as_ref().map_or(false, |t| t == "background"))
+        .collect();
+    backgrounds.sort_by_key(|i| !i.main);
+
+    backgrounds
+        .first()
+        .map(|i| i.resolutions.original.url.clone())
+}
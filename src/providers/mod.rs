@@ -1 +1,3 @@
+pub mod download;
+pub mod indexer;
 pub mod metadata;
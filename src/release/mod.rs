@@ -0,0 +1,3 @@
+mod parser;
+
+pub use self::parser::{normalize_title, parse, title_matches, ParsedRelease, VIDEO_EXTENSIONS};
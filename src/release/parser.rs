@@ -0,0 +1,326 @@
+use resources::quality::Quality;
+
+/// Extensions of video files, which are stripped from names before parsing.
+pub const VIDEO_EXTENSIONS: &'static [&'static str] = &["avi", "m4v", "mkv", "mp4", "ts", "wmv"];
+
+/// The pieces of information that could be extracted from a release name such
+/// as `Show.Name.S01E02.720p.WEB-DL.x264-GROUP`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ParsedRelease {
+    pub title: String,
+    pub year: Option<i32>,
+    pub season: i32,
+    pub episodes: Vec<i32>,
+    pub quality: Quality,
+    pub group: Option<String>,
+}
+
+impl ParsedRelease {
+    pub fn is_season_pack(&self) -> bool {
+        self.episodes.is_empty()
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Source {
+    Bluray,
+    Web,
+    HDTV,
+    DVD,
+    SDTV,
+}
+
+/// Parses a scene-style release or file name.
+///
+/// Returns `None` if no season/episode marker (`S01E02`, `1x02`, `S01` or
+/// `Season 1`) could be found, since such a release can't be mapped to an
+/// episode. The title is empty for names like `S01E02.mkv`, which are common
+/// for files already sorted into a show's folder.
+pub fn parse(name: &str) -> Option<ParsedRelease> {
+    let name = strip_extension(name.trim());
+    let (name, group) = split_group(name);
+    let tokens: Vec<&str> = name
+        .split(|c: char| c == '.' || c == ' ' || c == '_' || c == '-')
+        .filter(|t| !t.is_empty())
+        .collect();
+
+    let (marker_idx, marker_len, season, episodes) = find_marker(&tokens)?;
+
+    let mut title_tokens: Vec<&str> = tokens[..marker_idx].to_vec();
+    let mut year = None;
+    if title_tokens.len() > 1 {
+        let last = title_tokens[title_tokens.len() - 1];
+        let last = last.trim_matches(|c| c == '(' || c == ')');
+        if let Some(y) = parse_year(last) {
+            year = Some(y);
+            title_tokens.pop();
+        }
+    }
+
+    let lowered: Vec<String> = tokens[marker_idx + marker_len..]
+        .iter()
+        .map(|t| t.to_lowercase())
+        .collect();
+
+    Some(ParsedRelease {
+        title: title_tokens.join(" "),
+        year,
+        season,
+        episodes,
+        quality: parse_quality(&lowered),
+        group,
+    })
+}
+
+/// Reduces a title to lowercase alphanumerics so that `Marvel's Agents of
+/// S.H.I.E.L.D.` and `Marvels.Agents.of.SHIELD` compare equal.
+pub fn normalize_title(title: &str) -> String {
+    title
+        .chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(|c| c.to_lowercase())
+        .collect()
+}
+
+/// Whether a parsed release title is `name` or one of the `aliases` of a
+/// show.
+pub fn title_matches(title: &str, name: &str, aliases: &[String]) -> bool {
+    let title = normalize_title(title);
+    !title.is_empty()
+        && (normalize_title(name) == title || aliases.iter().any(|a| normalize_title(a) == title))
+}
+
+fn strip_extension(name: &str) -> &str {
+    if let Some(idx) = name.rfind('.') {
+        let ext = name[idx + 1..].to_lowercase();
+        if VIDEO_EXTENSIONS.contains(&ext.as_str()) {
+            return &name[..idx];
+        }
+    }
+    name
+}
+
+fn split_group(name: &str) -> (&str, Option<String>) {
+    if let Some(idx) = name.rfind('-') {
+        let group = &name[idx + 1..];
+        let lowered = group.to_lowercase();
+        let is_group = !group.is_empty()
+            && group.chars().all(|c| c.is_alphanumeric())
+            && lowered != "dl"
+            && lowered != "rip"
+            && lowered != "ray";
+
+        if is_group {
+            return (&name[..idx], Some(group.to_string()));
+        }
+    }
+    (name, None)
+}
+
+/// Finds the season/episode marker, returning its token index, the number of
+/// tokens it spans, the season and the episodes it covers.
+fn find_marker(tokens: &[&str]) -> Option<(usize, usize, i32, Vec<i32>)> {
+    for (idx, token) in tokens.iter().enumerate() {
+        let lowered = token.to_lowercase();
+
+        if let Some((season, mut episodes)) = parse_sxxeyy(&lowered) {
+            // Multi-episode releases are sometimes written as `S01E01-E02`,
+            // which the tokenizer splits into two tokens.
+            let mut len = 1;
+            for next in tokens[idx + 1..].iter() {
+                match parse_episode_token(&next.to_lowercase()) {
+                    Some(episode) => {
+                        episodes.push(episode);
+                        len += 1;
+                    }
+                    None => break,
+                }
+            }
+            return Some((idx, len, season, episodes));
+        }
+
+        if let Some((season, episode)) = parse_nxnn(&lowered) {
+            return Some((idx, 1, season, vec![episode]));
+        }
+
+        if lowered == "season" {
+            if let Some(season) = tokens.get(idx + 1).and_then(|s| s.parse().ok()) {
+                return Some((idx, 2, season, vec![]));
+            }
+        }
+    }
+
+    None
+}
+
+/// Parses `s01e02`, `s01e02e03` and season packs written as `s01`.
+fn parse_sxxeyy(token: &str) -> Option<(i32, Vec<i32>)> {
+    if !token.starts_with('s') {
+        return None;
+    }
+
+    let mut parts = token[1..].split('e');
+    let season = parts.next().and_then(parse_number)?;
+    let mut episodes = Vec::new();
+
+    for part in parts {
+        episodes.push(parse_number(part)?);
+    }
+
+    Some((season, episodes))
+}
+
+fn parse_episode_token(token: &str) -> Option<i32> {
+    if token.starts_with('e') {
+        parse_number(&token[1..])
+    } else {
+        None
+    }
+}
+
+/// Parses the `1x02` style of episode marker.
+fn parse_nxnn(token: &str) -> Option<(i32, i32)> {
+    let mut parts = token.splitn(2, 'x');
+    let season = parts.next().and_then(parse_number)?;
+    let episode = parts.next().and_then(parse_number)?;
+
+    if season > 0 && token.len() >= 4 {
+        Some((season, episode))
+    } else {
+        None
+    }
+}
+
+fn parse_number(s: &str) -> Option<i32> {
+    if s.is_empty() || s.len() > 4 || !s.chars().all(|c| c.is_ascii_digit()) {
+        None
+    } else {
+        s.parse().ok()
+    }
+}
+
+fn parse_year(s: &str) -> Option<i32> {
+    if s.len() == 4 && (s.starts_with("19") || s.starts_with("20")) {
+        s.parse().ok()
+    } else {
+        None
+    }
+}
+
+fn parse_quality(tokens: &[String]) -> Quality {
+    let has = |needle: &str| tokens.iter().any(|t| t == needle);
+    let follows = |first: &str, second: &str| {
+        tokens
+            .windows(2)
+            .any(|w| w[0] == first && w[1] == second)
+    };
+
+    let resolution = if has("2160p") || has("4k") || has("uhd") {
+        Some(2160)
+    } else if has("1080p") || has("1080i") {
+        Some(1080)
+    } else if has("720p") {
+        Some(720)
+    } else if has("480p") || has("576p") {
+        Some(480)
+    } else {
+        None
+    };
+
+    let source = if has("bluray") || has("bdrip") || has("brrip") || follows("blu", "ray") {
+        Some(Source::Bluray)
+    } else if has("webdl") || has("webrip") || has("web") || has("amzn") || has("nf") {
+        Some(Source::Web)
+    } else if has("hdtv") {
+        Some(Source::HDTV)
+    } else if has("dvdrip") || has("dvd") {
+        Some(Source::DVD)
+    } else if has("sdtv") || has("pdtv") || has("dsr") {
+        Some(Source::SDTV)
+    } else {
+        None
+    };
+
+    match (source, resolution) {
+        (Some(Source::Bluray), Some(2160)) => Quality::Bluray2160p,
+        (Some(Source::Bluray), Some(1080)) => Quality::Bluray1080p,
+        (Some(Source::Bluray), _) => Quality::Bluray720p,
+        (Some(Source::Web), Some(2160)) => Quality::WEBDL2160p,
+        (Some(Source::Web), Some(1080)) => Quality::WEBDL1080p,
+        (Some(Source::Web), Some(720)) => Quality::WEBDL720p,
+        (Some(Source::Web), _) => Quality::WEBDL480p,
+        (Some(Source::DVD), _) => Quality::DVD,
+        (Some(Source::SDTV), _) => Quality::SDTV,
+        (Some(Source::HDTV), Some(2160)) | (None, Some(2160)) => Quality::HDTV2160p,
+        (Some(Source::HDTV), Some(1080)) | (None, Some(1080)) => Quality::HDTV1080p,
+        (Some(Source::HDTV), Some(720)) | (None, Some(720)) => Quality::HDTV720p,
+        (Some(Source::HDTV), _) | (None, Some(_)) => Quality::SDTV,
+        (None, None) => Quality::Unknown,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_standard_release() {
+        let parsed = parse("The.Expanse.S03E05.720p.WEB-DL.x264-GROUP").unwrap();
+        assert_eq!(parsed.title, "The Expanse");
+        assert_eq!(parsed.season, 3);
+        assert_eq!(parsed.episodes, vec![5]);
+        assert_eq!(parsed.quality, Quality::WEBDL720p);
+        assert_eq!(parsed.group, Some("GROUP".to_string()));
+    }
+
+    #[test]
+    fn parses_multi_episode_and_year() {
+        let parsed = parse("Doctor Who 2005 S10E01-E02 1080p HDTV").unwrap();
+        assert_eq!(parsed.title, "Doctor Who");
+        assert_eq!(parsed.year, Some(2005));
+        assert_eq!(parsed.episodes, vec![1, 2]);
+        assert_eq!(parsed.quality, Quality::HDTV1080p);
+    }
+
+    #[test]
+    fn parses_alternate_markers() {
+        let parsed = parse("show_name_2x07_hdtv.mkv").unwrap();
+        assert_eq!(parsed.title, "show name");
+        assert_eq!((parsed.season, parsed.episodes), (2, vec![7]));
+        assert_eq!(parsed.quality, Quality::SDTV);
+
+        let parsed = parse("Show.Name.Season.4.1080p.BluRay-GRP").unwrap();
+        assert!(parsed.is_season_pack());
+        assert_eq!(parsed.season, 4);
+        assert_eq!(parsed.quality, Quality::Bluray1080p);
+    }
+
+    #[test]
+    fn parses_names_without_title() {
+        let parsed = parse("S02E03 - Episode Name.mkv").unwrap();
+        assert_eq!(parsed.title, "");
+        assert_eq!((parsed.season, parsed.episodes), (2, vec![3]));
+    }
+
+    #[test]
+    fn rejects_names_without_marker() {
+        assert!(parse("Some.Movie.2018.1080p.BluRay").is_none());
+    }
+
+    #[test]
+    fn normalizes_titles() {
+        assert_eq!(
+            normalize_title("Marvel's Agents of S.H.I.E.L.D."),
+            normalize_title("Marvels.Agents.of.SHIELD")
+        );
+    }
+
+    #[test]
+    fn matches_titles_by_alias() {
+        let aliases = vec!["La casa de papel".to_string()];
+        assert!(title_matches("Money Heist", "Money Heist", &aliases));
+        assert!(title_matches("La.Casa.De.Papel", "Money Heist", &aliases));
+        assert!(!title_matches("Casa", "Money Heist", &aliases));
+        assert!(!title_matches("", "", &[]));
+    }
+}
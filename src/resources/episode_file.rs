@@ -0,0 +1,54 @@
+use db::{DBInsertable, ToSqlWrapper};
+use media::MediaInfo;
+use resources::quality::Quality;
+
+use chrono::{DateTime, Utc};
+use postgres::rows::Row;
+use postgres::types::ToSql;
+use serde_json;
+
+/// A video file in the library that holds an episode.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct EpisodeFile {
+    pub id: i32,
+    pub episode_id: i32,
+    pub path: String,
+    pub size: i64,
+    pub quality: Quality,
+    pub created_at: DateTime<Utc>,
+    /// What the file's container headers say it holds, if it could be
+    /// probed.
+    pub media_info: Option<MediaInfo>,
+}
+
+impl<'a> From<&'a Row<'a>> for EpisodeFile {
+    fn from(row: &'a Row) -> Self {
+        EpisodeFile {
+            id: row.get("file_id"),
+            episode_id: row.get("file_episode_id"),
+            path: row.get("file_path"),
+            size: row.get("file_size"),
+            quality: row.get("file_quality"),
+            created_at: row.get("file_created_at"),
+            media_info: row.get::<_, Option<serde_json::Value>>("file_media_info")
+                .and_then(|info| serde_json::from_value(info).ok()),
+        }
+    }
+}
+
+impl DBInsertable for EpisodeFile {
+    fn as_db_params(&self) -> Vec<ToSqlWrapper> {
+        let media_info = self.media_info
+            .as_ref()
+            .map(|info| serde_json::to_value(info).unwrap());
+
+        db_params![
+            self.episode_id,
+            self.path,
+            self.size,
+            self.quality,
+            self.created_at,
+            media_info
+        ]
+    }
+}
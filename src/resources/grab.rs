@@ -0,0 +1,45 @@
+use db::{DBInsertable, ToSqlWrapper};
+use resources::quality::Quality;
+
+use chrono::{DateTime, Utc};
+use postgres::rows::Row;
+use postgres::types::ToSql;
+
+/// A release that was sent to the download provider for an episode.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct EpisodeGrab {
+    pub id: i32,
+    pub episode_id: i32,
+    pub title: String,
+    pub quality: Quality,
+    pub indexer: String,
+    pub download_id: String,
+    pub grabbed_at: DateTime<Utc>,
+}
+
+impl<'a> From<&'a Row<'a>> for EpisodeGrab {
+    fn from(row: &'a Row) -> Self {
+        EpisodeGrab {
+            id: row.get("grab_id"),
+            episode_id: row.get("grab_episode_id"),
+            title: row.get("grab_title"),
+            quality: row.get("grab_quality"),
+            indexer: row.get("grab_indexer"),
+            download_id: row.get("grab_download_id"),
+            grabbed_at: row.get("grab_grabbed_at"),
+        }
+    }
+}
+
+impl DBInsertable for EpisodeGrab {
+    fn as_db_params(&self) -> Vec<ToSqlWrapper> {
+        db_params![
+            self.episode_id,
+            self.title,
+            self.quality,
+            self.indexer,
+            self.download_id,
+            self.grabbed_at
+        ]
+    }
+}
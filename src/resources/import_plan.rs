@@ -0,0 +1,69 @@
+use resources::quality::Quality;
+use resources::tv_show::TVShow;
+
+use chrono::{DateTime, Utc};
+use postgres::rows::Row;
+use serde_json;
+
+/// How a folder on disk was matched to a show.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MatchSource {
+    /// An external id in the folder name or its `tvshow.nfo`.
+    ExternalId,
+    /// A search for the folder's name.
+    Search,
+}
+
+/// An episode file found on disk and the episodes it was matched to.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PlannedFile {
+    pub path: String,
+    pub season: i32,
+    pub episodes: Vec<i32>,
+    pub quality: Quality,
+}
+
+/// A folder in a root folder and the show it would be imported as.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PlannedShow {
+    pub folder: String,
+    pub matched_by: Option<MatchSource>,
+    pub show: Option<TVShow>,
+    /// Set when the matched show is already in the library, in which case it
+    /// is skipped when the plan is confirmed.
+    pub existing_show_id: Option<i32>,
+    pub files: Vec<PlannedFile>,
+    pub unmatched_files: Vec<String>,
+    /// Why the folder couldn't be scanned or its show looked up, if that
+    /// failed. Such folders are kept in the plan without a show so that they
+    /// can be looked into.
+    #[serde(default)]
+    pub scan_error: Option<String>,
+}
+
+/// The result of scanning a root folder, waiting to be reviewed and
+/// confirmed before anything is added to the library.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ImportPlan {
+    pub id: i32,
+    pub root_folder_id: i32,
+    pub shows: Vec<PlannedShow>,
+    pub created_at: DateTime<Utc>,
+    pub confirmed_at: Option<DateTime<Utc>>,
+}
+
+impl<'a> From<&'a Row<'a>> for ImportPlan {
+    fn from(row: &'a Row) -> Self {
+        let shows: serde_json::Value = row.get("plan_shows");
+
+        ImportPlan {
+            id: row.get("plan_id"),
+            root_folder_id: row.get("plan_root_folder_id"),
+            // The column is only ever written from a `Vec<PlannedShow>`.
+            shows: serde_json::from_value(shows).unwrap_or_default(),
+            created_at: row.get("plan_created_at"),
+            confirmed_at: row.get("plan_confirmed_at"),
+        }
+    }
+}
@@ -1 +1,9 @@
+pub mod episode_file;
+pub mod grab;
+pub mod import_plan;
+pub mod page;
+pub mod person;
+pub mod quality;
+pub mod root_folder;
 pub mod tv_show;
+pub mod wanted;
@@ -0,0 +1,77 @@
+/// The largest page size a client may ask for.
+pub const MAX_PAGE_SIZE: i64 = 250;
+
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SortDirection {
+    Asc,
+    Desc,
+}
+
+impl SortDirection {
+    pub fn as_sql(&self) -> &'static str {
+        match *self {
+            SortDirection::Asc => "ASC",
+            SortDirection::Desc => "DESC",
+        }
+    }
+}
+
+/// A 1-based page request, clamped to sane bounds.
+#[derive(Clone, Copy, Debug)]
+pub struct Paging {
+    pub page: i64,
+    pub page_size: i64,
+    pub sort_dir: SortDirection,
+}
+
+impl Paging {
+    pub fn new(
+        page: Option<i64>,
+        page_size: Option<i64>,
+        sort_dir: Option<SortDirection>,
+    ) -> Paging {
+        Paging {
+            page: page.unwrap_or(1).max(1),
+            page_size: page_size.unwrap_or(20).max(1).min(MAX_PAGE_SIZE),
+            sort_dir: sort_dir.unwrap_or(SortDirection::Desc),
+        }
+    }
+
+    /// Saturates for pages too far out to exist, which then come back empty.
+    pub fn offset(&self) -> i64 {
+        (self.page - 1).saturating_mul(self.page_size)
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Page<T> {
+    pub page: i64,
+    pub page_size: i64,
+    pub total: i64,
+    pub records: Vec<T>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clamps_pages_and_page_sizes() {
+        let paging = Paging::new(None, None, None);
+        assert_eq!((paging.page, paging.page_size, paging.offset()), (1, 20, 0));
+
+        let paging = Paging::new(Some(-3), Some(0), Some(SortDirection::Asc));
+        assert_eq!((paging.page, paging.page_size, paging.offset()), (1, 1, 0));
+
+        let paging = Paging::new(Some(3), Some(1000), None);
+        assert_eq!((paging.page, paging.page_size), (3, MAX_PAGE_SIZE));
+        assert_eq!(paging.offset(), 2 * MAX_PAGE_SIZE);
+    }
+
+    #[test]
+    fn saturates_offsets_of_huge_pages() {
+        let paging = Paging::new(Some(i64::max_value()), Some(MAX_PAGE_SIZE), None);
+        assert_eq!(paging.offset(), i64::max_value());
+    }
+}
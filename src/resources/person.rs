@@ -0,0 +1,124 @@
+use db::{DBInsertable, ToSqlWrapper};
+use resources::tv_show::TVMetadataProvider;
+
+use chrono::NaiveDate;
+use postgres::rows::Row;
+use postgres::types::ToSql;
+
+/// An actor or crew member of shows in the library.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Person {
+    pub id: i32,
+    pub name: String,
+    pub image: Option<String>,
+    pub birthday: Option<NaiveDate>,
+    pub deathday: Option<NaiveDate>,
+    pub gender: Option<String>,
+    pub country: Option<String>,
+    pub provider: TVMetadataProvider,
+    pub provider_id: String,
+    pub provider_url: String,
+}
+
+/// A person playing a character in a show.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CastMember {
+    pub person: Person,
+    pub character: String,
+    pub character_img: Option<String>,
+}
+
+/// A person working on a show, with their job such as "Creator".
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CrewMember {
+    pub person: Person,
+    pub job: String,
+}
+
+/// A show's cast, in the order they are billed, and crew.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct ShowCredits {
+    pub cast: Vec<CastMember>,
+    pub crew: Vec<CrewMember>,
+}
+
+impl ShowCredits {
+    pub fn is_empty(&self) -> bool {
+        self.cast.is_empty() && self.crew.is_empty()
+    }
+}
+
+/// A show in the library that a person appears in, with the characters they
+/// play and the jobs they have in it.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PersonShow {
+    pub show_id: i32,
+    pub name: String,
+    pub cover_img: Option<String>,
+    pub characters: Vec<String>,
+    pub jobs: Vec<String>,
+}
+
+impl<'a> From<&'a Row<'a>> for Person {
+    fn from(row: &'a Row) -> Self {
+        Person {
+            id: row.get("person_id"),
+            name: row.get("person_name"),
+            image: row.get("person_image"),
+            birthday: row.get("person_birthday"),
+            deathday: row.get("person_deathday"),
+            gender: row.get("person_gender"),
+            country: row.get("person_country"),
+            provider: row.get("person_provider"),
+            provider_id: row.get("person_provider_id"),
+            provider_url: row.get("person_provider_url"),
+        }
+    }
+}
+
+impl DBInsertable for Person {
+    fn as_db_params(&self) -> Vec<ToSqlWrapper> {
+        db_params![
+            self.name,
+            self.image,
+            self.birthday,
+            self.deathday,
+            self.gender,
+            self.country,
+            self.provider,
+            self.provider_id,
+            self.provider_url
+        ]
+    }
+}
+
+impl<'a> From<&'a Row<'a>> for CastMember {
+    fn from(row: &'a Row) -> Self {
+        CastMember {
+            person: Person::from(row),
+            character: row.get("cast_character"),
+            character_img: row.get("cast_character_img"),
+        }
+    }
+}
+
+impl<'a> From<&'a Row<'a>> for CrewMember {
+    fn from(row: &'a Row) -> Self {
+        CrewMember {
+            person: Person::from(row),
+            job: row.get("crew_job"),
+        }
+    }
+}
+
+impl<'a> From<&'a Row<'a>> for PersonShow {
+    fn from(row: &'a Row) -> Self {
+        PersonShow {
+            show_id: row.get("show_id"),
+            name: row.get("show_name"),
+            cover_img: row.get("show_cover_img"),
+            characters: row.get("person_characters"),
+            jobs: row.get("person_jobs"),
+        }
+    }
+}
@@ -0,0 +1,80 @@
+use postgres::rows::Row;
+
+/// The id of the profile seeded by the migrations, used for new shows when no
+/// profile is given.
+pub const DEFAULT_QUALITY_PROFILE_ID: i32 = 1;
+
+/// The quality of a release, ordered from worst to best.
+///
+/// The variant order matches the `quality` enum in Postgres, so comparisons
+/// done in Rust and in SQL agree with each other.
+#[derive(
+    Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, ToSql,
+    FromSql,
+)]
+#[postgres(name = "quality")]
+#[serde(rename_all = "lowercase")]
+pub enum Quality {
+    #[postgres(name = "unknown")]
+    Unknown,
+    #[postgres(name = "sdtv")]
+    SDTV,
+    #[postgres(name = "dvd")]
+    DVD,
+    #[postgres(name = "webdl480p")]
+    WEBDL480p,
+    #[postgres(name = "hdtv720p")]
+    HDTV720p,
+    #[postgres(name = "webdl720p")]
+    WEBDL720p,
+    #[postgres(name = "bluray720p")]
+    Bluray720p,
+    #[postgres(name = "hdtv1080p")]
+    HDTV1080p,
+    #[postgres(name = "webdl1080p")]
+    WEBDL1080p,
+    #[postgres(name = "bluray1080p")]
+    Bluray1080p,
+    #[postgres(name = "hdtv2160p")]
+    HDTV2160p,
+    #[postgres(name = "webdl2160p")]
+    WEBDL2160p,
+    #[postgres(name = "bluray2160p")]
+    Bluray2160p,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct QualityProfile {
+    pub id: i32,
+    pub name: String,
+    pub allowed: Vec<Quality>,
+    pub cutoff: Quality,
+}
+
+impl QualityProfile {
+    pub fn allows(&self, quality: Quality) -> bool {
+        self.allowed.contains(&quality)
+    }
+
+    /// Returns whether an episode whose best copy is `current` should still
+    /// be searched for.
+    pub fn wants(&self, current: Option<Quality>) -> bool {
+        current.map_or(true, |current| current < self.cutoff)
+    }
+
+    /// Returns whether `candidate` is allowed and better than `current`.
+    pub fn is_upgrade(&self, current: Option<Quality>, candidate: Quality) -> bool {
+        self.allows(candidate) && current.map_or(true, |current| candidate > current)
+    }
+}
+
+impl<'a> From<&'a Row<'a>> for QualityProfile {
+    fn from(row: &'a Row) -> Self {
+        QualityProfile {
+            id: row.get("profile_id"),
+            name: row.get("profile_name"),
+            allowed: row.get("profile_allowed"),
+            cutoff: row.get("profile_cutoff"),
+        }
+    }
+}
@@ -0,0 +1,60 @@
+use std::fs;
+use std::path::Path;
+
+use naming::show_folder_name;
+use resources::tv_show::TVShow;
+
+use fs2;
+use postgres::rows::Row;
+
+/// A folder that show folders are created in.
+///
+/// `free_space` and `accessible` aren't stored, they are filled in by
+/// `check_disk` whenever a root folder is returned to a client.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RootFolder {
+    pub id: i32,
+    pub path: String,
+    pub free_space: Option<u64>,
+    pub accessible: bool,
+}
+
+impl RootFolder {
+    /// Fills in the free space and whether the folder is a writable
+    /// directory.
+    pub fn check_disk(mut self) -> RootFolder {
+        self.accessible = is_accessible(&self.path);
+        self.free_space = if self.accessible {
+            fs2::available_space(&self.path).ok()
+        } else {
+            None
+        };
+        self
+    }
+
+    /// The folder a show would be stored in inside this root folder.
+    pub fn show_path(&self, template: &str, show: &TVShow) -> String {
+        Path::new(&self.path)
+            .join(show_folder_name(template, show))
+            .to_string_lossy()
+            .into_owned()
+    }
+}
+
+/// Returns whether `path` is an existing directory that isn't read-only.
+pub fn is_accessible(path: &str) -> bool {
+    fs::metadata(path)
+        .map(|m| m.is_dir() && !m.permissions().readonly())
+        .unwrap_or(false)
+}
+
+impl<'a> From<&'a Row<'a>> for RootFolder {
+    fn from(row: &'a Row) -> Self {
+        RootFolder {
+            id: row.get("root_folder_id"),
+            path: row.get("root_folder_path"),
+            free_space: None,
+            accessible: false,
+        }
+    }
+}
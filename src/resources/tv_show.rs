@@ -1,27 +1,155 @@
+use std::collections::BTreeMap;
+
 use db::{DBInsertable, ToSqlWrapper};
 
+use chrono::{Datelike, NaiveDate};
 use postgres::rows::Row;
 use postgres::types::ToSql;
+use serde::ser::{Error, Serialize, SerializeMap, Serializer};
+use serde_json::{self, Value};
 
-#[derive(Clone, Debug, Serialize, Deserialize, ToSql, FromSql)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize, ToSql, FromSql)]
 #[postgres(name = "tv_metadata_provider")]
 pub enum TVMetadataProvider {
     #[postgres(name = "tvmaze")]
     TVMaze,
+    #[postgres(name = "thetvdb")]
+    TheTVDB,
+    /// Read from the NFO files in the show's own folder.
+    #[postgres(name = "local")]
+    Local,
+}
+
+/// An id of a show in a database other than the one it was added from.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExternalId {
+    IMDB(String),
+    TheTVDB(u64),
+    TVRage(u64),
 }
 
+/// The ids of a show in other databases, as far as they are known.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct ExternalIds {
+    pub imdb: Option<String>,
+    pub thetvdb: Option<i64>,
+    pub tvrage: Option<i64>,
+}
+
+/// The providers that the fields of a show, season or episode were filled in
+/// from, by field name. Fields that aren't listed come from the record's own
+/// provider. See `CompositeProvider`.
+pub type MetadataSources = BTreeMap<String, TVMetadataProvider>;
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct TVShow {
     pub id: i32,
     pub name: String,
     pub summary: Option<String>,
     pub cover_img: Option<String>,
+    pub fanart_img: Option<String>,
+    pub genres: Vec<String>,
+    /// The usual runtime of an episode in minutes.
+    pub runtime: Option<i32>,
+    pub premiered: Option<NaiveDate>,
+    pub external_ids: ExternalIds,
     pub provider: TVMetadataProvider,
     pub provider_id: String,
     pub provider_url: String,
+    /// When the show last changed at its provider, as a unix timestamp, if
+    /// the provider keeps track. See `MetadataProvider::get_show_updates`.
+    #[serde(default)]
+    pub provider_updated: Option<i64>,
+    pub monitored: bool,
+    pub quality_profile_id: i32,
+    pub root_folder_id: Option<i32>,
+    pub path: Option<String>,
+    /// Other titles of the show, such as its localized names, that releases
+    /// may be named after.
+    #[serde(default)]
+    pub aliases: Vec<String>,
+    #[serde(default)]
+    pub metadata_sources: MetadataSources,
     pub seasons: Option<Vec<TVShowSeason>>,
 }
 
+/// A show found by a search, with the details that tell apart shows of the
+/// same name. Serialized as the show's fields along with its own, without the
+/// show's id, which is only a placeholder. Shows in the library have their
+/// `library_id` instead.
+#[derive(Clone, Debug)]
+pub struct ShowSearchResult {
+    pub show: TVShow,
+    /// How well the show matches the search, higher being better. The scale
+    /// depends on where the result came from.
+    pub score: Option<f64>,
+    /// The year the show premiered.
+    pub year: Option<i32>,
+    /// The network or streaming service the show airs on.
+    pub network: Option<String>,
+    /// Whether the show is still running, in the provider's own words.
+    pub status: Option<String>,
+    /// The id of the show in the library, if it was added already.
+    pub library_id: Option<i32>,
+    pub in_library: bool,
+}
+
+impl ShowSearchResult {
+    pub fn new(
+        show: TVShow,
+        score: Option<f64>,
+        network: Option<String>,
+        status: Option<String>,
+    ) -> ShowSearchResult {
+        ShowSearchResult {
+            year: show.premiered.map(|premiered| premiered.year()),
+            show,
+            score,
+            network,
+            status,
+            library_id: None,
+            in_library: false,
+        }
+    }
+
+    pub fn set_library_id(&mut self, library_id: Option<i32>) {
+        self.library_id = library_id;
+        self.in_library = library_id.is_some();
+    }
+}
+
+impl Serialize for ShowSearchResult {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let show = match serde_json::to_value(&self.show).map_err(S::Error::custom)? {
+            Value::Object(show) => show,
+            _ => return Err(S::Error::custom("a show has to serialize to an object")),
+        };
+
+        let mut map = serializer.serialize_map(None)?;
+        for (key, value) in show.iter().filter(|&(key, _)| key != "id") {
+            map.serialize_entry(key, value)?;
+        }
+        map.serialize_entry("score", &self.score)?;
+        map.serialize_entry("year", &self.year)?;
+        map.serialize_entry("network", &self.network)?;
+        map.serialize_entry("status", &self.status)?;
+        map.serialize_entry("library_id", &self.library_id)?;
+        map.serialize_entry("in_library", &self.in_library)?;
+        map.end()
+    }
+}
+
+/// An alternate title of a show. Aliases from the show's providers are
+/// replaced whenever it is refreshed, while user defined ones are kept.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ShowAlias {
+    pub id: i32,
+    pub show_id: i32,
+    pub name: String,
+    pub user_defined: bool,
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct TVShowSeason {
     pub id: i32,
@@ -32,6 +160,8 @@ pub struct TVShowSeason {
     pub cover_img: Option<String>,
     pub provider_id: String,
     pub provider_url: String,
+    #[serde(default)]
+    pub metadata_sources: MetadataSources,
     pub episodes: Option<Vec<TVShowEpisode>>,
 }
 
@@ -46,8 +176,23 @@ pub struct TVShowEpisode {
     pub summary: Option<String>,
     pub cover_img: Option<String>,
     pub runtime: Option<i32>,
+    pub airdate: Option<NaiveDate>,
+    pub monitored: bool,
     pub provider_id: String,
     pub provider_url: String,
+    #[serde(default)]
+    pub metadata_sources: MetadataSources,
+}
+
+impl TVShow {
+    /// Adds aliases that aren't the show's name or one of its aliases yet.
+    pub fn add_aliases<I: IntoIterator<Item = String>>(&mut self, aliases: I) {
+        for alias in aliases {
+            if alias != self.name && !self.aliases.contains(&alias) {
+                self.aliases.push(alias);
+            }
+        }
+    }
 }
 
 impl<'a> From<&'a Row<'a>> for TVShow {
@@ -57,9 +202,25 @@ impl<'a> From<&'a Row<'a>> for TVShow {
             name: row.get("show_name"),
             summary: row.get("show_summary"),
             cover_img: row.get("show_cover_img"),
+            fanart_img: row.get("show_fanart_img"),
+            genres: row.get("show_genres"),
+            runtime: row.get("show_runtime"),
+            premiered: row.get("show_premiered"),
+            external_ids: ExternalIds {
+                imdb: row.get("show_imdb_id"),
+                thetvdb: row.get("show_thetvdb_id"),
+                tvrage: row.get("show_tvrage_id"),
+            },
             provider: row.get("show_provider"),
             provider_id: row.get("show_provider_id"),
             provider_url: row.get("show_provider_url"),
+            provider_updated: row.get("show_provider_updated"),
+            monitored: row.get("show_monitored"),
+            quality_profile_id: row.get("show_quality_profile_id"),
+            root_folder_id: row.get("show_root_folder_id"),
+            path: row.get("show_path"),
+            aliases: get_aliases(row),
+            metadata_sources: get_metadata_sources(row, "show_metadata_sources"),
             seasons: None,
         }
     }
@@ -73,7 +234,20 @@ impl DBInsertable for TVShow {
             self.cover_img,
             self.provider,
             self.provider_id,
-            self.provider_url
+            self.provider_url,
+            self.monitored,
+            self.quality_profile_id,
+            self.root_folder_id,
+            self.path,
+            self.fanart_img,
+            self.genres,
+            self.runtime,
+            self.premiered,
+            self.external_ids.imdb,
+            self.external_ids.thetvdb,
+            self.external_ids.tvrage,
+            metadata_sources_value(&self.metadata_sources),
+            self.provider_updated
         ]
     }
 }
@@ -89,6 +263,7 @@ impl<'a> From<&'a Row<'a>> for TVShowSeason {
             cover_img: row.get("season_cover_img"),
             provider_id: row.get("season_provider_id"),
             provider_url: row.get("season_provider_url"),
+            metadata_sources: get_metadata_sources(row, "season_metadata_sources"),
             episodes: None,
         }
     }
@@ -103,7 +278,8 @@ impl DBInsertable for TVShowSeason {
             self.summary,
             self.cover_img,
             self.provider_id,
-            self.provider_url
+            self.provider_url,
+            metadata_sources_value(&self.metadata_sources)
         ]
     }
 }
@@ -120,8 +296,11 @@ impl<'a> From<&'a Row<'a>> for TVShowEpisode {
             summary: row.get("episode_summary"),
             cover_img: row.get("episode_cover_img"),
             runtime: row.get("episode_runtime"),
+            airdate: row.get("episode_airdate"),
+            monitored: row.get("episode_monitored"),
             provider_id: row.get("episode_provider_id"),
             provider_url: row.get("episode_provider_url"),
+            metadata_sources: get_metadata_sources(row, "episode_metadata_sources"),
         }
     }
 }
@@ -137,8 +316,41 @@ impl DBInsertable for TVShowEpisode {
             self.summary,
             self.cover_img,
             self.runtime,
+            self.airdate,
+            self.monitored,
             self.provider_id,
-            self.provider_url
+            self.provider_url,
+            metadata_sources_value(&self.metadata_sources)
         ]
     }
 }
+
+impl<'a> From<&'a Row<'a>> for ShowAlias {
+    fn from(row: &'a Row) -> Self {
+        ShowAlias {
+            id: row.get("alias_id"),
+            show_id: row.get("alias_show_id"),
+            name: row.get("alias_name"),
+            user_defined: row.get("alias_user_defined"),
+        }
+    }
+}
+
+/// Aliases are stored separately, so rows returned by inserts and updates of
+/// a show don't have them.
+fn get_aliases(row: &Row) -> Vec<String> {
+    row.get_opt::<_, Vec<String>>("show_aliases")
+        .and_then(|aliases| aliases.ok())
+        .unwrap_or_default()
+}
+
+/// Seasons and episodes are left joined, so their sources can be NULL.
+fn get_metadata_sources(row: &Row, column: &str) -> MetadataSources {
+    row.get::<_, Option<serde_json::Value>>(column)
+        .and_then(|sources| serde_json::from_value(sources).ok())
+        .unwrap_or_default()
+}
+
+pub fn metadata_sources_value(sources: &MetadataSources) -> serde_json::Value {
+    serde_json::to_value(sources).unwrap()
+}
@@ -0,0 +1,82 @@
+use std::cmp;
+
+use resources::quality::{Quality, QualityProfile};
+
+use resources::page::SortDirection;
+
+use chrono::NaiveDate;
+use postgres::rows::Row;
+
+/// What pages of wanted episodes are sorted by.
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WantedSortKey {
+    Airdate,
+    Show,
+}
+
+impl WantedSortKey {
+    /// The `ORDER BY` clause for sorting by this key in `dir`. Ties are broken
+    /// in a fixed order so that pages don't overlap.
+    pub fn order_by(&self, dir: SortDirection) -> String {
+        match *self {
+            WantedSortKey::Airdate => format!(
+                " ORDER BY E.airdate {}, S.name, E.season_num, E.num",
+                dir.as_sql()
+            ),
+            WantedSortKey::Show => format!(
+                " ORDER BY S.name {dir}, E.season_num {dir}, E.num {dir}, E.id",
+                dir = dir.as_sql()
+            ),
+        }
+    }
+}
+
+/// An episode together with everything needed to search for it: the show's
+/// name and aliases, its quality profile and the qualities already on disk or grabbed.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct WantedEpisode {
+    pub episode_id: i32,
+    pub show_id: i32,
+    pub season_id: i32,
+    pub show_name: String,
+    #[serde(default)]
+    pub show_aliases: Vec<String>,
+    pub season_num: i32,
+    pub num: Option<i32>,
+    pub name: String,
+    pub airdate: Option<NaiveDate>,
+    pub profile: QualityProfile,
+    pub file_quality: Option<Quality>,
+    pub grab_quality: Option<Quality>,
+}
+
+impl WantedEpisode {
+    /// The best quality that is either on disk or already being downloaded.
+    pub fn current_quality(&self) -> Option<Quality> {
+        cmp::max(self.file_quality, self.grab_quality)
+    }
+
+    pub fn is_wanted(&self) -> bool {
+        self.num.is_some() && self.profile.wants(self.current_quality())
+    }
+}
+
+impl<'a> From<&'a Row<'a>> for WantedEpisode {
+    fn from(row: &'a Row) -> Self {
+        WantedEpisode {
+            episode_id: row.get("episode_id"),
+            show_id: row.get("episode_show_id"),
+            season_id: row.get("episode_season_id"),
+            show_name: row.get("show_name"),
+            show_aliases: row.get("show_aliases"),
+            season_num: row.get("episode_season_num"),
+            num: row.get("episode_num"),
+            name: row.get("episode_name"),
+            airdate: row.get("episode_airdate"),
+            profile: QualityProfile::from(row),
+            file_quality: row.get("file_quality"),
+            grab_quality: row.get("grab_quality"),
+        }
+    }
+}
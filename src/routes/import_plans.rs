@@ -0,0 +1,60 @@
+use std::rc::Rc;
+
+use db::import_plan::get_import_plan;
+use db::root_folder::get_root_folder;
+use error::WebError;
+use library::scanner::{confirm_import_plan, create_import_plan};
+use resources::tv_show::TVMetadataProvider;
+use AppState;
+
+use actix_web::{AsyncResponder, HttpResponse, Json, Path, State};
+use futures::Future;
+
+#[derive(Deserialize)]
+pub struct ImportPlanPath {
+    id: i32,
+}
+
+#[derive(Deserialize)]
+pub struct ConfirmImportPlan {
+    folders: Vec<String>,
+}
+
+pub fn root_folders_scan(
+    (state, req): (State<AppState>, Path<ImportPlanPath>),
+) -> Box<Future<Item = HttpResponse, Error = WebError>> {
+    // Folders are identified by searching TVmaze for their names.
+    let provider = state.metadata_providers.get(TVMetadataProvider::TVMaze);
+    let pool = state.db_pool.clone();
+
+    get_root_folder(req.id, state.db_pool.clone())
+        .and_then(move |folder| provider.map(|provider| (folder, provider)))
+        .and_then(move |(folder, provider)| create_import_plan(folder, provider, pool))
+        .and_then(|plan| Ok(HttpResponse::Ok().json(plan)))
+        .responder()
+}
+
+pub fn import_plans_get(
+    (state, req): (State<AppState>, Path<ImportPlanPath>),
+) -> Box<Future<Item = HttpResponse, Error = WebError>> {
+    get_import_plan(req.id, state.db_pool.clone())
+        .and_then(|plan| Ok(HttpResponse::Ok().json(plan)))
+        .responder()
+}
+
+pub fn import_plans_confirm(
+    (state, req, body): (State<AppState>, Path<ImportPlanPath>, Json<ConfirmImportPlan>),
+) -> Box<Future<Item = HttpResponse, Error = WebError>> {
+    let providers = state.metadata_providers.clone();
+    let config = Rc::new(state.config.clone());
+
+    confirm_import_plan(
+        req.id,
+        body.into_inner().folders,
+        providers,
+        config,
+        state.db_pool.clone(),
+    )
+        .and_then(|shows| Ok(HttpResponse::Ok().json(shows)))
+        .responder()
+}
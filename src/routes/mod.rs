@@ -1,5 +1,17 @@
+mod episodes;
+mod import_plans;
+mod people;
+mod root_folders;
 mod search;
+mod seasons;
 mod shows;
+mod wanted;
 
+pub use self::episodes::*;
+pub use self::import_plans::*;
+pub use self::people::*;
+pub use self::root_folders::*;
 pub use self::search::search;
+pub use self::seasons::*;
 pub use self::shows::*;
+pub use self::wanted::*;
@@ -0,0 +1,31 @@
+use db::person::{get_person, get_person_shows};
+use error::WebError;
+use AppState;
+
+use actix_web::{AsyncResponder, HttpResponse, Path, State};
+use futures::Future;
+
+#[derive(Deserialize)]
+pub struct GetPerson {
+    id: i32,
+}
+
+pub fn people_get(
+    (state, req): (State<AppState>, Path<GetPerson>),
+) -> Box<Future<Item = HttpResponse, Error = WebError>> {
+    get_person(req.id, state.db_pool.clone())
+        .and_then(|person| Ok(HttpResponse::Ok().json(person)))
+        .responder()
+}
+
+/// Lists the shows in the library that a person appears in.
+pub fn people_shows(
+    (state, req): (State<AppState>, Path<GetPerson>),
+) -> Box<Future<Item = HttpResponse, Error = WebError>> {
+    let pool = state.db_pool.clone();
+
+    get_person(req.id, state.db_pool.clone())
+        .and_then(move |person| get_person_shows(person.id, pool))
+        .and_then(|shows| Ok(HttpResponse::Ok().json(shows)))
+        .responder()
+}
@@ -0,0 +1,89 @@
+use std::path::Path as FsPath;
+
+use db::root_folder::*;
+use error::WebError;
+use resources::root_folder::is_accessible;
+use AppState;
+
+use actix_web::{AsyncResponder, Form, HttpResponse, Path, State};
+use futures::{future, Future};
+
+#[derive(Deserialize)]
+pub struct AddRootFolder {
+    path: String,
+}
+
+#[derive(Deserialize)]
+pub struct RootFolderPath {
+    id: i32,
+}
+
+pub fn root_folders_all(
+    state: State<AppState>,
+) -> Box<Future<Item = HttpResponse, Error = WebError>> {
+    get_all_root_folders(state.db_pool.clone())
+        .and_then(|folders| {
+            let folders: Vec<_> = folders.into_iter().map(|f| f.check_disk()).collect();
+            Ok(HttpResponse::Ok().json(folders))
+        })
+        .responder()
+}
+
+pub fn root_folders_get(
+    (state, req): (State<AppState>, Path<RootFolderPath>),
+) -> Box<Future<Item = HttpResponse, Error = WebError>> {
+    get_root_folder(req.id, state.db_pool.clone())
+        .and_then(|folder| Ok(HttpResponse::Ok().json(folder.check_disk())))
+        .responder()
+}
+
+/// Checks that `path` can be used as a root folder.
+fn validate_path(path: &str) -> Result<(), WebError> {
+    if !FsPath::new(path).is_absolute() {
+        return Err(WebError::InvalidRequest(format!(
+            "Root folder {} is not an absolute path",
+            path
+        )));
+    }
+
+    if !is_accessible(path) {
+        return Err(WebError::InvalidRequest(format!(
+            "Root folder {} does not exist or is not writable",
+            path
+        )));
+    }
+
+    Ok(())
+}
+
+pub fn root_folders_add(
+    (state, form): (State<AppState>, Form<AddRootFolder>),
+) -> Box<Future<Item = HttpResponse, Error = WebError>> {
+    if let Err(err) = validate_path(&form.path) {
+        return Box::new(future::err(err));
+    }
+
+    insert_root_folder(form.path.clone(), state.db_pool.clone())
+        .and_then(|folder| Ok(HttpResponse::Ok().json(folder.check_disk())))
+        .responder()
+}
+
+pub fn root_folders_update(
+    (state, req, form): (State<AppState>, Path<RootFolderPath>, Form<AddRootFolder>),
+) -> Box<Future<Item = HttpResponse, Error = WebError>> {
+    if let Err(err) = validate_path(&form.path) {
+        return Box::new(future::err(err));
+    }
+
+    update_root_folder(req.id, form.path.clone(), state.db_pool.clone())
+        .and_then(|folder| Ok(HttpResponse::Ok().json(folder.check_disk())))
+        .responder()
+}
+
+pub fn root_folders_delete(
+    (state, req): (State<AppState>, Path<RootFolderPath>),
+) -> Box<Future<Item = HttpResponse, Error = WebError>> {
+    delete_root_folder(req.id, state.db_pool.clone())
+        .and_then(|_| Ok(HttpResponse::NoContent().finish()))
+        .responder()
+}
@@ -1,25 +1,30 @@
 use error::WebError;
-use providers::metadata::MetadataProvider;
+use library::catalog::{search_limit, search_shows};
+use resources::tv_show::TVMetadataProvider;
 use AppState;
 
 use actix_web::{AsyncResponder, HttpResponse, Query, State};
-use futures::Future;
+use futures::{future, Future};
 
 #[derive(Deserialize)]
 pub struct Search {
     q: String,
+    provider: Option<TVMetadataProvider>,
+    limit: Option<usize>,
 }
 
 pub fn search(
     (state, search_q): (State<AppState>, Query<Search>),
 ) -> Box<Future<Item = HttpResponse, Error = WebError>> {
-    Box::new(
-        state
-            .provider
-            .search(&search_q.q)
-            .unwrap()
-            .and_then(|results| Ok(HttpResponse::Ok().json(&results)))
-            .map_err(WebError::from)
-            .responder(),
-    )
+    let kind = search_q.provider.unwrap_or(TVMetadataProvider::TVMaze);
+    let limit = search_limit(search_q.limit);
+    let mirrored = state.config.catalog_providers.contains(&kind);
+    let pool = state.db_pool.clone();
+
+    future::result(state.metadata_providers.get(kind))
+        .and_then(move |provider| {
+            search_shows(kind, provider, &search_q.q, limit, mirrored, pool)
+        })
+        .and_then(|results| Ok(HttpResponse::Ok().json(&results)))
+        .responder()
 }
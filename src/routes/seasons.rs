@@ -0,0 +1,24 @@
+use db::episode::*;
+use error::WebError;
+use library::search::grab_episodes;
+use AppState;
+
+use actix_web::{AsyncResponder, HttpResponse, Path, State};
+use futures::Future;
+
+#[derive(Deserialize)]
+pub struct SeasonSearch {
+    id: i32,
+}
+
+pub fn seasons_search(
+    (state, req): (State<AppState>, Path<SeasonSearch>),
+) -> Box<Future<Item = HttpResponse, Error = WebError>> {
+    let searcher = state.searcher.clone();
+    let pool = state.db_pool.clone();
+
+    get_wanted_season_episodes(req.id, state.db_pool.clone())
+        .and_then(move |episodes| grab_episodes(searcher, episodes, pool))
+        .and_then(|grabs| Ok(HttpResponse::Ok().json(grabs)))
+        .responder()
+}
@@ -1,15 +1,31 @@
+use std::rc::Rc;
+
+use db::person::{get_show_cast, get_show_crew};
+use db::quality_profile::get_quality_profile;
+use db::root_folder::get_root_folder;
 use db::tv_show::*;
 use error::WebError;
+use library::metadata::try_update_show_metadata;
+use library::show::{load_full_show, refresh_show, try_refresh_show_credits};
 use providers::metadata::MetadataProvider;
-use resources::tv_show::*;
+use resources::root_folder::RootFolder;
+use resources::tv_show::{ExternalId, TVMetadataProvider};
 use AppState;
 
-use actix_web::{AsyncResponder, Form, HttpResponse, Path, ResponseError, State};
+use actix_web::{AsyncResponder, Form, HttpResponse, Path, Query, ResponseError, State};
 use futures::{future, Future};
 
 #[derive(Deserialize)]
 pub struct AddShow {
-    id: String,
+    /// The show's id with `provider`, which for local shows is their folder.
+    /// Can be left out if the show is added by an id in another database.
+    id: Option<String>,
+    imdb: Option<String>,
+    thetvdb: Option<u64>,
+    tvrage: Option<u64>,
+    provider: Option<TVMetadataProvider>,
+    quality_profile_id: Option<i32>,
+    root_folder_id: Option<i32>,
 }
 
 #[derive(Deserialize)]
@@ -17,6 +33,42 @@ pub struct GetShow {
     id: i32,
 }
 
+#[derive(Deserialize)]
+pub struct ListShows {
+    /// Only lists shows whose name or one of whose aliases contains `q`.
+    q: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct AddAlias {
+    name: String,
+}
+
+#[derive(Deserialize)]
+pub struct GetAlias {
+    id: i32,
+    alias_id: i32,
+}
+
+#[derive(Deserialize)]
+pub struct LookupShow {
+    imdb: Option<String>,
+    thetvdb: Option<u64>,
+    tvrage: Option<u64>,
+}
+
+/// Picks the first of the given ids in other databases.
+fn external_id(
+    imdb: &Option<String>,
+    thetvdb: Option<u64>,
+    tvrage: Option<u64>,
+) -> Option<ExternalId> {
+    imdb.clone()
+        .map(ExternalId::IMDB)
+        .or_else(|| thetvdb.map(ExternalId::TheTVDB))
+        .or_else(|| tvrage.map(ExternalId::TVRage))
+}
+
 pub fn shows_get(
     (state, req): (State<AppState>, Path<GetShow>),
 ) -> Box<Future<Item = HttpResponse, Error = WebError>> {
@@ -28,9 +80,16 @@ pub fn shows_get(
     )
 }
 
-pub fn shows_all(state: State<AppState>) -> Box<Future<Item = HttpResponse, Error = WebError>> {
+pub fn shows_all(
+    (state, query): (State<AppState>, Query<ListShows>),
+) -> Box<Future<Item = HttpResponse, Error = WebError>> {
+    let shows = match query.q {
+        Some(ref q) => search_shows(q, state.db_pool.clone()),
+        None => get_all_shows(state.db_pool.clone()),
+    };
+
     Box::new(
-        get_all_shows(state.db_pool.clone())
+        shows
             .and_then(|shows| match shows {
                 Ok(shows) => Ok(HttpResponse::Ok().json(shows)),
                 Err(e) => Ok(e.error_response()),
@@ -39,51 +98,207 @@ pub fn shows_all(state: State<AppState>) -> Box<Future<Item = HttpResponse, Erro
     )
 }
 
+/// The id of the show to add with `provider`, which is looked up if it was
+/// only given by an id in another database.
+fn find_provider_id(
+    provider: &MetadataProvider,
+    id: Option<String>,
+    external_id: Option<ExternalId>,
+) -> Box<Future<Item = String, Error = WebError>> {
+    match (id, external_id) {
+        (Some(id), _) => Box::new(future::ok(id)),
+        (None, Some(external_id)) => {
+            let missing = external_id.clone();
+            Box::new(provider.lookup_show(&external_id).and_then(move |show| {
+                show.map(|show| show.provider_id)
+                    .ok_or(WebError::ExternalIdNotFound(missing))
+            }))
+        }
+        (None, None) => Box::new(future::err(WebError::InvalidRequest(
+            "Either a show id or an imdb, thetvdb or tvrage id is required".to_string(),
+        ))),
+    }
+}
+
+/// Adds a show to the library, or responds with a conflict and the id of the
+/// show if it's already there.
 pub fn shows_add(
     (state, form): (State<AppState>, Form<AddShow>),
 ) -> Box<Future<Item = HttpResponse, Error = WebError>> {
-    let provider = state.provider.clone();
-    let load_show = provider.get_show(&form.id).unwrap().map_err(WebError::from);
-
-    let provider = state.provider.clone();
-    let load_seasons = load_show.and_then(move |show| {
-        provider
-            .get_show_seasons(&show.provider_id)
-            .unwrap()
-            .map(|seasons| (show, seasons))
-            .map_err(WebError::from)
-    });
-
-    let provider = state.provider.clone();
-    let load_eps = load_seasons.and_then(move |(mut show, seasons)| {
-        let mut fut: Box<Future<Item = Vec<TVShowSeason>, Error = WebError>> =
-            Box::new(future::ok(vec![]));
-
-        for mut season in seasons.into_iter() {
-            let provider = provider.clone();
-
-            fut = Box::new(fut.and_then(move |mut seasons| {
-                provider
-                    .get_season_episodes("", &season.provider_id)
-                    .unwrap()
-                    .map(move |episodes| {
-                        season.episodes = Some(episodes);
-                        seasons.push(season);
-                        seasons
-                    })
-                    .map_err(WebError::from)
-            }));
+    // Checked before the show is loaded, since a missing profile would only
+    // fail the insert at the end.
+    let check_quality_profile: Box<Future<Item = (), Error = WebError>> =
+        match form.quality_profile_id {
+            Some(id) => Box::new(get_quality_profile(id, state.db_pool.clone()).map(|_| ())),
+            None => Box::new(future::ok(())),
+        };
+    let load_root_folder: Box<Future<Item = Option<RootFolder>, Error = WebError>> =
+        match form.root_folder_id {
+            Some(id) => Box::new(get_root_folder(id, state.db_pool.clone()).map(Some)),
+            None => Box::new(future::ok(None)),
+        };
+
+    let provider = form.provider.unwrap_or(TVMetadataProvider::TVMaze);
+    let external_id = external_id(&form.imdb, form.thetvdb, form.tvrage);
+    let id = form.id.clone();
+    let kind = provider;
+    let added_pool = state.db_pool.clone();
+    let load_show = future::result(state.metadata_providers.get(provider)).and_then(
+        move |provider| {
+            find_provider_id(&*provider, id, external_id)
+                .and_then(move |id| {
+                    // Checked before the show is loaded for nothing. The
+                    // insert still fails if the show is added in between.
+                    get_show_ids_by_provider_ids(kind, vec![id.clone()], added_pool).and_then(
+                        move |ids| match ids.get(&id) {
+                            Some(&existing) => Err(WebError::ShowExists(existing)),
+                            None => Ok(id),
+                        },
+                    )
+                })
+                .and_then(move |id| {
+                    load_full_show(provider.clone(), &id).map(move |show| (provider, show))
+                })
+        },
+    );
+    let quality_profile_id = form.quality_profile_id;
+    let show_folder_format = state.config.show_folder_format.clone();
+    let load_eps = check_quality_profile
+        .and_then(move |_| load_root_folder.join(load_show))
+        .map(move |(root_folder, (provider, mut show))| {
+            if let Some(id) = quality_profile_id {
+                show.quality_profile_id = id;
+            }
+            if let Some(root_folder) = root_folder {
+                // Local shows already have a folder of their own.
+                if show.path.is_none() {
+                    show.path = Some(root_folder.show_path(&show_folder_format, &show));
+                }
+                show.root_folder_id = Some(root_folder.id);
+            }
+            (provider, show)
+        });
+
+    let pool = state.db_pool.clone();
+    let config = Rc::new(state.config.clone());
+
+    Box::new(load_eps.and_then(move |(provider, show)| {
+        insert_show(&show, pool.clone())
+            .and_then(move |show| {
+                let show_id = show.id;
+                try_refresh_show_credits(show_id, &show.provider_id, provider, pool.clone())
+                    .and_then(move |_| try_update_show_metadata(show_id, false, config, pool))
+                    .map(move |_| show)
+            })
+            .and_then(|show| Ok(HttpResponse::Ok().json(show)))
+    }))
+}
+
+/// Lists a show's cast in the order they are billed, with the characters they
+/// play.
+pub fn shows_cast(
+    (state, req): (State<AppState>, Path<GetShow>),
+) -> Box<Future<Item = HttpResponse, Error = WebError>> {
+    let pool = state.db_pool.clone();
+
+    get_entire_show(req.id, state.db_pool.clone())
+        .and_then(|show| show)
+        .and_then(move |show| get_show_cast(show.id, pool))
+        .and_then(|cast| Ok(HttpResponse::Ok().json(cast)))
+        .responder()
+}
+
+pub fn shows_crew(
+    (state, req): (State<AppState>, Path<GetShow>),
+) -> Box<Future<Item = HttpResponse, Error = WebError>> {
+    let pool = state.db_pool.clone();
+
+    get_entire_show(req.id, state.db_pool.clone())
+        .and_then(|show| show)
+        .and_then(move |show| get_show_crew(show.id, pool))
+        .and_then(|crew| Ok(HttpResponse::Ok().json(crew)))
+        .responder()
+}
+
+/// Finds the show in the library with an id in another database, to check
+/// whether it has been added already.
+pub fn shows_lookup(
+    (state, query): (State<AppState>, Query<LookupShow>),
+) -> Box<Future<Item = HttpResponse, Error = WebError>> {
+    let id = match external_id(&query.imdb, query.thetvdb, query.tvrage) {
+        Some(id) => id,
+        None => {
+            return Box::new(future::err(WebError::InvalidRequest(
+                "An imdb, thetvdb or tvrage id is required".to_string(),
+            )))
         }
+    };
+
+    get_show_by_external_id(&id, state.db_pool.clone())
+        .and_then(move |show| show.ok_or(WebError::ExternalIdNotFound(id)))
+        .and_then(|show| Ok(HttpResponse::Ok().json(show)))
+        .responder()
+}
+
+/// Reloads a show from its provider and rewrites its metadata files.
+pub fn shows_refresh(
+    (state, req): (State<AppState>, Path<GetShow>),
+) -> Box<Future<Item = HttpResponse, Error = WebError>> {
+    let providers = state.metadata_providers.clone();
+    let pool = state.db_pool.clone();
+    let config = Rc::new(state.config.clone());
 
-        fut.map(move |seasons| {
-            show.seasons = Some(seasons);
-            show
+    get_entire_show(req.id, state.db_pool.clone())
+        .and_then(|show| show)
+        .and_then(move |show| {
+            let refresh_pool = pool.clone();
+            future::result(providers.get(show.provider))
+                .and_then(move |provider| refresh_show(show, provider, refresh_pool))
+                .and_then(move |show| {
+                    try_update_show_metadata(show.id, true, config, pool).map(move |_| show)
+                })
         })
-    });
+        .and_then(|show| Ok(HttpResponse::Ok().json(show)))
+        .responder()
+}
 
+pub fn shows_aliases(
+    (state, req): (State<AppState>, Path<GetShow>),
+) -> Box<Future<Item = HttpResponse, Error = WebError>> {
     let pool = state.db_pool.clone();
 
-    Box::new(load_eps.and_then(move |show| {
-        insert_show(&show, pool).and_then(|show| Ok(HttpResponse::Ok().json(show)))
-    }))
+    get_entire_show(req.id, state.db_pool.clone())
+        .and_then(|show| show)
+        .and_then(move |show| get_show_aliases(show.id, pool))
+        .and_then(|aliases| Ok(HttpResponse::Ok().json(aliases)))
+        .responder()
+}
+
+/// Adds an alias of the user's own, which releases and files may be named
+/// after.
+pub fn shows_aliases_add(
+    (state, req, form): (State<AppState>, Path<GetShow>, Form<AddAlias>),
+) -> Box<Future<Item = HttpResponse, Error = WebError>> {
+    let name = form.name.trim().to_string();
+    if name.is_empty() {
+        return Box::new(future::err(WebError::InvalidRequest(
+            "An alias needs a name".to_string(),
+        )));
+    }
+
+    let pool = state.db_pool.clone();
+
+    get_entire_show(req.id, state.db_pool.clone())
+        .and_then(|show| show)
+        .and_then(move |show| insert_show_alias(show.id, &name, pool))
+        .and_then(|alias| Ok(HttpResponse::Ok().json(alias)))
+        .responder()
+}
+
+pub fn shows_aliases_delete(
+    (state, req): (State<AppState>, Path<GetAlias>),
+) -> Box<Future<Item = HttpResponse, Error = WebError>> {
+    delete_show_alias(req.id, req.alias_id, state.db_pool.clone())
+        .and_then(|_| Ok(HttpResponse::NoContent().finish()))
+        .responder()
 }
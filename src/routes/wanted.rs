@@ -0,0 +1,42 @@
+use db::wanted::*;
+use error::WebError;
+use resources::page::{Paging, SortDirection};
+use resources::wanted::WantedSortKey;
+use AppState;
+
+use actix_web::{AsyncResponder, HttpResponse, Query, State};
+use futures::Future;
+
+#[derive(Deserialize)]
+pub struct WantedQuery {
+    page: Option<i64>,
+    page_size: Option<i64>,
+    sort_key: Option<WantedSortKey>,
+    sort_dir: Option<SortDirection>,
+}
+
+impl WantedQuery {
+    fn paging(&self) -> Paging {
+        Paging::new(self.page, self.page_size, self.sort_dir)
+    }
+
+    fn sort_key(&self) -> WantedSortKey {
+        self.sort_key.unwrap_or(WantedSortKey::Airdate)
+    }
+}
+
+pub fn wanted_missing(
+    (state, query): (State<AppState>, Query<WantedQuery>),
+) -> Box<Future<Item = HttpResponse, Error = WebError>> {
+    get_missing_episodes(query.paging(), query.sort_key(), state.db_pool.clone())
+        .and_then(|page| Ok(HttpResponse::Ok().json(page)))
+        .responder()
+}
+
+pub fn wanted_cutoff(
+    (state, query): (State<AppState>, Query<WantedQuery>),
+) -> Box<Future<Item = HttpResponse, Error = WebError>> {
+    get_cutoff_unmet_episodes(query.paging(), query.sort_key(), state.db_pool.clone())
+        .and_then(|page| Ok(HttpResponse::Ok().json(page)))
+        .responder()
+}